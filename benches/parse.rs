@@ -0,0 +1,43 @@
+//! Time parsing and validating an array of 10k entries, run with `cargo bench --bench parse`.
+
+use std::hint::black_box;
+use std::time::Instant;
+use tsm_language_server::parser::LspParser;
+use tsm_language_server::{analyze, AnalysisConfig};
+
+const ENTRIES: usize = 10_000;
+const ITERATIONS: u32 = 20;
+
+fn bench(name: &str, mut f: impl FnMut()) {
+    f();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let elapsed = start.elapsed() / ITERATIONS;
+    println!("{:<12} {:>10.2?} per iteration", name, elapsed);
+}
+
+fn main() {
+    let names: Vec<String> = (0..ENTRIES).map(|i| format!("folder-{}", i)).collect();
+    let entries: Vec<String> = names
+        .iter()
+        .map(|name| format!("  \"{}\",", name))
+        .collect();
+    let source_code = format!("export const folders = [\n{}\n];\n", entries.join("\n"));
+
+    bench("parse_code", || {
+        black_box(LspParser::parse_code(black_box(&source_code), "folders"));
+    });
+
+    let config = AnalysisConfig {
+        varname: "folders".into(),
+        folders: names[..ENTRIES / 2].to_vec(),
+        byte_exact: false,
+        matcher: Default::default(),
+        max_suggestions: 5,
+    };
+    bench("analyze", || {
+        black_box(analyze(black_box(&source_code), &config));
+    });
+}
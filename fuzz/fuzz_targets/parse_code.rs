@@ -0,0 +1,23 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tower_lsp::lsp_types;
+use tsm_language_server::analysis::Span;
+use tsm_language_server::diagnostics::MyRange;
+use tsm_language_server::parser::LspParser;
+
+// Arbitrary source code and variable names must never panic, neither while parsing nor while
+// mapping the captured ranges to positions.
+fuzz_target!(|input: (&str, &str)| {
+    let (source_code, varname) = input;
+
+    for entry in LspParser::parse_code(source_code, varname)
+        .into_iter()
+        .chain(LspParser::parse_identifiers(source_code, varname))
+    {
+        let span = Span::from(entry.range);
+        let range: lsp_types::Range = MyRange(entry.range).into();
+        assert!(span.start.line <= span.end.line);
+        assert!(range.start.line <= range.end.line);
+    }
+});
@@ -0,0 +1,146 @@
+use crate::index::SuggestionIndex;
+#[cfg(feature = "server")]
+use crate::indexer;
+use crate::matcher::Matcher;
+use crate::parser::{LspParser, PositionalText};
+use serde::{Deserialize, Serialize};
+
+/// Zero-based line and byte column within the analyzed source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl From<tree_sitter::Range> for Span {
+    fn from(range: tree_sitter::Range) -> Self {
+        Span {
+            start: Position {
+                line: range.start_point.row,
+                column: range.start_point.column,
+            },
+            end: Position {
+                line: range.end_point.row,
+                column: range.end_point.column,
+            },
+        }
+    }
+}
+
+/// A single entry of a tracked array.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub text: String,
+    /// Span of the string literal including its quotes.
+    pub span: Span,
+    pub valid: bool,
+    /// Best matching folders for invalid entries, best first.
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisResult {
+    pub entries: Vec<Entry>,
+}
+
+impl AnalysisResult {
+    pub fn invalid_entries(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(|entry| !entry.valid)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.entries.iter().all(|entry| entry.valid)
+    }
+}
+
+/// Settings for `analyze`, independent of any language server or filesystem state.
+#[derive(Clone, Debug)]
+pub struct AnalysisConfig {
+    pub varname: String,
+    /// Names entries are validated against.
+    pub folders: Vec<String>,
+    pub byte_exact: bool,
+    pub matcher: Matcher,
+    pub max_suggestions: usize,
+}
+
+impl AnalysisConfig {
+    /// Configuration validating `varname` against the folders currently in `dir`.
+    #[cfg(feature = "server")]
+    pub fn from_dir(varname: &str, dir: &str) -> Self {
+        Self {
+            varname: varname.into(),
+            folders: indexer::get_files(dir),
+            byte_exact: false,
+            matcher: Matcher::default(),
+            max_suggestions: 5,
+        }
+    }
+}
+
+/// Find all entries of the tracked arrays in `source` and validate them against the configured folders.
+pub fn analyze(source: &str, config: &AnalysisConfig) -> AnalysisResult {
+    let index = SuggestionIndex::build(config.folders.iter().cloned(), config.byte_exact);
+
+    let entries = LspParser::parse_code(source, &config.varname)
+        .into_iter()
+        .map(|PositionalText { text, range }| {
+            let valid = index.contains(text);
+            let suggestions = if valid {
+                vec![]
+            } else {
+                config
+                    .matcher
+                    .best_matches(text, &index.candidates(text), config.max_suggestions)
+            };
+
+            Entry {
+                text: text.to_string(),
+                span: range.into(),
+                valid,
+                suggestions,
+            }
+        })
+        .collect();
+
+    AnalysisResult { entries }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze() {
+        let config = AnalysisConfig {
+            varname: "folders".into(),
+            folders: vec!["assets".into(), "fonts".into()],
+            byte_exact: false,
+            matcher: Matcher::default(),
+            max_suggestions: 5,
+        };
+        let source_code = r#"
+             export const folders = ["assets", "asets"];
+         "#;
+
+        let result = analyze(source_code, &config);
+        assert!(!result.is_valid());
+        assert_eq!(2, result.entries.len());
+        assert!(result.entries[0].valid);
+
+        let invalid: Vec<&Entry> = result.invalid_entries().collect();
+        assert_eq!(1, invalid.len());
+        assert_eq!("asets", invalid[0].text);
+        assert_eq!(vec!["assets".to_string()], invalid[0].suggestions);
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!("asets", json["entries"][1]["text"]);
+        assert_eq!(1, json["entries"][1]["span"]["start"]["line"]);
+    }
+}
@@ -1,114 +1,2102 @@
+use crate::capabilities::{self, ClientSupport, Feature};
+use crate::cli::CliArgs;
+use crate::commands;
+use crate::config::{Config, ItemKind, Labels, Override, Setting, Source, CONFIG_FILE};
+use crate::diagnostics::{DiagnosticEngine, MyRange};
+use crate::documents::DocumentStore;
+use crate::edits;
+use crate::grammar;
+use crate::health::{Health, State};
+use crate::i18n::{Locale, Message};
+use crate::index::SuggestionIndex;
+use crate::indexer::{self, Indexer, IndexerConfig};
+use crate::language;
+use crate::matcher::{self, Matcher};
+use crate::metrics;
 use crate::parser::LspParser;
-use crate::CliArgs;
-use fuzzy_matcher::skim::SkimMatcherV2;
-use fuzzy_matcher::FuzzyMatcher;
-use std::collections::HashMap;
+use crate::paths;
+use crate::process;
+use crate::project::{Array, Entry, Project};
+use crate::provider::Provider;
+use crate::rules::{self, FixContext};
+use crate::semantic;
+use crate::validator::{self, Validator};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
-use std::{env, fs};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tokio::sync::mpsc;
 use tower_lsp::jsonrpc::Result;
-use tower_lsp::{lsp_types, Client};
+use tower_lsp::Client;
 use tower_lsp::{lsp_types::*, LanguageServer};
 
-pub struct MyRange(pub tree_sitter::Range);
+/// Maximum number of completion items returned at once, the list is marked incomplete beyond that.
+const MAX_COMPLETIONS: usize = 1000;
 
-impl From<MyRange> for lsp_types::Range {
-    fn from(value: MyRange) -> Self {
-        lsp_types::Range {
-            start: {
-                Position {
-                    line: value.0.start_point.row as u32,
-                    character: value.0.start_point.column as u32,
-                }
-            },
-            end: {
-                Position {
-                    line: value.0.end_point.row as u32,
-                    character: value.0.end_point.column as u32,
-                }
-            },
+/// Maximum number of folder contents listed in the tooltip of an inlay hint.
+const MAX_TOOLTIP_NAMES: usize = 10;
+
+/// Maximum number of folder entries returned by a `tsm/previewFolder` request at once.
+const PREVIEW_PAGE_SIZE: usize = 100;
+
+/// Maximum number of memoized quick fix suggestions, the memo is cleared beyond that.
+const MAX_MEMOIZED_MATCHES: usize = 1000;
+
+/// Problems of the validated documents, sent as `tsm/summary` notification after each
+/// diagnostics run so editor extensions can show a workspace-level badge.
+pub enum Summary {}
+
+impl notification::Notification for Summary {
+    type Params = SummaryParams;
+    const METHOD: &'static str = "tsm/summary";
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SummaryParams {
+    /// Documents with diagnostics, opened or found by the workspace scan
+    pub files: usize,
+    pub invalid_entries: usize,
+    pub duplicates: usize,
+}
+
+impl SummaryParams {
+    /// The problems of a single document with `diagnostics`.
+    fn of(diagnostics: &[Diagnostic]) -> Self {
+        let count = |name: &str| {
+            diagnostics
+                .iter()
+                .filter(|diagnostic| rules::of(diagnostic).is_some_and(|rule| rule.name() == name))
+                .count()
+        };
+        Self {
+            files: 1,
+            invalid_entries: count("invalid-folder") + count("case-mismatch"),
+            duplicates: count("duplicate-entry"),
         }
     }
 }
 
+/// Parameters of the `tsm/previewFolder` request.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewFolderParams {
+    /// Name of the folder as listed in the array
+    pub name: String,
+    /// Document listing the folder, its inline configuration may set the suggestions directory
+    pub uri: Option<Url>,
+    /// Number of entries to skip, the `nextOffset` of the previous page
+    #[serde(default)]
+    pub offset: usize,
+    /// Maximum number of entries of the page, at most 100
+    pub limit: Option<usize>,
+}
+
+/// A page of the contents of a folder.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewFolderResult {
+    pub entries: Vec<FolderEntry>,
+    /// Number of entries of the folder on all pages
+    pub total: usize,
+    /// Offset of the next page, `None` on the last one
+    pub next_offset: Option<usize>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderEntry {
+    pub name: String,
+    pub is_directory: bool,
+    /// Size in bytes
+    pub size: u64,
+    /// Time of the last modification in milliseconds since the Unix epoch
+    pub modified: Option<u64>,
+}
+
+/// Array a document validates and where its names come from, the command line arguments
+/// overridden by the inline configuration of the document.
+#[derive(Clone, Debug, PartialEq)]
+struct DocumentScope {
+    varname: String,
+    provider: Provider,
+}
+
+/// Best matches with their scores keyed by the provider location, the input and their number,
+/// computed from the index of the indexer generation.
+#[derive(Default)]
+struct MatchesMemo {
+    generation: u64,
+    matches: HashMap<(String, String, usize), Vec<(String, f64)>>,
+}
+
+type SemanticTokensCache = Arc<RwLock<HashMap<Url, (String, Vec<SemanticToken>)>>>;
+
+type DiagnosticsCache = Arc<RwLock<HashMap<Url, (String, Vec<Diagnostic>)>>>;
+
 pub struct Backend {
     client: Client,
-    documents: Arc<RwLock<HashMap<Url, String>>>, // To store opened documents
+    documents: Arc<DocumentStore>,
     args: CliArgs,
     push_diagnostics: Arc<RwLock<bool>>,
+    /// The client pulls diagnostics of opened documents instead of having them pushed
+    pull_diagnostics: Arc<RwLock<bool>>,
+    client_capabilities: Arc<RwLock<ClientCapabilities>>,
+    features: Arc<RwLock<HashSet<Feature>>>,
+    locale: Arc<RwLock<Locale>>,
+    indexer: Indexer,
+    workspace_roots: Arc<RwLock<Vec<PathBuf>>>,
+    config: Arc<RwLock<Config>>,
+    /// Keys of the configuration set by the client, in `initializationOptions` and
+    /// `workspace/didChangeConfiguration`, overriding the configuration file
+    client_config: Arc<RwLock<BTreeMap<Source, toml::Table>>>,
+    /// The resolved configuration with the source of every setting
+    settings: Arc<RwLock<Vec<Setting>>>,
+    validators: Arc<RwLock<Vec<Arc<dyn Validator>>>>,
+    /// Semantic tokens last sent for each document with their result id, the base of deltas
+    semantic_tokens: SemanticTokensCache,
+    next_result_id: Arc<AtomicU64>,
+    /// Incremented whenever the diagnostics of unchanged documents may change, part of the
+    /// result ids of pulled diagnostics
+    diagnostics_generation: Arc<AtomicU64>,
+    /// Diagnostics of opened documents with their result ids, shared by the notifications and
+    /// requests following an edit
+    diagnostics_cache: DiagnosticsCache,
+    /// Clients request code actions over and over while the cursor is on a diagnostic
+    matches_memo: Arc<RwLock<MatchesMemo>>,
+    /// The client requested a shutdown, which makes the following exit a clean one
+    shutdown_requested: Arc<AtomicBool>,
+    /// Problems of the documents diagnostics were last published or pulled for
+    problems: Arc<RwLock<HashMap<Url, SummaryParams>>>,
+    /// The suggestions directory of the workspace was missing when last checked
+    missing_suggestionsdir: Arc<AtomicBool>,
+    /// Reported by `tsm/status`, transitions are logged
+    health: Arc<RwLock<Health>>,
+    /// Workspace files declaring tracked arrays, scanned on the first workspace-wide request
+    project: Arc<Project>,
 }
 
 impl Backend {
     pub fn new(client: Client, args: CliArgs) -> Self {
+        let indexer = Indexer::spawn(IndexerConfig {
+            provider: Provider::from_args(&args),
+            byte_exact: args.byte_exact,
+            extensions: args.extensions.clone(),
+            ttl: Duration::from_secs(args.suggestions_ttl),
+            cache_dir: args.index_cache.clone(),
+        });
         Self {
             client,
-            documents: Arc::new(RwLock::new(HashMap::new())),
+            documents: Arc::new(DocumentStore::default()),
             args,
             push_diagnostics: Arc::new(RwLock::new(false)),
+            pull_diagnostics: Arc::new(RwLock::new(false)),
+            client_capabilities: Arc::new(RwLock::new(ClientCapabilities::default())),
+            features: Arc::new(RwLock::new(Feature::ALL.into_iter().collect())),
+            locale: Arc::new(RwLock::new(Locale::default())),
+            indexer,
+            workspace_roots: Arc::new(RwLock::new(vec![])),
+            config: Arc::new(RwLock::new(Config::default())),
+            client_config: Arc::new(RwLock::new(BTreeMap::new())),
+            settings: Arc::new(RwLock::new(vec![])),
+            validators: Arc::new(RwLock::new(vec![])),
+            semantic_tokens: Arc::new(RwLock::new(HashMap::new())),
+            next_result_id: Arc::new(AtomicU64::new(1)),
+            diagnostics_generation: Arc::new(AtomicU64::new(0)),
+            diagnostics_cache: Arc::new(RwLock::new(HashMap::new())),
+            matches_memo: Arc::new(RwLock::new(MatchesMemo::default())),
+            shutdown_requested: Arc::new(AtomicBool::new(false)),
+            problems: Arc::new(RwLock::new(HashMap::new())),
+            missing_suggestionsdir: Arc::new(AtomicBool::new(false)),
+            health: Arc::new(RwLock::new(Health::default())),
+            project: Arc::new(Project::default()),
+        }
+    }
+
+    /// Handler of the `tsm/status` request, the health of the server, the location of the
+    /// suggestions and the resolved configuration with the source of every setting.
+    pub async fn status(&self) -> Result<serde_json::Value> {
+        let settings: Vec<serde_json::Value> = self
+            .settings
+            .read()
+            .map(|settings| {
+                settings
+                    .iter()
+                    .map(|setting| {
+                        serde_json::json!({
+                            "name": setting.name,
+                            "value": setting.value,
+                            "source": setting.source.name(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let health = self
+            .health
+            .read()
+            .map(|health| health.clone())
+            .unwrap_or_else(|err| err.into_inner().clone());
+        Ok(serde_json::json!({
+            "health": {
+                "state": health.state.name(),
+                "reason": health.reason,
+            },
+            "suggestions": self.provider().location(),
+            "settings": settings,
+        }))
+    }
+
+    /// Handler of the `tsm/findAllInvalid` request and the `tsm.findAllInvalid` command, the
+    /// locations of the invalid entries of all files in the workspace, open documents with their
+    /// unsaved content.
+    pub async fn find_all_invalid(&self) -> Result<Vec<Location>> {
+        self.discover_project().await;
+        let mut locations = vec![];
+        for (uri, source_code) in self.project.files() {
+            let scopes = self.document_scopes(&uri, &source_code);
+            self.load_document_indexes(&scopes).await;
+            locations.extend(
+                self.perform_all_diagnostics(&scopes, &source_code)
+                    .into_iter()
+                    .filter(|diagnostic| {
+                        rules::of(diagnostic).is_some_and(|rule| {
+                            matches!(rule.name(), "invalid-folder" | "case-mismatch")
+                        })
+                    })
+                    .map(|diagnostic| Location::new(uri.clone(), diagnostic.range)),
+            );
+        }
+        Ok(locations)
+    }
+
+    /// Handler of the `tsm/previewFolder` request, a page of the contents of a folder sorted by
+    /// name, so clients can preview it without access to the filesystem.
+    pub async fn preview_folder(&self, params: PreviewFolderParams) -> Result<PreviewFolderResult> {
+        let path = self.document_folder_path(params.uri.as_ref(), &params.name);
+        let error = |err: String| {
+            tower_lsp::jsonrpc::Error::invalid_params(format!("{}: {}", paths::display(&path), err))
+        };
+        if !self.is_allowed_path(&path) {
+            return Err(error("outside of the workspace".into()));
+        }
+
+        let mut entries: Vec<FolderEntry> = fs::read_dir(&path)
+            .map_err(|err| error(err.to_string()))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| {
+                let metadata = entry.metadata().ok();
+                FolderEntry {
+                    name: entry.file_name().to_string_lossy().into_owned(),
+                    is_directory: metadata.as_ref().is_some_and(|m| m.is_dir()),
+                    size: metadata.as_ref().map_or(0, |m| m.len()),
+                    modified: metadata
+                        .and_then(|m| m.modified().ok())
+                        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                        .map(|modified| modified.as_millis() as u64),
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let total = entries.len();
+        let offset = params.offset.min(total);
+        let limit = params
+            .limit
+            .unwrap_or(PREVIEW_PAGE_SIZE)
+            .clamp(1, PREVIEW_PAGE_SIZE);
+        let entries: Vec<FolderEntry> = entries.into_iter().skip(offset).take(limit).collect();
+        let next_offset = Some(offset + entries.len()).filter(|next| *next < total);
+        Ok(PreviewFolderResult {
+            entries,
+            total,
+            next_offset,
+        })
+    }
+
+    /// Set once the client requested a shutdown. The process should exit with 0 after the exit
+    /// notification only then, and with 1 otherwise.
+    pub fn shutdown_requested(&self) -> Arc<AtomicBool> {
+        self.shutdown_requested.clone()
+    }
+
+    /// The client, to report failures outside of the handlers.
+    pub fn client(&self) -> Client {
+        self.client.clone()
+    }
+
+    /// Whether a document should be stored and validated, based on its language id, its
+    /// `language` detected by [`language::detect`] or extension. Documents of an unknown language
+    /// are validated if they declare the tracked array.
+    ///
+    /// The extension is taken from the URI path, so this works for all schemes.
+    fn is_tracked_document(
+        &self,
+        uri: &Url,
+        language_id: &str,
+        language: Option<&str>,
+        source_code: &str,
+    ) -> bool {
+        self.args
+            .languages
+            .iter()
+            .any(|l| l == language_id || Some(l.as_str()) == language)
+            || indexer::has_extension(Path::new(uri.path()), &self.args.extensions)
+            || self.args.is_package_json(Path::new(uri.path()))
+            || language.is_some_and(|language| grammar::get(language).is_some())
+            || (language.is_none()
+                && !LspParser::parse_arrays(source_code, &self.args.varname).is_empty())
+    }
+
+    /// Selector matching all documents this server is interested in.
+    fn document_selector(&self) -> DocumentSelector {
+        let by_language = self.args.languages.iter().map(|language| DocumentFilter {
+            language: Some(language.clone()),
+            scheme: None,
+            pattern: None,
+        });
+        let by_extension = self.args.extensions.iter().map(|ext| DocumentFilter {
+            language: None,
+            scheme: None,
+            pattern: Some(format!("**/*.{}", ext)),
+        });
+        let package_json = self.args.package_json.as_ref().map(|_| DocumentFilter {
+            language: None,
+            scheme: None,
+            pattern: Some("**/package.json".into()),
+        });
+
+        by_language
+            .chain(by_extension)
+            .chain(package_json)
+            .collect()
+    }
+
+    /// Register the text document synchronization notifications restricted to the document selector.
+    async fn register_document_sync(&self) {
+        let selector = self.document_selector();
+        let registration_options = serde_json::to_value(TextDocumentRegistrationOptions {
+            document_selector: Some(selector.clone()),
+        })
+        .ok();
+
+        let registrations = vec![
+            Registration {
+                id: "tsm-did-open".into(),
+                method: "textDocument/didOpen".into(),
+                register_options: registration_options.clone(),
+            },
+            Registration {
+                id: "tsm-did-change".into(),
+                method: "textDocument/didChange".into(),
+                register_options: Some(serde_json::json!({
+                    "documentSelector": selector,
+                    "syncKind": TextDocumentSyncKind::FULL,
+                })),
+            },
+            Registration {
+                id: "tsm-did-close".into(),
+                method: "textDocument/didClose".into(),
+                register_options: registration_options,
+            },
+        ];
+
+        if let Err(err) = self.client.register_capability(registrations).await {
+            self.client
+                .log_message(
+                    MessageType::ERROR,
+                    format!("Failed to register document synchronization: {}", err),
+                )
+                .await;
+        }
+    }
+
+    fn supports_dynamic_sync(capabilities: &ClientCapabilities) -> bool {
+        capabilities
+            .text_document
+            .as_ref()
+            .and_then(|td| td.synchronization.as_ref())
+            .and_then(|sync| sync.dynamic_registration)
+            .unwrap_or(false)
+    }
+
+    fn locale(&self) -> Locale {
+        self.locale.read().map(|locale| *locale).unwrap_or_default()
+    }
+
+    fn is_enabled(&self, feature: Feature) -> bool {
+        self.features
+            .read()
+            .map(|features| features.contains(&feature))
+            .unwrap_or(false)
+    }
+
+    fn supports_dynamic_registration(&self, feature: Feature) -> bool {
+        self.client_capabilities
+            .read()
+            .map(|capabilities| feature.supports_dynamic_registration(&capabilities))
+            .unwrap_or(false)
+    }
+
+    /// Dynamically register or unregister features whose enabled state differs from `enabled`.
+    async fn update_features(&self, enabled: &HashSet<Feature>) {
+        let (added, removed): (Vec<Feature>, Vec<Feature>) = {
+            let mut features = match self.features.write() {
+                Ok(features) => features,
+                Err(_) => return,
+            };
+            let added = enabled.difference(&features).copied().collect();
+            let removed = features.difference(enabled).copied().collect();
+            *features = enabled.clone();
+            (added, removed)
+        };
+
+        let selector = self.document_selector();
+        let watch_globs = self.watch_globs();
+
+        let registrations: Vec<Registration> = added
+            .iter()
+            .filter(|feature| self.supports_dynamic_registration(**feature))
+            .map(|feature| feature.registration(&selector, &watch_globs))
+            .collect();
+        if !registrations.is_empty() {
+            if let Err(err) = self.client.register_capability(registrations).await {
+                self.client
+                    .log_message(
+                        MessageType::ERROR,
+                        format!("Failed to register capabilities: {}", err),
+                    )
+                    .await;
+            }
+        }
+
+        let unregistrations: Vec<Unregistration> = removed
+            .iter()
+            .filter(|feature| self.supports_dynamic_registration(**feature))
+            .map(|feature| feature.unregistration())
+            .collect();
+        if !unregistrations.is_empty() {
+            if let Err(err) = self.client.unregister_capability(unregistrations).await {
+                self.client
+                    .log_message(
+                        MessageType::ERROR,
+                        format!("Failed to unregister capabilities: {}", err),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    fn client_support(&self) -> ClientSupport {
+        self.client_capabilities
+            .read()
+            .map(|capabilities| ClientSupport::new(&capabilities))
+            .unwrap_or_default()
+    }
+
+    fn supports_diagnostic_refresh(&self) -> bool {
+        self.client_capabilities
+            .read()
+            .map(|capabilities| {
+                capabilities
+                    .workspace
+                    .as_ref()
+                    .and_then(|workspace| workspace.diagnostic.as_ref())
+                    .and_then(|diagnostic| diagnostic.refresh_support)
+                    .unwrap_or(false)
+            })
+            .unwrap_or(false)
+    }
+
+    fn supports_work_done_progress(&self) -> bool {
+        self.client_capabilities
+            .read()
+            .map(|capabilities| {
+                capabilities
+                    .window
+                    .as_ref()
+                    .and_then(|window| window.work_done_progress)
+                    .unwrap_or(false)
+            })
+            .unwrap_or(false)
+    }
+
+    async fn report_progress(&self, token: &NumberOrString, progress: WorkDoneProgress) {
+        self.client
+            .send_notification::<notification::Progress>(ProgressParams {
+                token: token.clone(),
+                value: ProgressParamsValue::WorkDone(progress),
+            })
+            .await;
+    }
+
+    /// Rebuild the suggestion index, reporting `WorkDoneProgress` when the client supports it.
+    async fn rebuild_index(&self) -> usize {
+        let token = NumberOrString::String("tsm-indexing".into());
+        let report = self.supports_work_done_progress()
+            && self
+                .client
+                .send_request::<request::WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                    token: token.clone(),
+                })
+                .await
+                .is_ok();
+        self.set_health(State::Indexing, None).await;
+
+        if report {
+            self.report_progress(
+                &token,
+                WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                    title: "Indexing folders".into(),
+                    cancellable: Some(false),
+                    message: Some(self.provider().location()),
+                    percentage: Some(0),
+                }),
+            )
+            .await;
+        }
+
+        let (progress, mut updates) = mpsc::unbounded_channel();
+        let rebuild = self.indexer.rebuild(progress);
+        tokio::pin!(rebuild);
+
+        let rebuilt = loop {
+            tokio::select! {
+                rebuilt = &mut rebuild => break rebuilt,
+                Some(update) = updates.recv() => {
+                    if report {
+                        self.report_progress(
+                            &token,
+                            WorkDoneProgress::Report(WorkDoneProgressReport {
+                                cancellable: Some(false),
+                                message: Some(update.path),
+                                percentage: Some(update.percentage),
+                            }),
+                        )
+                        .await;
+                    }
+                }
+            }
+        };
+
+        let (indexed, failure) = match rebuilt {
+            Ok(indexed) => (indexed, None),
+            Err(err) => {
+                let failure = format!("Failed to index folders: {}", err);
+                self.client
+                    .show_message(MessageType::WARNING, &failure)
+                    .await;
+                (0, Some(failure))
+            }
+        };
+
+        if report {
+            self.report_progress(
+                &token,
+                WorkDoneProgress::End(WorkDoneProgressEnd {
+                    message: Some(format!("Indexed {} folders", indexed)),
+                }),
+            )
+            .await;
+        }
+        self.check_health(failure).await;
+
+        indexed
+    }
+
+    /// Move the server to `state`, logging the transition.
+    async fn set_health(&self, state: State, reason: Option<String>) {
+        let transition = match self.health.write() {
+            Ok(mut health) => health.transition(state, reason),
+            Err(err) => err.into_inner().transition(state, reason),
+        };
+        if let Some(message) = transition {
+            self.client.log_message(MessageType::INFO, message).await;
+        }
+    }
+
+    /// Update the health after indexing, degraded by the `failure` of the provider or a missing
+    /// suggestions directory.
+    ///
+    /// Warn when the suggestions directory of the workspace went missing, instead of leaving
+    /// the user wondering why every entry is invalid. Only warns again after it existed again.
+    async fn check_health(&self, failure: Option<String>) {
+        let missing = Backend::missing_dir(&self.provider());
+        let reason = failure.or_else(|| {
+            missing
+                .as_ref()
+                .map(|dir| Message::MissingSuggestionsDir(dir).text(self.locale()))
+        });
+        let state = match reason {
+            Some(_) => State::Degraded,
+            None => State::Ready,
+        };
+        self.set_health(state, reason).await;
+
+        let was_missing = self
+            .missing_suggestionsdir
+            .swap(missing.is_some(), Ordering::Relaxed);
+        if let (Some(dir), false) = (missing, was_missing) {
+            self.client
+                .show_message(
+                    MessageType::WARNING,
+                    Message::MissingSuggestionsDir(&dir).text(self.locale()),
+                )
+                .await;
+        }
+    }
+
+    /// The directory of `provider`, if it is one which doesn't exist.
+    fn missing_dir(provider: &Provider) -> Option<String> {
+        match provider {
+            Provider::Directory { dir, .. } if !Path::new(dir).is_dir() => Some(dir.clone()),
+            _ => None,
+        }
+    }
+
+    /// Re-run diagnostics for all open documents, e.g. after the suggestions directory changed.
+    ///
+    /// Clients pulling diagnostics are asked to pull them again instead.
+    async fn republish_diagnostics(&self) {
+        self.diagnostics_generation.fetch_add(1, Ordering::Relaxed);
+        let pull_diagnostics = self
+            .pull_diagnostics
+            .read()
+            .map(|pull| *pull)
+            .unwrap_or(false);
+        if pull_diagnostics {
+            if self.supports_diagnostic_refresh() {
+                if let Err(err) = self.client.workspace_diagnostic_refresh().await {
+                    self.client
+                        .log_message(
+                            MessageType::ERROR,
+                            format!("Failed to refresh diagnostics: {}", err),
+                        )
+                        .await;
+                }
+            }
+            return;
+        }
+
+        let push_diagnostics = self.pushes_diagnostics();
+        if !push_diagnostics {
+            return;
+        }
+
+        for (uri, text, version) in self.documents.snapshot() {
+            let scopes = self.document_scopes(&uri, &text);
+            self.load_document_indexes(&scopes).await;
+            let (_, diagnostics) = self.document_diagnostics(&uri, &scopes, &text);
+            self.publish(uri, diagnostics, Some(version)).await;
+        }
+        self.send_summary().await;
+    }
+
+    /// Publish `diagnostics` of the document at `uri` and remember its problems for the summary.
+    async fn publish(&self, uri: Url, diagnostics: Vec<Diagnostic>, version: Option<i32>) {
+        self.record_problems(&uri, &diagnostics);
+        self.client
+            .publish_diagnostics(uri, diagnostics, version)
+            .await;
+    }
+
+    fn record_problems(&self, uri: &Url, diagnostics: &[Diagnostic]) {
+        if let Ok(mut problems) = self.problems.write() {
+            problems.insert(uri.clone(), SummaryParams::of(diagnostics));
+        }
+    }
+
+    /// Send the `tsm/summary` notification with the problems of all documents.
+    async fn send_summary(&self) {
+        let summary = match self.problems.read() {
+            Ok(problems) => problems
+                .values()
+                .fold(SummaryParams::default(), |total, problems| SummaryParams {
+                    files: total.files + problems.files,
+                    invalid_entries: total.invalid_entries + problems.invalid_entries,
+                    duplicates: total.duplicates + problems.duplicates,
+                }),
+            Err(_) => return,
+        };
+        self.client.send_notification::<Summary>(summary).await;
+    }
+
+    /// Content of the document at `uri`, read from disk if it isn't open, e.g. for requests of
+    /// workspace-wide features. Files outside of the workspace aren't read with
+    /// `--restrict-to-workspace`.
+    fn document_text(&self, uri: &Url) -> Option<Arc<str>> {
+        self.documents.text(uri).or_else(|| {
+            let path = paths::from_uri(uri).filter(|path| self.is_allowed_path(path))?;
+            fs::read_to_string(path).ok().map(Arc::from)
+        })
+    }
+
+    /// Whether diagnostics are published to the client rather than pulled by it.
+    fn pushes_diagnostics(&self) -> bool {
+        self.push_diagnostics
+            .read()
+            .map(|push| *push)
+            .unwrap_or_else(|err| *err.into_inner())
+    }
+
+    /// Publish diagnostics for every workspace file containing the tracked variable
+    /// which is not already opened in the editor.
+    async fn scan_workspace(&self) {
+        self.discover_project().await;
+        let mut scanned = 0;
+        for (uri, source_code) in self.project.files() {
+            if self.documents.contains(&uri) {
+                continue;
+            }
+
+            let scopes = self.document_scopes(&uri, &source_code);
+            self.load_document_indexes(&scopes).await;
+            let diagnostics = self.perform_all_diagnostics(&scopes, &source_code);
+            self.publish(uri, diagnostics, None).await;
+            scanned += 1;
+        }
+        self.send_summary().await;
+
+        self.client
+            .log_message(
+                MessageType::INFO,
+                format!("Workspace scan published diagnostics for {} files", scanned),
+            )
+            .await;
+    }
+
+    /// Fill the project with the workspace files containing the tracked variable unless it was
+    /// already, open documents with their unsaved content.
+    async fn discover_project(&self) {
+        if self.project.is_discovered() {
+            return;
+        }
+        let mut roots = self
+            .workspace_roots
+            .read()
+            .map(|roots| roots.clone())
+            .unwrap_or_default();
+        if roots.is_empty() {
+            roots.push(self.workspace_root());
+        }
+        let files = self
+            .indexer
+            .scan_workspace(roots, LspParser::needle(&self.args.varname).to_string())
+            .await;
+        // Marked before the files are added, so edits in the meantime update the project
+        self.project.set_discovered();
+        for (path, source_code) in files {
+            if let Ok(uri) = Url::from_file_path(&path) {
+                self.update_project(&uri, source_code.into());
+            }
+        }
+        // Opened documents replace the files on disk, even outside of the workspace or unsaved
+        for (uri, source_code, _) in self.documents.snapshot() {
+            self.update_project(&uri, source_code);
+        }
+    }
+
+    /// The entry at `position` of the document `uri` in the project.
+    async fn project_entry_at(&self, uri: &Url, position: Position) -> Option<Entry> {
+        self.discover_project().await;
+        self.project.entry_at(uri, position)
+    }
+
+    /// Parse the file `uri` for the project once the workspace was scanned, the next scan
+    /// includes it otherwise.
+    fn update_project(&self, uri: &Url, source_code: Arc<str>) {
+        if !self.project.is_discovered() {
+            return;
+        }
+        let arrays: Vec<Array> = self
+            .document_scopes(uri, &source_code)
+            .into_iter()
+            .map(|scope| Array {
+                location: scope.provider.location(),
+                varname: scope.varname,
+            })
+            .collect();
+        self.project.update(uri.clone(), source_code, &arrays);
+    }
+
+    fn is_valid_folder(&self, scope: &DocumentScope, name: &str) -> bool {
+        self.with_index(scope, |index| index.contains(name))
+    }
+
+    /// The `top_n` best matching folders for `user_input`, never suggesting denied names.
+    fn get_suggestions(
+        &self,
+        scope: &DocumentScope,
+        user_input: &str,
+        top_n: usize,
+    ) -> Vec<String> {
+        self.scored_suggestions(scope, user_input, top_n)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Like [`Backend::get_suggestions`], with the score of every name. Validators ranking the
+    /// suggestions decide their order, the matcher's scores otherwise.
+    fn scored_suggestions(
+        &self,
+        scope: &DocumentScope,
+        user_input: &str,
+        top_n: usize,
+    ) -> Vec<(String, f64)> {
+        let scored = self.memoized_matches(scope, user_input, top_n);
+
+        let validators = self
+            .validators
+            .read()
+            .map(|v| v.clone())
+            .unwrap_or_default();
+        if validators.is_empty() {
+            return scored;
+        }
+        let names = scored.iter().map(|(name, _)| name.clone()).collect();
+        validators
+            .iter()
+            .fold(names, |suggestions, validator| {
+                validator.rank(user_input, suggestions)
+            })
+            .into_iter()
+            .map(|name| {
+                // Names added by a validator weren't scored
+                let score = scored
+                    .iter()
+                    .find(|(scored, _)| *scored == name)
+                    .map_or(0.0, |(_, score)| *score);
+                (name, score)
+            })
+            .collect()
+    }
+
+    /// The first workspace root, or the working directory if the client didn't send any.
+    fn workspace_root(&self) -> PathBuf {
+        self.workspace_roots
+            .read()
+            .ok()
+            .and_then(|roots| roots.first().cloned())
+            .or_else(|| env::current_dir().ok())
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    /// Whether `path` is inside of a workspace root, always with `--restrict-to-workspace` off.
+    fn is_allowed_path(&self, path: &Path) -> bool {
+        if !self.args.restrict_to_workspace {
+            return true;
+        }
+        let roots = self
+            .workspace_roots
+            .read()
+            .map(|roots| roots.clone())
+            .unwrap_or_default();
+        if roots.is_empty() {
+            return paths::is_inside(path, &self.workspace_root());
+        }
+        roots.iter().any(|root| paths::is_inside(path, root))
+    }
+
+    /// The provider of the command line arguments with relative paths resolved against the
+    /// workspace root, as the working directory is wherever the editor spawned the server.
+    fn provider(&self) -> Provider {
+        self.configured_provider().resolve(&self.workspace_root())
+    }
+
+    /// The provider of the command line arguments and the configuration file, with relative
+    /// paths unresolved.
+    fn configured_provider(&self) -> Provider {
+        let config = self.config.read().map(|c| c.clone()).unwrap_or_default();
+        Provider::from_config(&self.args, &config)
+    }
+
+    /// Directory of the document at `uri`, `None` unless it's a file.
+    fn document_dir(uri: &Url) -> Option<PathBuf> {
+        paths::from_uri(uri).and_then(|path| path.parent().map(Path::to_path_buf))
+    }
+
+    /// Directory relative paths of the document at `uri` are resolved against, the directory of
+    /// the document with `--relative-to-document` and the workspace root otherwise.
+    fn document_base(&self, uri: &Url) -> PathBuf {
+        match Backend::document_dir(uri) {
+            Some(dir) if self.args.relative_to_document => dir,
+            _ => self.workspace_root(),
+        }
+    }
+
+    /// Settings of the document at `uri`, including the inline configuration of `source_code`.
+    fn document_scope(&self, uri: &Url, source_code: &str) -> DocumentScope {
+        let inline = LspParser::parse_inline_config(source_code);
+        // Relative to the document itself, like an import
+        let inline_provider = inline.suggestionsdir.map(|dir| {
+            Provider::Directory {
+                dir,
+                recursive: self.args.recursive,
+            }
+            .resolve(&Backend::document_dir(uri).unwrap_or_else(|| self.workspace_root()))
+        });
+        let matching = self.document_override(uri);
+        // Relative to the workspace root, like the configuration file
+        let override_provider = matching
+            .as_ref()
+            .and_then(|entry| entry.suggestionsdir.clone())
+            .map(|dir| self.workspace_directory(dir));
+        let varname = inline
+            .varname
+            .or_else(|| matching.and_then(|entry| entry.varname))
+            .unwrap_or_else(|| self.document_varname(uri));
+        let provider = match inline_provider
+            .or(override_provider)
+            .or_else(|| self.mapped_provider(&varname))
+        {
+            Some(Provider::Directory { dir, recursive })
+                if self.is_allowed_path(Path::new(&dir)) =>
+            {
+                Provider::Directory { dir, recursive }
+            }
+            _ => self.configured_provider().resolve(&self.document_base(uri)),
+        };
+        DocumentScope { varname, provider }
+    }
+
+    /// Name of the arrays validated in the document at `uri` by its path, or with the runtime-loaded
+    /// grammar of its language if the path doesn't tell, like for untitled documents.
+    fn document_varname(&self, uri: &Url) -> String {
+        let varname = self.args.varname_for(Path::new(uri.path()));
+        if varname != self.args.varname {
+            return varname;
+        }
+        match self
+            .documents
+            .language(uri)
+            .filter(|language| grammar::get(language).is_some())
+        {
+            Some(language) => format!("{}:{}", language, varname),
+            None => varname,
+        }
+    }
+
+    /// Scopes of all arrays validated in the document at `uri`, the one of
+    /// [`Backend::document_scope`] first, followed by the other arrays the `directories` of the
+    /// configuration map to a directory.
+    fn document_scopes(&self, uri: &Url, source_code: &str) -> Vec<DocumentScope> {
+        let scope = self.document_scope(uri, source_code);
+        let varnames: Vec<String> = self
+            .config
+            .read()
+            .map(|config| config.directories.keys().cloned().collect())
+            .unwrap_or_default();
+        let mapped = varnames
+            .into_iter()
+            .filter(|varname| *varname != scope.varname)
+            .filter_map(|varname| {
+                let provider = self.mapped_provider(&varname)?;
+                Some(DocumentScope { varname, provider })
+            })
+            .collect::<Vec<_>>();
+        std::iter::once(scope).chain(mapped).collect()
+    }
+
+    /// The scope of the array declared at `position`, the first of `scopes` if none of their
+    /// arrays is.
+    fn scope_at<'a>(
+        scopes: &'a [DocumentScope],
+        source_code: &str,
+        position: Position,
+    ) -> &'a DocumentScope {
+        scopes
+            .iter()
+            .find(|scope| {
+                LspParser::parse_identifiers(source_code, &scope.varname)
+                    .into_iter()
+                    .chain(LspParser::parse_arrays(source_code, &scope.varname))
+                    .any(|declaration| {
+                        let range = Range::from(MyRange(declaration.range, source_code));
+                        range.start <= position && position <= range.end
+                    })
+            })
+            .unwrap_or(&scopes[0])
+    }
+
+    /// The directory `dir` relative to the workspace root.
+    fn workspace_directory(&self, dir: String) -> Provider {
+        Provider::Directory {
+            dir,
+            recursive: self.args.recursive,
+        }
+        .resolve(&self.workspace_root())
+    }
+
+    /// The directory the `directories` of the configuration map `varname` to, unless it's
+    /// outside of the workspace with `--restrict-to-workspace`.
+    fn mapped_provider(&self, varname: &str) -> Option<Provider> {
+        let dir = self.config.read().ok()?.directories.get(varname)?.clone();
+        let provider = self.workspace_directory(dir);
+        match &provider {
+            Provider::Directory { dir, .. } if !self.is_allowed_path(Path::new(dir)) => None,
+            _ => Some(provider),
+        }
+    }
+
+    /// The override of the configuration for the document at `uri`, if it's a file in the
+    /// workspace root matching one.
+    fn document_override(&self, uri: &Url) -> Option<Override> {
+        let path = paths::from_uri(uri)?;
+        let relative = path.strip_prefix(self.workspace_root()).ok()?;
+        let config = self.config.read().ok()?;
+        config.override_for(relative).cloned()
+    }
+
+    /// Build the index of the document's own provider if it differs from the workspace one.
+    async fn load_document_index(&self, scope: &DocumentScope) {
+        if scope.provider != self.provider() {
+            self.indexer.load(scope.provider.clone()).await;
+        }
+    }
+
+    /// Forget the indexes of the closed document `uri` which no other opened document uses.
+    fn unload_document_indexes(&self, uri: &Url, source_code: &str) {
+        let used: HashSet<String> = self
+            .documents
+            .snapshot()
+            .into_iter()
+            .flat_map(|(uri, text, _)| self.document_scopes(&uri, &text))
+            .map(|scope| scope.provider.location())
+            .collect();
+        for scope in self.document_scopes(uri, source_code) {
+            if scope.provider != self.provider() && !used.contains(&scope.provider.location()) {
+                self.indexer.unload(scope.provider);
+            }
+        }
+    }
+
+    /// Build the indexes of all `scopes` of a document.
+    async fn load_document_indexes(&self, scopes: &[DocumentScope]) {
+        for scope in scopes {
+            self.load_document_index(scope).await;
+        }
+    }
+
+    /// Call `f` with the index of the names valid in the document of `scope`.
+    fn with_index<R: Default>(
+        &self,
+        scope: &DocumentScope,
+        f: impl FnOnce(&SuggestionIndex) -> R,
+    ) -> R {
+        self.indexer.revalidate();
+        let document_index = self.indexer.document_index(&scope.provider);
+        // Names of documents whose provider failed to be read are unknown, not the workspace's
+        if document_index.is_none()
+            && scope.provider != self.provider()
+            && self.indexer.failure(&scope.provider).is_some()
+        {
+            return R::default();
+        }
+        document_index
+            .as_deref()
+            .unwrap_or(self.indexer.index())
+            .read()
+            .map(|index| f(&index))
+            .unwrap_or_default()
+    }
+
+    /// Glob of the watched files, below any directory if suggestions are next to each document.
+    fn watch_glob(&self) -> String {
+        let glob = self.configured_provider().watch_glob();
+        if !self.args.relative_to_document || Path::new(&glob).is_absolute() {
+            return self.provider().watch_glob();
+        }
+        format!(
+            "**/{}",
+            glob.trim_start_matches("./").trim_start_matches("../")
+        )
+    }
+
+    /// Globs of the watched files, those of the names, of the suggestions directories of the
+    /// overrides and the configuration file.
+    fn watch_globs(&self) -> Vec<String> {
+        let dirs: Vec<String> = self
+            .config
+            .read()
+            .map(|config| {
+                let overrides = config
+                    .overrides
+                    .iter()
+                    .filter_map(|entry| entry.suggestionsdir.clone());
+                overrides
+                    .chain(config.directories.values().cloned())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut globs = vec![self.watch_glob()];
+        for dir in dirs {
+            globs.push(self.workspace_directory(dir).watch_glob());
+        }
+        globs.push(format!("**/{}", CONFIG_FILE));
+        globs.push(self.source_glob());
+        let mut seen = HashSet::new();
+        globs.retain(|glob| seen.insert(glob.clone()));
+        globs
+    }
+
+    /// Glob of the source files, whose changes update the project.
+    fn source_glob(&self) -> String {
+        match self.args.extensions.as_slice() {
+            [extension] => format!("**/*.{}", extension),
+            extensions => format!("**/*.{{{}}}", extensions.join(",")),
+        }
+    }
+
+    /// Whether the watched file `uri` is a source file, which may be one of the names of the
+    /// suggestions directory as well.
+    fn is_source_file(&self, uri: &Url) -> bool {
+        paths::from_uri(uri)
+            .is_some_and(|path| indexer::has_extension(&path, &self.args.extensions))
+    }
+
+    /// Whether the watched file `uri` is inside of the suggestions directory.
+    fn is_suggestion_file(&self, uri: &Url) -> bool {
+        paths::from_uri(uri).is_some_and(|path| path.starts_with(self.provider().location()))
+    }
+
+    /// Update the project from watched changes of files which aren't open, reading the changed
+    /// ones from disk instead of scanning the workspace again.
+    fn update_project_files(&self, changes: &[FileEvent]) {
+        if !self.project.is_discovered() {
+            return;
+        }
+        let needle = LspParser::needle(&self.args.varname);
+        for change in changes {
+            if self.documents.contains(&change.uri) {
+                continue;
+            }
+            if change.typ == FileChangeType::DELETED {
+                self.project.remove(&change.uri);
+                self.project.remove_folder(&change.uri);
+                continue;
+            }
+            match self.document_text(&change.uri) {
+                // Like the scan, only files mentioning the tracked variable are parsed
+                Some(source_code) if source_code.contains(needle) => {
+                    self.update_project(&change.uri, source_code)
+                }
+                _ => self.project.remove(&change.uri),
+            }
+        }
+    }
+
+    /// Labels of the names in completions and inlay hints.
+    fn labels(&self) -> Labels {
+        self.config
+            .read()
+            .map(|config| config.labels.clone())
+            .unwrap_or_default()
+    }
+
+    /// Whether `uri` is the configuration file in the workspace root.
+    fn is_config_file(&self, uri: &Url) -> bool {
+        paths::from_uri(uri).is_some_and(|path| path == self.workspace_root().join(CONFIG_FILE))
+    }
+
+    /// Load the configuration again, rebuild the index if the names come from elsewhere now and
+    /// watch the files of the new provider.
+    async fn reload_config(&self) {
+        let provider = self.provider();
+        let watch_globs = self.watch_globs();
+        self.load_config().await;
+        // Arrays and their directories may have changed
+        self.project.clear();
+        // A list of names in the configuration replaces the suggestions directory
+        if self.provider() != provider {
+            self.indexer.set_provider(self.provider());
+            self.rebuild_index().await;
+        }
+        if self.watch_globs() != watch_globs && self.is_enabled(Feature::WatchedFiles) {
+            let mut enabled = self.features.read().map(|f| f.clone()).unwrap_or_default();
+            enabled.remove(&Feature::WatchedFiles);
+            self.update_features(&enabled).await;
+            enabled.insert(Feature::WatchedFiles);
+            self.update_features(&enabled).await;
+        }
+    }
+
+    /// Resolve the configuration from the configuration file in the workspace root, the client
+    /// settings and the command line, and load its validator plugins.
+    async fn load_config(&self) {
+        let dir = self.workspace_root();
+        let mut layers = self
+            .client_config
+            .read()
+            .map(|layers| layers.clone())
+            .unwrap_or_default();
+        layers.insert(Source::CommandLine, self.args.config_layer());
+
+        let loaded = Config::file_layer(&dir).and_then(|file| {
+            layers.insert(Source::File, file);
+            let (config, sources) = Config::resolve(&layers)?;
+            if self.args.restrict_to_workspace {
+                config.ensure_inside(&dir)?;
+            }
+            let validators = validator::load_plugins(&config.plugins, &dir)?;
+            grammar::register_config(&config, &dir)?;
+            Ok((config, sources, validators))
+        });
+        match loaded {
+            Ok((loaded, sources, loaded_validators)) => {
+                if let Ok(mut settings) = self.settings.write() {
+                    *settings = loaded.settings(&sources);
+                }
+                if let Ok(mut config) = self.config.write() {
+                    *config = loaded;
+                }
+                if let Ok(mut validators) = self.validators.write() {
+                    *validators = loaded_validators;
+                }
+                // Denied names are never suggested
+                if let Ok(mut memo) = self.matches_memo.write() {
+                    memo.matches.clear();
+                }
+            }
+            Err(err) => {
+                self.client
+                    .show_message(
+                        MessageType::WARNING,
+                        format!("Invalid configuration {}", err),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    /// Result id of the pulled diagnostics of `source_code`, which only changes with the
+    /// document, its scope, the configuration or the diagnostics generation.
+    fn diagnostics_result_id(&self, scopes: &[DocumentScope], source_code: &str) -> String {
+        let mut hasher = DefaultHasher::new();
+        source_code.hash(&mut hasher);
+        for scope in scopes {
+            scope.varname.hash(&mut hasher);
+            scope.provider.location().hash(&mut hasher);
+            self.indexer.failure(&scope.provider).hash(&mut hasher);
+        }
+        if let Ok(config) = self.config.read() {
+            config.hash(&mut hasher);
+        }
+        self.diagnostics_generation
+            .load(Ordering::Relaxed)
+            .hash(&mut hasher);
+        self.indexer.generation().hash(&mut hasher);
+        format!("{:x}", hasher.finish())
+    }
+
+    /// Diagnostics of the opened document `uri` with their result id, validated only once for
+    /// the same content, scopes, configuration and folders.
+    fn document_diagnostics(
+        &self,
+        uri: &Url,
+        scopes: &[DocumentScope],
+        source_code: &str,
+    ) -> (String, Vec<Diagnostic>) {
+        let result_id = self.diagnostics_result_id(scopes, source_code);
+        let cached = self.diagnostics_cache.read().ok().and_then(|cache| {
+            cache
+                .get(uri)
+                .filter(|(cached_id, _)| *cached_id == result_id)
+                .map(|(_, diagnostics)| diagnostics.clone())
+        });
+        metrics::record_cache("diagnostics", cached.is_some());
+        if let Some(diagnostics) = cached {
+            return (result_id, diagnostics);
+        }
+
+        let diagnostics = self.perform_all_diagnostics(scopes, source_code);
+        if let Ok(mut cache) = self.diagnostics_cache.write() {
+            cache.insert(uri.clone(), (result_id.clone(), diagnostics.clone()));
+        }
+        (result_id, diagnostics)
+    }
+
+    /// Diagnostics of the arrays of all `scopes` of a document.
+    fn perform_all_diagnostics(
+        &self,
+        scopes: &[DocumentScope],
+        source_code: &str,
+    ) -> Vec<Diagnostic> {
+        scopes
+            .iter()
+            .flat_map(|scope| self.perform_diagnostics(scope, source_code))
+            .collect()
+    }
+
+    fn perform_diagnostics(&self, scope: &DocumentScope, source_code: &str) -> Vec<Diagnostic> {
+        // Every entry would be invalid, a single problem on the array tells what's wrong instead.
+        // Failed reads are remembered by the indexer rather than retried on every keystroke.
+        let problem = match Backend::missing_dir(&scope.provider) {
+            Some(dir) => Some(Message::MissingSuggestionsDir(&dir).text(self.locale())),
+            None => self
+                .indexer
+                .failure(&scope.provider)
+                .map(|err| Message::UnreadableSuggestions(&err).text(self.locale())),
+        };
+        if let Some(problem) = problem {
+            return LspParser::parse_identifiers(source_code, &scope.varname)
+                .first()
+                .map(|identifier| Diagnostic {
+                    range: MyRange(identifier.range, source_code).into(),
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    source: Some("tsm-language-server".into()),
+                    message: problem,
+                    ..Diagnostic::default()
+                })
+                .into_iter()
+                .collect();
+        }
+        let config = self.config.read().map(|c| c.clone()).unwrap_or_default();
+        let location = scope.provider.location();
+        let validators = self
+            .validators
+            .read()
+            .map(|v| v.clone())
+            .unwrap_or_default();
+        let engine = DiagnosticEngine {
+            varname: &scope.varname,
+            suggestionsdir: &location,
+            locale: self.locale(),
+            config: &config,
+            validators: &validators,
+        };
+
+        let start = Instant::now();
+        let mut diagnostics = engine.diagnostics_with_names(
+            source_code,
+            |name| self.is_valid_folder(scope, name),
+            |name| self.with_index(scope, |index| index.is_file(name)),
+            || self.with_index(scope, |index| index.names().map(String::from).collect()),
+        );
+        metrics::record_parse(start.elapsed());
+
+        // Shown in the problems panel without opening the code actions
+        if config.did_you_mean() {
+            for diagnostic in &mut diagnostics {
+                if rules::of(diagnostic).is_none_or(|rule| rule.name() != "invalid-folder") {
+                    continue;
+                }
+                let name = diagnostic.data.as_ref().and_then(|data| data.as_str());
+                let best_match =
+                    name.and_then(|name| self.get_suggestions(scope, name, 1).into_iter().next());
+                if let Some(best_match) = best_match {
+                    let suggestion = format!("'{}'", best_match);
+                    diagnostic.message = format!(
+                        "{}, {}",
+                        diagnostic.message,
+                        Message::DidYouMean(&suggestion).text(self.locale())
+                    );
+                }
+            }
+        }
+
+        // Clients render unknown tags literally or not at all
+        let support = self.client_support();
+        for diagnostic in &mut diagnostics {
+            if let Some(tags) = diagnostic.tags.take() {
+                let tags: Vec<DiagnosticTag> = tags
+                    .into_iter()
+                    .filter(|tag| support.diagnostic_tags.contains(tag))
+                    .collect();
+                diagnostic.tags = (!tags.is_empty()).then_some(tags);
+            }
+        }
+        diagnostics
+    }
+
+    /// Semantic tokens of the entries naming existing or deprecated folders.
+    async fn document_semantic_tokens(&self, uri: &Url) -> Option<Vec<SemanticToken>> {
+        let content = self.document_text(uri)?;
+        let scope = self.document_scope(uri, &content);
+        self.load_document_index(&scope).await;
+
+        let config = self.config.read().map(|c| c.clone()).unwrap_or_default();
+        let tokens: Vec<semantic::Token> = LspParser::parse_code(&content, &scope.varname)
+            .into_iter()
+            .filter(|entry| entry.range.start_point.row == entry.range.end_point.row)
+            .filter_map(|entry| {
+                let deprecated = config.replacement(entry.text).is_some();
+                // Tokens count UTF-16 code units like positions
+                let range = Range::from(MyRange(entry.range, &content));
+                (deprecated || self.is_valid_folder(&scope, entry.text)).then_some(
+                    semantic::Token {
+                        line: range.start.line,
+                        start: range.start.character,
+                        length: range.end.character - range.start.character,
+                        deprecated,
+                    },
+                )
+            })
+            .collect();
+        Some(semantic::encode(&tokens))
+    }
+
+    /// Remember the `tokens` sent for `uri` and return their new result id.
+    fn store_semantic_tokens(&self, uri: &Url, tokens: Vec<SemanticToken>) -> String {
+        let result_id = self
+            .next_result_id
+            .fetch_add(1, Ordering::Relaxed)
+            .to_string();
+        if let Ok(mut semantic_tokens) = self.semantic_tokens.write() {
+            semantic_tokens.insert(uri.clone(), (result_id.clone(), tokens));
+        }
+        result_id
+    }
+
+    fn reveal_folder_command(name: &str, uri: &Url, locale: Locale) -> Command {
+        Command {
+            title: Message::RevealFolder(name).text(locale),
+            command: commands::REVEAL_FOLDER.into(),
+            arguments: Some(vec![
+                serde_json::Value::String(name.into()),
+                serde_json::Value::String(uri.to_string()),
+            ]),
+        }
+    }
+
+    /// Open the folder named by the first argument, in the suggestions directory of the document
+    /// passed as second argument.
+    async fn reveal_folder(
+        &self,
+        arguments: &[serde_json::Value],
+    ) -> Result<Option<serde_json::Value>> {
+        // Clients may send any name, only folders of the suggestions directory are revealed
+        let name = match arguments.first().and_then(|arg| arg.as_str()) {
+            Some(name) if paths::is_folder_name(name) => name,
+            _ => {
+                return Err(tower_lsp::jsonrpc::Error::invalid_params(
+                    "expected a folder name",
+                ))
+            }
+        };
+
+        // The document the command was created for, its suggestions directory may differ
+        let document = arguments
+            .get(1)
+            .and_then(|arg| arg.as_str())
+            .and_then(|uri| Url::parse(uri).ok());
+        let path = self.document_folder_path(document.as_ref(), name);
+        if !self.is_allowed_path(&path) {
+            return Err(tower_lsp::jsonrpc::Error::invalid_params(format!(
+                "{} is outside of the workspace",
+                paths::display(&path)
+            )));
+        }
+        if let Err(err) = commands::reveal_folder(&path) {
+            self.client
+                .show_message(
+                    MessageType::ERROR,
+                    Message::RevealFolderFailed {
+                        path: &paths::display(&path),
+                        error: &err.to_string(),
+                    }
+                    .text(self.locale()),
+                )
+                .await;
+        }
+
+        Ok(None)
+    }
+
+    fn create_folder_command(name: &str, uri: &Url, locale: Locale) -> Command {
+        Command {
+            title: Message::CreateFolder(name).text(locale),
+            command: commands::CREATE_FOLDER.into(),
+            arguments: Some(vec![
+                serde_json::Value::String(name.into()),
+                serde_json::Value::String(uri.to_string()),
+            ]),
+        }
+    }
+
+    /// Create the folder named by the first argument in the suggestions directory of the document
+    /// passed as second argument. A dry run returns the path of the folder instead.
+    async fn create_folder(
+        &self,
+        arguments: &[serde_json::Value],
+    ) -> Result<Option<serde_json::Value>> {
+        let name = match arguments.first().and_then(|arg| arg.as_str()) {
+            Some(name) if paths::is_folder_name(name) => name,
+            _ => {
+                return Err(tower_lsp::jsonrpc::Error::invalid_params(
+                    "expected a folder name",
+                ))
+            }
+        };
+        let uri = match arguments
+            .get(1)
+            .and_then(|arg| arg.as_str())
+            .and_then(|uri| Url::parse(uri).ok())
+        {
+            Some(uri) => uri,
+            None => {
+                return Err(tower_lsp::jsonrpc::Error::invalid_params(
+                    "expected a document URI",
+                ))
+            }
+        };
+
+        let content = self.document_text(&uri).unwrap_or_default();
+        let path = match self.document_scope(&uri, &content).provider {
+            Provider::Directory { dir, .. } => Path::new(&dir).join(name),
+            provider => {
+                return Err(tower_lsp::jsonrpc::Error::invalid_params(format!(
+                    "{} is not a directory",
+                    provider.location()
+                )))
+            }
+        };
+        if !self.is_allowed_path(&path) {
+            return Err(tower_lsp::jsonrpc::Error::invalid_params(format!(
+                "{} is outside of the workspace",
+                paths::display(&path)
+            )));
+        }
+        if commands::is_dry_run(arguments) {
+            return Ok(Some(serde_json::json!({
+                "name": name,
+                "folder": paths::display(&path),
+            })));
+        }
+
+        if let Err(err) = fs::create_dir_all(&path) {
+            self.client
+                .show_message(
+                    MessageType::ERROR,
+                    Message::CreateFolderFailed {
+                        name,
+                        error: &err.to_string(),
+                    }
+                    .text(self.locale()),
+                )
+                .await;
+            return Ok(None);
+        }
+        // The entry is valid right away, without waiting for the file watcher
+        if let Ok(uri) = Url::from_file_path(&path) {
+            let event = FileEvent::new(uri, FileChangeType::CREATED);
+            self.indexer.files_changed(vec![event]).await;
+        }
+        self.republish_diagnostics().await;
+        Ok(None)
+    }
+
+    /// Path of the folder `name` in the suggestions directory of the document at `uri`, of the
+    /// workspace without one.
+    fn document_folder_path(&self, uri: Option<&Url>, name: &str) -> PathBuf {
+        let provider = match uri {
+            Some(uri) => {
+                let content = self.document_text(uri);
+                self.document_scope(uri, &content.unwrap_or_default())
+                    .provider
+            }
+            None => self.provider(),
+        };
+        self.folder_path(&provider, name)
+    }
+
+    /// Path of the folder `name` of `provider`, below the suggestions directory in the workspace
+    /// root for providers without a directory.
+    fn folder_path(&self, provider: &Provider, name: &str) -> PathBuf {
+        match provider {
+            Provider::Directory { dir, .. } => Path::new(dir).join(name),
+            _ => self
+                .workspace_root()
+                .join(&self.args.suggestionsdir)
+                .join(name),
+        }
+    }
+
+    /// Number of entries and total size of the files directly in the folder `name` referenced in
+    /// the document `uri`.
+    fn folder_summary(&self, uri: &Url, name: &str) -> Option<(usize, u64)> {
+        let content = self.document_text(uri).unwrap_or_default();
+        let scope = self.document_scope(uri, &content);
+        let path = self.folder_path(&scope.provider, name);
+
+        let (count, size) = fs::read_dir(path)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .fold((0, 0), |(count, size), entry| {
+                let file_size = entry
+                    .metadata()
+                    .ok()
+                    .filter(|metadata| metadata.is_file())
+                    .map_or(0, |metadata| metadata.len());
+                (count + 1, size + file_size)
+            });
+        Some((count, size))
+    }
+
+    /// First paragraph of the README.md in the folder `name` of the array `varname` in the
+    /// document `uri`, which tells similar-sounding folders apart.
+    fn folder_readme(&self, uri: &Url, varname: &str, name: &str) -> Option<String> {
+        let content = self.document_text(uri)?;
+        let scopes = self.document_scopes(uri, &content);
+        let scope = scopes.iter().find(|scope| scope.varname == varname)?;
+        let path = self.folder_path(&scope.provider, name).join("README.md");
+        let readme = fs::read_to_string(path).ok()?;
+        Backend::readme_excerpt(&readme)
+    }
+
+    /// The first paragraph of `readme`, skipping headings and badges.
+    fn readme_excerpt(readme: &str) -> Option<String> {
+        let paragraph: Vec<&str> = readme
+            .lines()
+            .map(str::trim)
+            .skip_while(|line| line.is_empty() || line.starts_with('#') || line.starts_with("!["))
+            .take_while(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+        (!paragraph.is_empty()).then(|| paragraph.join("\n"))
+    }
+
+    /// Summary of the contents of the folder `name` referenced in the document `uri`.
+    fn folder_tooltip(&self, uri: &Url, name: &str) -> Option<InlayHintTooltip> {
+        let content = self.documents.text(uri).unwrap_or_default();
+        let scope = self.document_scope(uri, &content);
+        let path = self.folder_path(&scope.provider, name);
+
+        let mut names: Vec<String> = fs::read_dir(path)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                    format!("{}/", name)
+                } else {
+                    name
+                }
+            })
+            .collect();
+        names.sort();
+
+        let locale = self.locale();
+        let summary = if names.is_empty() {
+            Message::EmptyFolder.text(locale)
+        } else {
+            let mut listed = names
+                .iter()
+                .take(MAX_TOOLTIP_NAMES)
+                .cloned()
+                .collect::<Vec<String>>()
+                .join(", ");
+            if names.len() > MAX_TOOLTIP_NAMES {
+                listed.push_str(", …");
+            }
+            Message::FolderContents {
+                count: names.len(),
+                names: &listed,
+            }
+            .text(locale)
+        };
+        Some(InlayHintTooltip::String(summary))
+    }
+
+    /// Whether the client resolves the `property` of inlay hints lazily.
+    fn supports_inlay_hint_resolve(&self, property: &str) -> bool {
+        self.client_capabilities
+            .read()
+            .map(|capabilities| {
+                capabilities
+                    .text_document
+                    .as_ref()
+                    .and_then(|td| td.inlay_hint.as_ref())
+                    .and_then(|inlay_hint| inlay_hint.resolve_support.as_ref())
+                    .is_some_and(|support| support.properties.iter().any(|p| p == property))
+            })
+            .unwrap_or(false)
+    }
+
+    /// Add the folders missing from the array of the document `uri` and remove the entries of
+    /// folders which no longer exist, once the user confirmed the changes. A `dry_run` returns
+    /// the added and removed names with the planned edit instead.
+    async fn sync_folders(&self, uri: Url, dry_run: bool) -> Option<serde_json::Value> {
+        let content = self.document_text(&uri)?;
+        let scope = self.document_scope(&uri, &content);
+        self.load_document_index(&scope).await;
+
+        let config = self.config.read().map(|c| c.clone()).unwrap_or_default();
+        let validator_names: HashSet<String> = self
+            .validators
+            .read()
+            .map(|validators| validators.iter().flat_map(|v| v.names()).collect())
+            .unwrap_or_default();
+        let is_gone = |name: &str| {
+            !(self.is_valid_folder(&scope, name)
+                || config.allow.contains(name)
+                || validator_names.contains(name))
+        };
+
+        let entries = LspParser::parse_code(&content, &scope.varname);
+        let folder_names = self.with_index(&scope, |index| {
+            index.names().map(String::from).collect::<Vec<_>>()
+        });
+        let missing = rules::missing_names(&entries, folder_names, &config);
+        let removed: Vec<&str> = entries
+            .iter()
+            .map(|entry| entry.text)
+            .filter(|name| is_gone(name))
+            .collect();
+
+        let locale = self.locale();
+        let in_sync = missing.is_empty() && removed.is_empty();
+        if in_sync && !dry_run {
+            self.client
+                .show_message(MessageType::INFO, Message::FoldersInSync.text(locale))
+                .await;
+            return None;
+        }
+
+        let missing: Vec<&str> = missing.iter().map(String::as_str).collect();
+        let edits = edits::sync_entries(
+            &content,
+            &scope.varname,
+            &missing,
+            is_gone,
+            self.args.quote_style,
+        );
+        let edit = WorkspaceEdit {
+            changes: Some(HashMap::from([(uri, edits)])),
+            ..Default::default()
+        };
+        // The planned changes are returned as they are, without asking or applying them
+        if dry_run {
+            return Some(serde_json::json!({
+                "added": missing,
+                "removed": removed,
+                "edit": (!in_sync).then_some(edit),
+            }));
+        }
+
+        let question = Message::SyncFolders {
+            added: &missing.join(", "),
+            removed: &removed.join(", "),
+        };
+        if !self.confirm(question.text(locale)).await {
+            return None;
+        }
+
+        if let Err(err) = self.client.apply_edit(edit).await {
+            self.client
+                .log_message(MessageType::ERROR, err.to_string())
+                .await;
+        }
+        None
+    }
+
+    /// Ask the user to confirm `question`, whether they chose to apply the changes.
+    async fn confirm(&self, question: String) -> bool {
+        let locale = self.locale();
+        let apply = Message::Apply.text(locale);
+        let confirmation = self
+            .client
+            .show_message_request(
+                MessageType::INFO,
+                question,
+                Some(vec![
+                    MessageActionItem {
+                        title: apply.clone(),
+                        properties: HashMap::new(),
+                    },
+                    MessageActionItem {
+                        title: Message::Cancel.text(locale),
+                        properties: HashMap::new(),
+                    },
+                ]),
+            )
+            .await;
+        matches!(confirmation, Ok(Some(action)) if action.title == apply)
+    }
+
+    /// Create the folders of all entries of the document `uri` which don't exist, once the user
+    /// confirmed them, reporting the outcome of every folder as `WorkDoneProgress`. A `dry_run`
+    /// returns the folders instead. Returns the created folders and the failures.
+    async fn create_all_missing(
+        &self,
+        uri: Url,
+        dry_run: bool,
+    ) -> Result<Option<serde_json::Value>> {
+        let content = match self.document_text(&uri) {
+            Some(content) => content,
+            None => return Ok(None),
+        };
+        let scope = self.document_scope(&uri, &content);
+        let dir = match &scope.provider {
+            Provider::Directory { dir, .. } => PathBuf::from(dir),
+            provider => {
+                return Err(tower_lsp::jsonrpc::Error::invalid_params(format!(
+                    "{} is not a directory",
+                    provider.location()
+                )))
+            }
+        };
+        self.load_document_index(&scope).await;
+
+        let config = self.config.read().map(|c| c.clone()).unwrap_or_default();
+        let validator_names: HashSet<String> = self
+            .validators
+            .read()
+            .map(|validators| validators.iter().flat_map(|v| v.names()).collect())
+            .unwrap_or_default();
+        let is_missing = |name: &str| {
+            !(name.is_empty()
+                || self.is_valid_folder(&scope, name)
+                || config.allow.contains(name)
+                || config.deny.contains(name)
+                || validator_names.contains(name))
+        };
+        let mut seen = HashSet::new();
+        // Entries escaping the suggestions directory are never created
+        let missing: Vec<(String, PathBuf)> = LspParser::parse_code(&content, &scope.varname)
+            .into_iter()
+            .map(|entry| entry.text)
+            .filter(|name| is_missing(name) && seen.insert(*name))
+            .map(|name| (name.to_string(), dir.join(name)))
+            .filter(|(_, path)| paths::is_inside(path, &dir) && self.is_allowed_path(path))
+            .collect();
+
+        let locale = self.locale();
+        let names: Vec<&str> = missing.iter().map(|(name, _)| name.as_str()).collect();
+        if dry_run {
+            let folders: Vec<String> = missing
+                .iter()
+                .map(|(_, path)| paths::display(path))
+                .collect();
+            return Ok(Some(serde_json::json!({
+                "names": names,
+                "folders": folders,
+            })));
         }
+        if missing.is_empty() {
+            self.client
+                .show_message(MessageType::INFO, Message::NoMissingFolders.text(locale))
+                .await;
+            return Ok(None);
+        }
+        let question = Message::CreateMissingFolders(&names.join(", "));
+        if !self.confirm(question.text(locale)).await {
+            return Ok(None);
+        }
+
+        let token = NumberOrString::String("tsm-create-folders".into());
+        let report = self.supports_work_done_progress()
+            && self
+                .client
+                .send_request::<request::WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                    token: token.clone(),
+                })
+                .await
+                .is_ok();
+        if report {
+            self.report_progress(
+                &token,
+                WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                    title: Message::CreatingFolders.text(locale),
+                    cancellable: Some(false),
+                    message: None,
+                    percentage: Some(0),
+                }),
+            )
+            .await;
+        }
+
+        let mut created = vec![];
+        let mut failed = vec![];
+        let mut events = vec![];
+        for (done, (name, path)) in missing.iter().enumerate() {
+            let message = match fs::create_dir_all(path) {
+                Ok(()) => {
+                    created.push(name.clone());
+                    if let Ok(uri) = Url::from_file_path(path) {
+                        events.push(FileEvent::new(uri, FileChangeType::CREATED));
+                    }
+                    Message::CreatedFolder(name).text(locale)
+                }
+                Err(err) => {
+                    let error = err.to_string();
+                    let message = Message::CreateFolderFailed {
+                        name,
+                        error: &error,
+                    }
+                    .text(locale);
+                    self.client.log_message(MessageType::ERROR, &message).await;
+                    failed.push(serde_json::json!({ "name": name, "error": error }));
+                    message
+                }
+            };
+            if report {
+                self.report_progress(
+                    &token,
+                    WorkDoneProgress::Report(WorkDoneProgressReport {
+                        cancellable: Some(false),
+                        message: Some(message),
+                        percentage: Some(((done + 1) * 100 / missing.len()) as u32),
+                    }),
+                )
+                .await;
+            }
+        }
+
+        let summary = Message::CreatedFolders {
+            created: created.len(),
+            total: missing.len(),
+        }
+        .text(locale);
+        if report {
+            self.report_progress(
+                &token,
+                WorkDoneProgress::End(WorkDoneProgressEnd {
+                    message: Some(summary.clone()),
+                }),
+            )
+            .await;
+        }
+        if !failed.is_empty() {
+            self.client
+                .show_message(MessageType::WARNING, summary)
+                .await;
+        }
+
+        // The entries are valid right away, without waiting for the file watcher
+        self.indexer.files_changed(events).await;
+        self.republish_diagnostics().await;
+        Ok(Some(serde_json::json!({
+            "created": created,
+            "failed": failed,
+        })))
     }
 
-    fn get_files(root: &str) -> Vec<String> {
-        match fs::read_dir(root) {
-            Ok(paths) => paths
-                .into_iter()
-                .filter_map(|e| e.ok())
-                .map(|d| d.file_name().to_string_lossy().to_string())
-                .collect::<Vec<String>>(),
-            Err(_) => vec![],
+    /// Markdown summary of the validity of all entries of the tracked array.
+    fn validation_summary(&self, scope: &DocumentScope, source_code: &str) -> String {
+        let used_folders = LspParser::parse_code(source_code, &scope.varname);
+        let invalid_folders: Vec<&crate::parser::PositionalText> = used_folders
+            .iter()
+            .filter(|used_folder| !self.is_valid_folder(scope, used_folder.text))
+            .collect();
+
+        let locale = self.locale();
+        let mut summary = Message::Summary {
+            varname: &scope.varname,
+            total: used_folders.len(),
+            valid: used_folders.len() - invalid_folders.len(),
+            invalid: invalid_folders.len(),
         }
-    }
+        .text(locale);
 
-    fn perform_diagnostics(&self, source_code: &str) -> Vec<Diagnostic> {
-        let used_folders = LspParser::parse_code(source_code, &self.args.varname);
-        let available_folders = Backend::get_files(&self.args.suggestionsdir);
+        if !invalid_folders.is_empty() {
+            summary.push_str(&format!("\n\n{}\n", Message::InvalidEntries.text(locale)));
+            for invalid_folder in invalid_folders {
+                let best_matches = self.get_suggestions(scope, invalid_folder.text, 3);
+                let line = if best_matches.is_empty() {
+                    format!("- `{}`\n", invalid_folder.text)
+                } else {
+                    let suggestions = best_matches
+                        .iter()
+                        .map(|m| format!("`{}`", m))
+                        .collect::<Vec<String>>()
+                        .join(", ");
+                    format!(
+                        "- `{}` — {}\n",
+                        invalid_folder.text,
+                        Message::DidYouMean(&suggestions).text(locale)
+                    )
+                };
+                summary.push_str(&line);
+            }
+        }
 
-        used_folders
-            .iter()
-            .filter(|used_folder| !available_folders.contains(&used_folder.text))
-            .map(|invalid_folder| Diagnostic {
-                range: MyRange(invalid_folder.range).into(),
-                severity: Some(DiagnosticSeverity::ERROR),
-                code: Some(NumberOrString::String("100".into())),
-                source: Some("tsm-language-server".into()),
-                message: format!(
-                    "'{}' is not a valid folder, valid folders are those in '{}'",
-                    invalid_folder.text, self.args.suggestionsdir
-                ),
-                data: Some(serde_json::value::Value::String(
-                    invalid_folder.text.clone(),
-                )),
-                ..Diagnostic::default()
-            })
-            .collect()
+        summary
     }
 
-    fn get_best_matches(user_input: &str, possible_matches: &[&str], top_n: usize) -> Vec<String> {
-        let matcher = SkimMatcherV2::default();
-        let mut matches_with_scores: Vec<(&str, i64)> = possible_matches
-            .iter()
-            .filter_map(|&s| matcher.fuzzy_match(s, user_input).map(|score| (s, score)))
-            .collect();
+    fn matcher(&self) -> Matcher {
+        Matcher {
+            algorithm: self.args.matcher,
+            case_sensitive: self.args.case_sensitive,
+            cutoff: self.args.match_cutoff,
+        }
+    }
 
-        matches_with_scores.sort_by(|a, b| b.1.cmp(&a.1));
+    /// The best matches of [`Backend::get_suggestions`] before validators ranked them, computed
+    /// once per index generation.
+    fn memoized_matches(
+        &self,
+        scope: &DocumentScope,
+        user_input: &str,
+        top_n: usize,
+    ) -> Vec<(String, f64)> {
+        let key = (scope.provider.location(), user_input.to_string(), top_n);
+        let generation = self.indexer.generation();
+        let memoized = self.matches_memo.read().ok().and_then(|memo| {
+            (memo.generation == generation)
+                .then(|| memo.matches.get(&key).cloned())
+                .flatten()
+        });
+        metrics::record_cache("matches", memoized.is_some());
+        if let Some(matches) = memoized {
+            return matches;
+        }
 
-        matches_with_scores
-            .into_iter()
-            .take(top_n)
-            .map(|(s, _)| s.to_string())
-            .collect::<Vec<String>>()
+        let config = self.config.read().map(|c| c.clone()).unwrap_or_default();
+        let matches = self.with_index(scope, |index| {
+            let mut candidates = index.candidates(user_input);
+            candidates.retain(|candidate| !config.deny.contains(*candidate));
+            self.matcher()
+                .scored_matches(user_input, &candidates, top_n)
+        });
+        if let Ok(mut memo) = self.matches_memo.write() {
+            if memo.generation != generation || memo.matches.len() >= MAX_MEMOIZED_MATCHES {
+                memo.generation = generation;
+                memo.matches.clear();
+            }
+            memo.matches.insert(key, matches.clone());
+        }
+        matches
     }
 }
 
 trait ConvertToCompletionItem {
-    fn to_completionitem(&self) -> Option<CompletionItem>;
+    /// Completion item replacing the text in `range` with `new_text`, with the kind and detail
+    /// of `labels`.
+    fn to_completionitem(
+        &self,
+        labels: &Labels,
+        locale: Locale,
+        range: Range,
+        new_text: String,
+    ) -> Option<CompletionItem>;
 }
 
 impl ConvertToCompletionItem for String {
-    fn to_completionitem(&self) -> Option<CompletionItem> {
+    fn to_completionitem(
+        &self,
+        labels: &Labels,
+        locale: Locale,
+        range: Range,
+        new_text: String,
+    ) -> Option<CompletionItem> {
         let label = self;
-        let mut item = CompletionItem::new_simple(label.clone(), "Directory".to_string());
-        item.kind = Some(CompletionItemKind::FOLDER);
-        item.insert_text = Some(label.into());
+        let mut item = CompletionItem::new_simple(label.clone(), labels.detail(locale));
+        item.kind = Some(match labels.kind() {
+            ItemKind::Folder => CompletionItemKind::FOLDER,
+            ItemKind::Module => CompletionItemKind::MODULE,
+            ItemKind::Value => CompletionItemKind::VALUE,
+        });
+        if new_text != *label {
+            // Clients filter against the replaced text, which includes the quotes
+            item.filter_text = Some(new_text.clone());
+        }
+        item.text_edit = Some(CompletionTextEdit::Edit(TextEdit { range, new_text }));
         Some(item)
     }
 }
@@ -116,26 +2104,135 @@ impl ConvertToCompletionItem for String {
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
     async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
-        let push_diagnostics = params
-            .capabilities
-            .text_document
-            .as_ref()
-            .unwrap()
-            .publish_diagnostics
-            .is_some();
+        if let Some(pid) = params.process_id {
+            tokio::spawn(process::exit_with(pid));
+        }
 
-        {
-            let mut push_diag = self.push_diagnostics.write().unwrap();
-            *push_diag = push_diagnostics;
+        // Workspace scans only work with pushed diagnostics, pulled ones cover opened documents
+        let pull_diagnostics = !self.args.scan_workspace
+            && params
+                .capabilities
+                .text_document
+                .as_ref()
+                .is_some_and(|td| td.diagnostic.is_some());
+        let push_diagnostics = !pull_diagnostics
+            && params
+                .capabilities
+                .text_document
+                .as_ref()
+                .is_some_and(|td| td.publish_diagnostics.is_some());
+
+        if let Ok(mut push) = self.push_diagnostics.write() {
+            *push = push_diagnostics;
+        }
+        if let Ok(mut pull) = self.pull_diagnostics.write() {
+            *pull = pull_diagnostics;
+        }
+        let diagnostic_provider = pull_diagnostics.then(|| {
+            DiagnosticServerCapabilities::Options(DiagnosticOptions {
+                identifier: Some("tsm".into()),
+                inter_file_dependencies: false,
+                workspace_diagnostics: true,
+                ..DiagnosticOptions::default()
+            })
+        });
+
+        #[allow(deprecated)]
+        let roots: Vec<PathBuf> = match &params.workspace_folders {
+            Some(folders) => folders
+                .iter()
+                .filter_map(|folder| paths::from_uri(&folder.uri))
+                .collect(),
+            None => params
+                .root_uri
+                .as_ref()
+                .and_then(paths::from_uri)
+                .into_iter()
+                .collect(),
+        };
+
+        if let Ok(mut workspace_roots) = self.workspace_roots.write() {
+            *workspace_roots = roots;
+        }
+
+        if let Some(options) = &params.initialization_options {
+            if let Ok(mut layers) = self.client_config.write() {
+                layers.insert(Source::InitializationOptions, Config::client_layer(options));
+            }
+        }
+
+        if let Some(tag) = &params.locale {
+            if let Ok(mut locale) = self.locale.write() {
+                *locale = Locale::from_tag(tag);
+            }
+        }
+
+        // With dynamic registration the sync notifications are registered with a document selector in `initialized`
+        let text_document_sync = if Backend::supports_dynamic_sync(&params.capabilities) {
+            None
+        } else {
+            Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL))
+        };
+
+        // Features the client registers dynamically are registered in `initialized`
+        let completion_provider =
+            if Feature::Completion.supports_dynamic_registration(&params.capabilities) {
+                None
+            } else {
+                Some(CompletionOptions {
+                    resolve_provider: Some(true),
+                    ..CompletionOptions::default()
+                })
+            };
+        let code_action_provider =
+            if Feature::CodeAction.supports_dynamic_registration(&params.capabilities) {
+                None
+            } else {
+                Some(CodeActionProviderCapability::Options(CodeActionOptions {
+                    code_action_kinds: Some(capabilities::CODE_ACTION_KINDS.to_vec()),
+                    ..CodeActionOptions::default()
+                }))
+            };
+
+        if let Ok(mut client_capabilities) = self.client_capabilities.write() {
+            *client_capabilities = params.capabilities;
         }
 
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
-                completion_provider: Some(CompletionOptions::default()),
-                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
-                text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::FULL,
-                )),
+                completion_provider,
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
+                code_lens_provider: Some(CodeLensOptions {
+                    resolve_provider: Some(true),
+                }),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: commands::all(),
+                    ..Default::default()
+                }),
+                code_action_provider,
+                text_document_sync,
+                diagnostic_provider,
+                inlay_hint_provider: Some(OneOf::Right(InlayHintServerCapabilities::Options(
+                    InlayHintOptions {
+                        resolve_provider: Some(true),
+                        ..InlayHintOptions::default()
+                    },
+                ))),
+                references_provider: Some(OneOf::Left(true)),
+                workspace_symbol_provider: Some(OneOf::Left(true)),
+                rename_provider: Some(OneOf::Right(RenameOptions {
+                    prepare_provider: Some(true),
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                })),
+                semantic_tokens_provider: Some(
+                    SemanticTokensServerCapabilities::SemanticTokensOptions(
+                        SemanticTokensOptions {
+                            legend: semantic::legend(),
+                            full: Some(SemanticTokensFullOptions::Delta { delta: Some(true) }),
+                            ..SemanticTokensOptions::default()
+                        },
+                    ),
+                ),
                 ..Default::default()
             },
             ..Default::default()
@@ -153,158 +2250,975 @@ impl LanguageServer for Backend {
                 )
                 .await;
         }
+
+        self.load_config().await;
+        self.indexer.set_provider(self.provider());
+
+        let dynamic_sync = self
+            .client_capabilities
+            .read()
+            .map(|capabilities| Backend::supports_dynamic_sync(&capabilities))
+            .unwrap_or(false);
+
+        if dynamic_sync {
+            self.register_document_sync().await;
+        }
+
+        // Nothing is registered yet, so register every enabled feature from scratch
+        let enabled: HashSet<Feature> = match self.features.write() {
+            Ok(mut features) => std::mem::take(&mut *features),
+            Err(_) => HashSet::new(),
+        };
+        self.update_features(&enabled).await;
+
+        // Documents opened while indexing were validated against the empty index
+        let indexed = self.rebuild_index().await;
+        self.client
+            .log_message(
+                MessageType::INFO,
+                format!(
+                    "Indexed {} folders in '{}'",
+                    indexed,
+                    self.provider().location()
+                ),
+            )
+            .await;
+        self.republish_diagnostics().await;
+
+        let push_diagnostics = self.pushes_diagnostics();
+
+        if self.args.scan_workspace && push_diagnostics {
+            self.scan_workspace().await;
+        }
     }
 
     async fn shutdown(&self) -> Result<()> {
-        if let Ok(mut docs) = self.documents.write() {
-            docs.clear();
-        }
+        self.shutdown_requested.store(true, Ordering::Relaxed);
+        // File events queued before are applied, scans and rebuilds finish
+        self.indexer.stop().await;
+
+        self.documents.clear();
 
         Ok(())
     }
 
-    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
-        let docs = match self.documents.read() {
-            Ok(docs) => docs,
-            Err(_) => return Ok(None),
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        let enabled: HashSet<Feature> = Feature::ALL
+            .into_iter()
+            .filter(|feature| {
+                capabilities::feature_setting(&params.settings, *feature)
+                    .unwrap_or_else(|| self.is_enabled(*feature))
+            })
+            .collect();
+
+        self.update_features(&enabled).await;
+
+        let layer = Config::client_layer(&params.settings);
+        let changed = self.client_config.write().is_ok_and(|mut layers| {
+            layers.insert(Source::ClientSettings, layer.clone()) != Some(layer)
+        });
+        if changed {
+            self.reload_config().await;
+        }
+        self.republish_diagnostics().await;
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        let (config_changes, changes): (Vec<FileEvent>, Vec<FileEvent>) = params
+            .changes
+            .into_iter()
+            .partition(|change| self.is_config_file(&change.uri));
+        if !config_changes.is_empty() {
+            self.reload_config().await;
+        }
+        let (source_changes, mut changes): (Vec<FileEvent>, Vec<FileEvent>) = changes
+            .into_iter()
+            .partition(|change| self.is_source_file(&change.uri));
+        self.update_project_files(&source_changes);
+        // Deleted folders may contain source files, they aren't reported on their own
+        let deleted: Vec<FileEvent> = changes
+            .iter()
+            .filter(|change| change.typ == FileChangeType::DELETED)
+            .cloned()
+            .collect();
+        self.update_project_files(&deleted);
+        changes.extend(
+            source_changes
+                .into_iter()
+                .filter(|change| self.is_suggestion_file(&change.uri)),
+        );
+        // Edits of source files don't change the names, opened documents stay valid
+        if config_changes.is_empty() && changes.is_empty() {
+            return;
+        }
+        self.indexer.files_changed(changes).await;
+        self.check_health(None).await;
+        self.republish_diagnostics().await;
+    }
+
+    async fn diagnostic(
+        &self,
+        params: DocumentDiagnosticParams,
+    ) -> Result<DocumentDiagnosticReportResult> {
+        let uri = params.text_document.uri;
+        let (result_id, items) = match self.documents.text(&uri) {
+            Some(content) => {
+                let scopes = self.document_scopes(&uri, &content);
+                let result_id = self.diagnostics_result_id(&scopes, &content);
+                // Editors pull again on every focus change, mostly for unchanged documents
+                if params.previous_result_id.as_ref() == Some(&result_id) {
+                    return Ok(DocumentDiagnosticReportResult::Report(
+                        DocumentDiagnosticReport::Unchanged(
+                            RelatedUnchangedDocumentDiagnosticReport {
+                                related_documents: None,
+                                unchanged_document_diagnostic_report:
+                                    UnchangedDocumentDiagnosticReport { result_id },
+                            },
+                        ),
+                    ));
+                }
+                self.load_document_indexes(&scopes).await;
+                let (_, items) = self.document_diagnostics(&uri, &scopes, &content);
+                self.record_problems(&uri, &items);
+                self.send_summary().await;
+                (Some(result_id), items)
+            }
+            None => (None, vec![]),
         };
+        Ok(DocumentDiagnosticReportResult::Report(
+            DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+                related_documents: None,
+                full_document_diagnostic_report: FullDocumentDiagnosticReport { result_id, items },
+            }),
+        ))
+    }
 
-        let content = match docs.get(&params.text_document_position.text_document.uri) {
-            Some(text) => text,
-            None => {
-                return Ok(None);
+    async fn workspace_diagnostic(
+        &self,
+        params: WorkspaceDiagnosticParams,
+    ) -> Result<WorkspaceDiagnosticReportResult> {
+        self.discover_project().await;
+        let previous: HashMap<Url, String> = params
+            .previous_result_ids
+            .into_iter()
+            .map(|previous| (previous.uri, previous.value))
+            .collect();
+
+        let mut items = vec![];
+        for (uri, source_code) in self.project.files() {
+            // Opened documents are pulled one by one with their unsaved content
+            if self.documents.contains(&uri) {
+                continue;
+            }
+            let scopes = self.document_scopes(&uri, &source_code);
+            let result_id = self.diagnostics_result_id(&scopes, &source_code);
+            if previous.get(&uri) == Some(&result_id) {
+                items.push(WorkspaceDocumentDiagnosticReport::Unchanged(
+                    WorkspaceUnchangedDocumentDiagnosticReport {
+                        uri,
+                        version: None,
+                        unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+                            result_id,
+                        },
+                    },
+                ));
+                continue;
             }
+            self.load_document_indexes(&scopes).await;
+            let diagnostics = self.perform_all_diagnostics(&scopes, &source_code);
+            self.record_problems(&uri, &diagnostics);
+            items.push(WorkspaceDocumentDiagnosticReport::Full(
+                WorkspaceFullDocumentDiagnosticReport {
+                    uri,
+                    version: None,
+                    full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                        result_id: Some(result_id),
+                        items: diagnostics,
+                    },
+                },
+            ));
+        }
+        self.send_summary().await;
+        Ok(WorkspaceDiagnosticReportResult::Report(
+            WorkspaceDiagnosticReport { items },
+        ))
+    }
+
+    /// All entries in the workspace naming the same folder as the one at the position.
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let position = params.text_document_position;
+        let entry = match self
+            .project_entry_at(&position.text_document.uri, position.position)
+            .await
+        {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let locations = self
+            .project
+            .references(&entry)
+            .into_iter()
+            .map(|(location, _)| location)
+            .collect();
+        Ok(Some(locations))
+    }
+
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Result<Option<Vec<SymbolInformation>>> {
+        self.discover_project().await;
+        #[allow(deprecated)]
+        let symbols = self
+            .project
+            .symbols(&params.query)
+            .into_iter()
+            .map(|(location, entry)| SymbolInformation {
+                name: entry.name,
+                kind: SymbolKind::STRING,
+                tags: None,
+                deprecated: None,
+                location,
+                container_name: Some(entry.varname),
+            })
+            .collect();
+        Ok(Some(symbols))
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        let entry = self
+            .project_entry_at(&params.text_document.uri, params.position)
+            .await;
+        Ok(
+            entry.map(|entry| PrepareRenameResponse::RangeWithPlaceholder {
+                range: entry.range,
+                placeholder: entry.name,
+            }),
+        )
+    }
+
+    /// Rename the entry at the position in all files of the workspace naming the same folder,
+    /// the folder itself is left untouched.
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let position = params.text_document_position;
+        let entry = match self
+            .project_entry_at(&position.text_document.uri, position.position)
+            .await
+        {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+        for (location, _) in self.project.references(&entry) {
+            let original = self
+                .project
+                .text(&location.uri)
+                .and_then(|text| edits::text_at(&text, location.range).map(String::from));
+            let edit = edits::replace_string(
+                location.range,
+                original.as_deref().unwrap_or("\""),
+                &params.new_name,
+                self.args.quote_style,
+            );
+            changes.entry(location.uri).or_default().push(edit);
+        }
+        Ok(Some(WorkspaceEdit {
+            changes: Some(changes),
+            ..WorkspaceEdit::default()
+        }))
+    }
+
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let uri = params.text_document.uri;
+        let content = match self.document_text(&uri) {
+            Some(content) => content,
+            None => return Ok(None),
+        };
+
+        let scope = self.document_scope(&uri, &content);
+        self.load_document_index(&scope).await;
+        // Tooltips list the folder contents, which clients resolving them only read on hover
+        let resolve_tooltip = self.supports_inlay_hint_resolve("tooltip");
+        let label = self.labels().detail(self.locale());
+        let hints = LspParser::parse_code(&content, &scope.varname)
+            .into_iter()
+            .filter(|entry| self.is_valid_folder(&scope, entry.text))
+            .map(|entry| {
+                let position = Range::from(MyRange(entry.range, &content)).end;
+                (entry.text, position)
+            })
+            .filter(|(_, position)| {
+                params.range.start <= *position && *position <= params.range.end
+            })
+            .map(|(name, position)| InlayHint {
+                position,
+                label: InlayHintLabel::String(label.clone()),
+                kind: None,
+                text_edits: None,
+                tooltip: if resolve_tooltip {
+                    None
+                } else {
+                    self.folder_tooltip(&uri, name)
+                },
+                padding_left: Some(true),
+                padding_right: None,
+                data: Some(serde_json::json!({ "uri": uri, "name": name })),
+            })
+            .collect();
+        Ok(Some(hints))
+    }
+
+    async fn inlay_hint_resolve(&self, mut hint: InlayHint) -> Result<InlayHint> {
+        let data = hint.data.as_ref();
+        let uri = data
+            .and_then(|data| data["uri"].as_str())
+            .and_then(|uri| Url::parse(uri).ok());
+        let name = data.and_then(|data| data["name"].as_str());
+        if let (Some(uri), Some(name), None) = (uri, name, &hint.tooltip) {
+            hint.tooltip = self.folder_tooltip(&uri, name);
+        }
+        Ok(hint)
+    }
+
+    async fn semantic_tokens_full(
+        &self,
+        params: SemanticTokensParams,
+    ) -> Result<Option<SemanticTokensResult>> {
+        let uri = params.text_document.uri;
+        let data = match self.document_semantic_tokens(&uri).await {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+
+        let result_id = self.store_semantic_tokens(&uri, data.clone());
+        Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
+            result_id: Some(result_id),
+            data,
+        })))
+    }
+
+    async fn semantic_tokens_full_delta(
+        &self,
+        params: SemanticTokensDeltaParams,
+    ) -> Result<Option<SemanticTokensFullDeltaResult>> {
+        let uri = params.text_document.uri;
+        let data = match self.document_semantic_tokens(&uri).await {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+
+        // Without the tokens of the previous result id the client gets all tokens again
+        let previous = self
+            .semantic_tokens
+            .read()
+            .ok()
+            .and_then(|semantic_tokens| {
+                semantic_tokens
+                    .get(&uri)
+                    .filter(|(result_id, _)| *result_id == params.previous_result_id)
+                    .map(|(_, previous)| previous.clone())
+            });
+        let result_id = self.store_semantic_tokens(&uri, data.clone());
+        Ok(Some(match previous {
+            Some(previous) => SemanticTokensFullDeltaResult::TokensDelta(SemanticTokensDelta {
+                result_id: Some(result_id),
+                edits: semantic::delta(&previous, &data),
+            }),
+            None => SemanticTokensFullDeltaResult::Tokens(SemanticTokens {
+                result_id: Some(result_id),
+                data,
+            }),
+        }))
+    }
+
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        if !self.is_enabled(Feature::Completion) {
+            return Ok(None);
+        }
+
+        // A snapshot of the content, so edits arriving meanwhile don't wait for the completion
+        let content = match self.document_text(&params.text_document_position.text_document.uri) {
+            Some(content) => content,
+            None => return Ok(None),
         };
 
+        // Arrays mapped to other directories complete the names of theirs
+        let scopes =
+            self.document_scopes(&params.text_document_position.text_document.uri, &content);
+        let scope = Backend::scope_at(&scopes, &content, params.text_document_position.position);
+        if self.with_index(scope, |index| index.is_empty()) {
+            return Ok(None);
+        }
+
         let all_items: Vec<crate::parser::PositionalText> =
-            LspParser::parse_code(content, &self.args.varname);
+            LspParser::parse_code(&content, &scope.varname);
+        // The syntax tree counts columns in bytes, the position in UTF-16 code units
+        let position = params.text_document_position.position;
+        let column = edits::byte_column(&content, position);
         let all_completions = all_items
             .iter()
             .find(|item| {
-                item.range.start_point.row == params.text_document_position.position.line as usize
-                    && (item.range.start_point.column
-                        < params.text_document_position.position.character as usize
-                        && item.range.end_point.column
-                            > params.text_document_position.position.character as usize)
+                // Unterminated strings run into the next lines, maybe ending at their start
+                item.range.start_point.row == position.line as usize
+                    && item.range.end_point.row == position.line as usize
+                    && (item.range.start_point.column < column
+                        && item.range.end_point.column > column)
             })
-            .map(|_item_at_position| {
-                let completions = Backend::get_files(&self.args.suggestionsdir)
+            .map(|item_at_position| {
+                // Text between the opening quote and the cursor
+                let typed = column - item_at_position.range.start_point.column - 1;
+                let prefix = item_at_position
+                    .text
+                    .get(..typed)
+                    .unwrap_or(item_at_position.text);
+
+                // Replace the whole content between the quotes, not just the typed prefix, and
+                // the quotes as well if the configured quote style differs
+                let range = item_at_position.range;
+                let original = content.get(range.start_byte..range.end_byte).unwrap_or("");
+                let quote_style = self.args.quote_style;
+                let requote = !original.starts_with(quote_style.quote_char(original));
+                let quoted = Range::from(MyRange(range, &content));
+                let replace = if requote {
+                    quoted
+                } else {
+                    Range::new(
+                        Position::new(quoted.start.line, quoted.start.character + 1),
+                        Position::new(quoted.end.line, quoted.end.character - 1),
+                    )
+                };
+
+                // Names of the other entries would only add duplicates
+                let listed: HashSet<&str> = all_items
                     .iter()
-                    .map(|name| name.to_completionitem().unwrap())
-                    .collect::<Vec<CompletionItem>>();
-                CompletionResponse::Array(completions)
+                    .filter(|item| item.range != item_at_position.range)
+                    .map(|item| item.text)
+                    .collect();
+                let (complete_listed, sibling_ranking) = self
+                    .config
+                    .read()
+                    .map(|config| (config.complete_listed, config.sibling_ranking()))
+                    .unwrap_or((false, true));
+                let labels = self.labels();
+
+                let support = self.client_support();
+
+                let mut completions = self.with_index(scope, |index| {
+                    index
+                        .prefix(prefix)
+                        .filter(|name| complete_listed || !listed.contains(name))
+                        .take(MAX_COMPLETIONS + 1)
+                        .filter_map(|name| {
+                            let new_text = if requote {
+                                quote_style.quote(name, original)
+                            } else {
+                                name.to_string()
+                            };
+                            let mut item = name.to_string().to_completionitem(
+                                &labels,
+                                self.locale(),
+                                replace,
+                                new_text,
+                            )?;
+                            // The README of the folder is read in `completion_resolve`, only for
+                            // the item the client shows the details of
+                            item.data = Some(serde_json::json!({
+                                "uri": params.text_document_position.text_document.uri,
+                                "varname": scope.varname,
+                                "name": name,
+                            }));
+                            if listed.contains(name) {
+                                if support.completion_tags {
+                                    item.tags = Some(vec![CompletionItemTag::DEPRECATED]);
+                                } else if support.completion_deprecated {
+                                    item.deprecated = Some(true);
+                                }
+                            }
+                            Some(item)
+                        })
+                        .collect::<Vec<CompletionItem>>()
+                });
+                // Names next to the other entries are likely the sibling being added
+                if sibling_ranking {
+                    let siblings: Vec<&str> = listed.iter().copied().collect();
+                    let mut ranked: Vec<(usize, CompletionItem)> = completions
+                        .into_iter()
+                        .map(|item| (matcher::sibling_proximity(&item.label, &siblings), item))
+                        .collect();
+                    if ranked.iter().any(|(proximity, _)| *proximity > 0) {
+                        ranked.sort_by_key(|(proximity, _)| std::cmp::Reverse(*proximity));
+                        for (rank, (_, item)) in ranked.iter_mut().enumerate() {
+                            item.sort_text = Some(format!("{:05}", rank));
+                        }
+                    }
+                    completions = ranked.into_iter().map(|(_, item)| item).collect();
+                }
+                let is_incomplete = completions.len() > MAX_COMPLETIONS;
+                completions.truncate(MAX_COMPLETIONS);
+
+                CompletionResponse::List(CompletionList {
+                    is_incomplete,
+                    items: completions,
+                })
             });
 
         Ok(all_completions)
     }
 
+    async fn completion_resolve(&self, mut item: CompletionItem) -> Result<CompletionItem> {
+        let data = item.data.as_ref();
+        let uri = data
+            .and_then(|data| data["uri"].as_str())
+            .and_then(|uri| Url::parse(uri).ok());
+        let varname = data.and_then(|data| data["varname"].as_str());
+        let name = data.and_then(|data| data["name"].as_str());
+        if let (Some(uri), Some(varname), Some(name), None) =
+            (uri, varname, name, &item.documentation)
+        {
+            let readme = self.folder_readme(&uri, varname, name);
+            item.documentation = readme.map(|excerpt| {
+                if self.client_support().markdown_completion_documentation {
+                    Documentation::MarkupContent(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value: excerpt,
+                    })
+                } else {
+                    Documentation::String(capabilities::plain_text(&excerpt))
+                }
+            });
+        }
+        Ok(item)
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let position = params.text_document_position_params.position;
+        let content =
+            match self.document_text(&params.text_document_position_params.text_document.uri) {
+                Some(content) => content,
+                None => return Ok(None),
+            };
+
+        let scope = self.document_scope(
+            &params.text_document_position_params.text_document.uri,
+            &content,
+        );
+        // The syntax tree counts columns in bytes, the position in UTF-16 code units
+        let column = edits::byte_column(&content, position);
+        let identifier = LspParser::parse_identifiers(&content, &scope.varname)
+            .into_iter()
+            .find(|identifier| {
+                identifier.range.start_point.row == position.line as usize
+                    && identifier.range.start_point.column <= column
+                    && identifier.range.end_point.column >= column
+            });
+
+        let summary = self.validation_summary(&scope, &content);
+        let contents = if self.client_support().markdown_hover {
+            MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: summary,
+            }
+        } else {
+            MarkupContent {
+                kind: MarkupKind::PlainText,
+                value: capabilities::plain_text(&summary),
+            }
+        };
+        Ok(identifier.map(|identifier| Hover {
+            contents: HoverContents::Markup(contents),
+            range: Some(MyRange(identifier.range, &content).into()),
+        }))
+    }
+
+    async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        let content = match self.document_text(&params.text_document.uri) {
+            Some(content) => content,
+            None => return Ok(None),
+        };
+
+        // The command with the folder contents is computed in `code_lens_resolve`, only for the
+        // lenses the client shows
+        let scope = self.document_scope(&params.text_document.uri, &content);
+        let lenses = LspParser::parse_code(&content, &scope.varname)
+            .into_iter()
+            .filter(|used_folder| self.is_valid_folder(&scope, used_folder.text))
+            .map(|used_folder| CodeLens {
+                range: MyRange(used_folder.range, &content).into(),
+                command: None,
+                data: Some(serde_json::json!({
+                    "uri": params.text_document.uri,
+                    "name": used_folder.text,
+                })),
+            })
+            .collect();
+
+        Ok(Some(lenses))
+    }
+
+    async fn code_lens_resolve(&self, mut lens: CodeLens) -> Result<CodeLens> {
+        let data = lens.data.as_ref();
+        let uri = data
+            .and_then(|data| data["uri"].as_str())
+            .and_then(|uri| Url::parse(uri).ok());
+        let name = data.and_then(|data| data["name"].as_str());
+        if let (Some(uri), Some(name), None) = (uri, name, &lens.command) {
+            let mut command = Backend::reveal_folder_command(name, &uri, self.locale());
+            if let Some((count, size)) = self.folder_summary(&uri, name) {
+                command.title =
+                    Message::RevealFolderSummary { name, count, size }.text(self.locale());
+            }
+            lens.command = Some(command);
+        }
+        Ok(lens)
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<serde_json::Value>> {
+        match params.command.as_str() {
+            commands::REVEAL_FOLDER => self.reveal_folder(&params.arguments).await,
+            commands::CREATE_FOLDER => self.create_folder(&params.arguments).await,
+            commands::SYNC_FOLDERS => {
+                let document = params
+                    .arguments
+                    .first()
+                    .and_then(|arg| arg.as_str())
+                    .and_then(|uri| Url::parse(uri).ok());
+                match document {
+                    Some(uri) => Ok(self
+                        .sync_folders(uri, commands::is_dry_run(&params.arguments))
+                        .await),
+                    None => Err(tower_lsp::jsonrpc::Error::invalid_params(
+                        "expected a document URI",
+                    )),
+                }
+            }
+            commands::CREATE_ALL_MISSING => {
+                let document = params
+                    .arguments
+                    .first()
+                    .and_then(|arg| arg.as_str())
+                    .and_then(|uri| Url::parse(uri).ok());
+                match document {
+                    Some(uri) => {
+                        self.create_all_missing(uri, commands::is_dry_run(&params.arguments))
+                            .await
+                    }
+                    None => Err(tower_lsp::jsonrpc::Error::invalid_params(
+                        "expected a document URI",
+                    )),
+                }
+            }
+            commands::FIND_ALL_INVALID => {
+                let locations = self.find_all_invalid().await?;
+                Ok(serde_json::to_value(locations).ok())
+            }
+            _ => Err(tower_lsp::jsonrpc::Error::method_not_found()),
+        }
+    }
+
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
-        let docs = self.documents.write();
         let uri = params.text_document.uri;
+        let language = language::detect(
+            &params.text_document.language_id,
+            Path::new(uri.path()),
+            &params.text_document.text,
+        );
+        if !self.is_tracked_document(
+            &uri,
+            &params.text_document.language_id,
+            language.as_deref(),
+            &params.text_document.text,
+        ) {
+            return;
+        }
 
-        docs.unwrap()
-            .insert(uri.to_owned(), params.text_document.text.clone());
+        let text: Arc<str> = params.text_document.text.as_str().into();
+        self.documents.open(
+            uri.clone(),
+            text.clone(),
+            params.text_document.version,
+            language,
+        );
+        self.update_project(&uri, text);
 
-        let push_diagnostics = {
-            let push_diag = self.push_diagnostics.read().unwrap();
-            *push_diag
-        };
+        let push_diagnostics = self.pushes_diagnostics();
 
         if push_diagnostics {
-            self.client
-                .publish_diagnostics(
-                    uri,
-                    self.perform_diagnostics(params.text_document.text.as_str()),
-                    None,
-                )
+            let scopes = self.document_scopes(&uri, &params.text_document.text);
+            self.load_document_indexes(&scopes).await;
+            let (_, diagnostics) =
+                self.document_diagnostics(&uri, &scopes, &params.text_document.text);
+            self.publish(uri, diagnostics, Some(params.text_document.version))
                 .await;
+            self.send_summary().await;
         }
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
-        {
-            // capabilities are configured with TextDocumentSyncKind::FULL, so we know that the first change is the full content
-            let text = match params.content_changes.first() {
-                Some(change) => change.text.clone(),
-                None => return,
-            };
-
-            if let Ok(mut docs) = self.documents.write() {
-                docs.insert(params.text_document.uri.clone(), text);
-            }
+        // capabilities are configured with TextDocumentSyncKind::FULL, so we know that the first change is the full content
+        let text: Arc<str> = match params.content_changes.first() {
+            Some(change) => change.text.as_str().into(),
+            None => return,
+        };
+        // Documents filtered out in `did_open` are not tracked, changes for an older version than
+        // the stored one arrived late
+        let stored = self.documents.change(
+            &params.text_document.uri,
+            text.clone(),
+            params.text_document.version,
+        );
+        if !stored {
+            return;
         }
+        self.update_project(&params.text_document.uri, text.clone());
 
-        let push_diagnostics = {
-            let push_diag = self.push_diagnostics.read().unwrap();
-            *push_diag
-        };
+        let push_diagnostics = self.pushes_diagnostics();
 
+        let scopes = self.document_scopes(&params.text_document.uri, &text);
         if push_diagnostics {
-            self.client
-                .publish_diagnostics(
-                    params.text_document.uri,
-                    self.perform_diagnostics(params.content_changes.first().unwrap().text.as_str()),
-                    None,
-                )
-                .await;
+            self.load_document_indexes(&scopes).await;
+        }
+
+        // Skip publishing when a newer version arrived in the meantime
+        if push_diagnostics
+            && self
+                .documents
+                .is_current_version(&params.text_document.uri, params.text_document.version)
+        {
+            let (_, diagnostics) =
+                self.document_diagnostics(&params.text_document.uri, &scopes, &text);
+            self.publish(
+                params.text_document.uri,
+                diagnostics,
+                Some(params.text_document.version),
+            )
+            .await;
+            self.send_summary().await;
         }
     }
 
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
-        let docs = self.documents.write();
-        docs.unwrap().remove(&params.text_document.uri);
+        let closed = self.documents.close(&params.text_document.uri);
+        if let Some(doc) = &closed {
+            self.unload_document_indexes(&params.text_document.uri, &doc.text);
+        }
+        if let Ok(mut semantic_tokens) = self.semantic_tokens.write() {
+            semantic_tokens.remove(&params.text_document.uri);
+        }
+        if let Ok(mut cache) = self.diagnostics_cache.write() {
+            cache.remove(&params.text_document.uri);
+        }
+        // Unsaved changes are gone, the file on disk is part of the project again
+        match self.document_text(&params.text_document.uri) {
+            Some(source_code) => self.update_project(&params.text_document.uri, source_code),
+            None => self.project.remove(&params.text_document.uri),
+        }
+
+        let push_diagnostics = self.pushes_diagnostics();
+
+        if let Some(doc) = closed {
+            if self.args.keep_diagnostics_on_close {
+                return;
+            }
+            let removed = self
+                .problems
+                .write()
+                .is_ok_and(|mut problems| problems.remove(&params.text_document.uri).is_some());
+            if push_diagnostics {
+                self.client
+                    .publish_diagnostics(params.text_document.uri, vec![], Some(doc.version))
+                    .await;
+            }
+            if removed {
+                self.send_summary().await;
+            }
+        }
     }
 
     async fn code_action(
         &self,
         params: CodeActionParams,
     ) -> Result<Option<Vec<CodeActionOrCommand>>> {
-        let folders = Backend::get_files(&self.args.suggestionsdir);
-        let available_folders: Vec<&str> = folders.iter().map(|s| s.as_str()).collect();
+        if !self.is_enabled(Feature::CodeAction) {
+            return Ok(None);
+        }
+
         let mut actions: Vec<CodeActionOrCommand> = Vec::new();
+        let content = self.document_text(&params.text_document.uri);
+        let scopes = self.document_scopes(
+            &params.text_document.uri,
+            content.as_deref().unwrap_or_default(),
+        );
 
-        // Loop through diagnostics in the current document
-        for diagnostic in &params.context.diagnostics {
-            let data = diagnostic
-                .data
-                .as_ref()
-                .unwrap_or(&serde_json::to_value("").unwrap())
-                .clone();
-            let user_input = data.as_str().unwrap();
+        let source_code = content.as_deref().unwrap_or_default();
 
-            let best_matches = Backend::get_best_matches(user_input, &available_folders, 15);
+        // Best match edits of all diagnostics within the selection, offered as one action
+        let mut bulk_edits: Vec<TextEdit> = Vec::new();
+        let mut bulk_diagnostics: Vec<Diagnostic> = Vec::new();
 
-            for best_match in best_matches {
-                let edit = TextEdit {
-                    range: diagnostic.range,
-                    new_text: format!("\"{}\"", best_match),
-                };
+        for diagnostic in &params.context.diagnostics {
+            let scope = Backend::scope_at(&scopes, source_code, diagnostic.range.start);
+            let suggestions = |user_input: &str| self.scored_suggestions(scope, user_input, 15);
+            let fix_context = FixContext {
+                source_code,
+                varname: &scope.varname,
+                locale: self.locale(),
+                quote_style: self.args.quote_style,
+                algorithm: self.args.matcher,
+                suggestions: &suggestions,
+            };
+            let fixes = match rules::of(diagnostic) {
+                Some(rule) => rule.fixes(diagnostic, &fix_context),
+                None => continue,
+            };
 
-                // Create a workspace edit to apply the text edit
-                let edit = WorkspaceEdit {
-                    changes: Some(
-                        vec![(params.text_document.uri.clone(), vec![edit])]
-                            .into_iter()
-                            .collect(),
-                    ),
-                    ..Default::default()
-                };
+            let in_selection = params.range.start <= diagnostic.range.start
+                && diagnostic.range.end <= params.range.end;
+            if let Some(best_match) = fixes
+                .first()
+                .filter(|fix| in_selection && fix.score.is_some())
+            {
+                bulk_edits.extend(best_match.edits.iter().cloned());
+                bulk_diagnostics.push(diagnostic.clone());
+            }
 
-                // Build the code action with the edit
-                let code_action = CodeAction {
-                    title: format!("Use folder {}", best_match),
+            for fix in fixes {
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: fix.title,
                     kind: Some(CodeActionKind::QUICKFIX),
                     diagnostics: Some(vec![diagnostic.clone()]),
-                    edit: Some(edit),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(HashMap::from([(
+                            params.text_document.uri.clone(),
+                            fix.edits,
+                        )])),
+                        ..Default::default()
+                    }),
+                    is_preferred: fix.is_preferred.then_some(true),
                     ..Default::default()
-                };
+                }));
+            }
+        }
 
-                actions.push(tower_lsp::lsp_types::CodeActionOrCommand::CodeAction(
-                    code_action,
-                ));
+        if bulk_diagnostics.len() > 1 {
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title: Message::ReplaceInvalidEntries(bulk_diagnostics.len()).text(self.locale()),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(bulk_diagnostics),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(
+                        params.text_document.uri.clone(),
+                        bulk_edits,
+                    )])),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }));
+        }
+
+        // Fixes safe to apply unattended, for all problems of the document rather than the ones
+        // in the context, as clients requesting `source.fixAll` on save may not send any
+        let only = params.context.only.as_deref();
+        if capabilities::code_action_requested(only, Some(&CodeActionKind::SOURCE_FIX_ALL)) {
+            let (_, diagnostics) =
+                self.document_diagnostics(&params.text_document.uri, &scopes, source_code);
+            let fixes = diagnostics
+                .iter()
+                .filter_map(|diagnostic| {
+                    let fix_all_context = FixContext {
+                        source_code,
+                        varname: &Backend::scope_at(&scopes, source_code, diagnostic.range.start)
+                            .varname,
+                        locale: self.locale(),
+                        quote_style: self.args.quote_style,
+                        algorithm: self.args.matcher,
+                        suggestions: &|_| vec![],
+                    };
+                    rules::of(diagnostic).map(|rule| rule.fixes(diagnostic, &fix_all_context))
+                })
+                .flatten()
+                .filter(|fix| fix.fix_all);
+
+            // Fixes editing the same entries conflict, the ones left out are applied next time
+            let mut fix_all_edits: Vec<TextEdit> = Vec::new();
+            for fix in fixes {
+                let overlaps = fix.edits.iter().any(|edit| {
+                    fix_all_edits.iter().any(|taken| {
+                        edit.range.start < taken.range.end && taken.range.start < edit.range.end
+                    })
+                });
+                if !overlaps {
+                    fix_all_edits.extend(fix.edits);
+                }
+            }
+            if !fix_all_edits.is_empty() {
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: Message::FixAll.text(self.locale()),
+                    kind: Some(CodeActionKind::SOURCE_FIX_ALL),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(HashMap::from([(
+                            params.text_document.uri.clone(),
+                            fix_all_edits,
+                        )])),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }));
+            }
+        }
+
+        // Offer to reveal existing folders referenced within the requested range, and to create
+        // the missing ones in suggestions directories
+        if let Some(content) = content {
+            let used_folders = scopes.iter().flat_map(|scope| {
+                LspParser::parse_code(&content, &scope.varname)
+                    .into_iter()
+                    .map(move |used_folder| (scope, used_folder))
+            });
+            for (scope, used_folder) in used_folders {
+                let in_range = used_folder.range.start_point.row <= params.range.end.line as usize
+                    && used_folder.range.end_point.row >= params.range.start.line as usize;
+                if !in_range {
+                    continue;
+                }
+                let command = if self.is_valid_folder(scope, used_folder.text) {
+                    Backend::reveal_folder_command(
+                        used_folder.text,
+                        &params.text_document.uri,
+                        self.locale(),
+                    )
+                } else if matches!(scope.provider, Provider::Directory { .. })
+                    && paths::is_folder_name(used_folder.text)
+                {
+                    Backend::create_folder_command(
+                        used_folder.text,
+                        &params.text_document.uri,
+                        self.locale(),
+                    )
+                } else {
+                    continue;
+                };
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: command.title.clone(),
+                    command: Some(command),
+                    ..Default::default()
+                }));
             }
         }
 
+        // Drop the kinds the client didn't ask for
+        actions.retain(|action| match action {
+            CodeActionOrCommand::CodeAction(action) => {
+                capabilities::code_action_requested(only, action.kind.as_ref())
+            }
+            CodeActionOrCommand::Command(_) => only.is_none(),
+        });
+
         Ok(Some(actions))
     }
 }
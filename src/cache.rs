@@ -0,0 +1,125 @@
+//! Names of providers persisted between sessions, so huge suggestion directories needn't be
+//! read again on startup.
+//!
+//! An entry is keyed by the provider and stores the modification time and size of the
+//! directory or file the names come from, it is only used while they are unchanged. Recursive
+//! directories, commands and lists aren't cached, their changes can't be told by one path.
+
+use crate::metrics;
+use crate::provider::Provider;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Entry {
+    location: String,
+    fingerprint: String,
+    names: Vec<String>,
+}
+
+/// The names of `provider`, from the cache in `dir` if they are unchanged since they were
+/// stored, otherwise read from the provider and stored.
+pub fn names(dir: &Path, provider: &Provider) -> Result<Vec<String>, String> {
+    let Some(source) = source_path(provider) else {
+        return provider.names();
+    };
+    let location = provider.location();
+    let fingerprint = fingerprint(source);
+    let path = entry_path(dir, &location);
+
+    let cached = fs::read(&path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<Entry>(&bytes).ok())
+        .filter(|entry| {
+            entry.location == location && Some(&entry.fingerprint) == fingerprint.as_ref()
+        });
+    metrics::record_cache("index", cached.is_some());
+    if let Some(entry) = cached {
+        return Ok(entry.names);
+    }
+
+    let names = provider.names()?;
+    if let Some(fingerprint) = fingerprint {
+        let entry = Entry {
+            location,
+            fingerprint,
+            names,
+        };
+        // A cache which can't be written only costs the next startup time
+        if let Ok(json) = serde_json::to_vec(&entry) {
+            let _ = fs::create_dir_all(dir).and_then(|_| fs::write(&path, json));
+        }
+        return Ok(entry.names);
+    }
+    Ok(names)
+}
+
+/// The directory or file the names of `provider` are read from, if its changes tell whether
+/// the names changed.
+fn source_path(provider: &Provider) -> Option<&Path> {
+    match provider {
+        Provider::Directory {
+            dir,
+            recursive: false,
+        } => Some(Path::new(dir)),
+        Provider::Manifest(path) => Some(path),
+        Provider::Sqlite { path, .. } => Some(path),
+        Provider::Directory { .. } | Provider::Command { .. } | Provider::List(_) => None,
+    }
+}
+
+/// Modification time and size of `path`, `None` if it doesn't exist.
+fn fingerprint(path: &Path) -> Option<String> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(format!("{}:{}", modified.as_nanos(), metadata.len()))
+}
+
+fn entry_path(dir: &Path, location: &str) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    location.hash(&mut hasher);
+    dir.join(format!("{:016x}.json", hasher.finish()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names() {
+        let root = std::env::temp_dir().join(format!("tsm-cache-{}", std::process::id()));
+        let folders = root.join("folders");
+        let cache = root.join("cache");
+        fs::create_dir_all(folders.join("assets")).unwrap();
+        let provider = Provider::Directory {
+            dir: folders.to_string_lossy().into_owned(),
+            recursive: false,
+        };
+
+        assert_eq!(vec!["assets"], names(&cache, &provider).unwrap());
+        let path = entry_path(&cache, &provider.location());
+        let entry: Entry = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(vec!["assets"], entry.names);
+
+        // Served from the cache while the fingerprint matches
+        let cached = Entry {
+            names: vec!["cached".into()],
+            ..entry
+        };
+        fs::write(&path, serde_json::to_vec(&cached).unwrap()).unwrap();
+        assert_eq!(vec!["cached"], names(&cache, &provider).unwrap());
+
+        let stale = Entry {
+            fingerprint: "0:0".into(),
+            ..cached
+        };
+        fs::write(&path, serde_json::to_vec(&stale).unwrap()).unwrap();
+        assert_eq!(vec!["assets"], names(&cache, &provider).unwrap());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
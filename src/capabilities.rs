@@ -0,0 +1,266 @@
+use tower_lsp::lsp_types::{
+    ClientCapabilities, CodeActionKind, CompletionItemTag, DiagnosticTag, DocumentSelector,
+    MarkupKind, Registration, Unregistration,
+};
+
+/// Kinds of the code actions the server provides.
+pub const CODE_ACTION_KINDS: [CodeActionKind; 2] =
+    [CodeActionKind::QUICKFIX, CodeActionKind::SOURCE_FIX_ALL];
+
+/// Features which can be enabled or disabled at runtime through dynamic registration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Feature {
+    Completion,
+    CodeAction,
+    WatchedFiles,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 3] = [
+        Feature::Completion,
+        Feature::CodeAction,
+        Feature::WatchedFiles,
+    ];
+
+    /// Registration id, which is also used to unregister the feature again.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Feature::Completion => "tsm-completion",
+            Feature::CodeAction => "tsm-code-action",
+            Feature::WatchedFiles => "tsm-watched-files",
+        }
+    }
+
+    pub fn method(&self) -> &'static str {
+        match self {
+            Feature::Completion => "textDocument/completion",
+            Feature::CodeAction => "textDocument/codeAction",
+            Feature::WatchedFiles => "workspace/didChangeWatchedFiles",
+        }
+    }
+
+    /// Key of the feature toggle in the `workspace/didChangeConfiguration` settings.
+    pub fn setting(&self) -> &'static str {
+        match self {
+            Feature::Completion => "completion",
+            Feature::CodeAction => "codeActions",
+            Feature::WatchedFiles => "watchFiles",
+        }
+    }
+
+    /// Whether the client is able to register this feature dynamically.
+    pub fn supports_dynamic_registration(&self, capabilities: &ClientCapabilities) -> bool {
+        let dynamic_registration = match self {
+            Feature::Completion => capabilities
+                .text_document
+                .as_ref()
+                .and_then(|td| td.completion.as_ref())
+                .and_then(|c| c.dynamic_registration),
+            Feature::CodeAction => capabilities
+                .text_document
+                .as_ref()
+                .and_then(|td| td.code_action.as_ref())
+                .and_then(|c| c.dynamic_registration),
+            Feature::WatchedFiles => capabilities
+                .workspace
+                .as_ref()
+                .and_then(|ws| ws.did_change_watched_files.as_ref())
+                .and_then(|c| c.dynamic_registration),
+        };
+
+        dynamic_registration.unwrap_or(false)
+    }
+
+    pub fn registration(
+        &self,
+        selector: &DocumentSelector,
+        watch_globs: &[String],
+    ) -> Registration {
+        let register_options = match self {
+            Feature::Completion => serde_json::json!({
+                "documentSelector": selector,
+                "resolveProvider": true,
+            }),
+            Feature::CodeAction => serde_json::json!({
+                "documentSelector": selector,
+                "codeActionKinds": CODE_ACTION_KINDS,
+            }),
+            Feature::WatchedFiles => serde_json::json!({
+                "watchers": watch_globs
+                    .iter()
+                    .map(|glob| serde_json::json!({ "globPattern": glob }))
+                    .collect::<Vec<_>>(),
+            }),
+        };
+
+        Registration {
+            id: self.id().into(),
+            method: self.method().into(),
+            register_options: Some(register_options),
+        }
+    }
+
+    pub fn unregistration(&self) -> Unregistration {
+        Unregistration {
+            id: self.id().into(),
+            method: self.method().into(),
+        }
+    }
+}
+
+/// Optional parts of responses the client is able to render, the others are downgraded or left
+/// out instead of showing up as literal text.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClientSupport {
+    /// Markdown hover contents, plain text otherwise
+    pub markdown_hover: bool,
+    /// Markdown documentation of completion items, plain text otherwise
+    pub markdown_completion_documentation: bool,
+    /// The deprecated tag of completion items
+    pub completion_tags: bool,
+    /// The older deprecated flag of completion items, used without tag support
+    pub completion_deprecated: bool,
+    /// Tags of diagnostics the client knows
+    pub diagnostic_tags: Vec<DiagnosticTag>,
+}
+
+impl ClientSupport {
+    pub fn new(capabilities: &ClientCapabilities) -> Self {
+        let text_document = capabilities.text_document.as_ref();
+        let completion_item = text_document
+            .and_then(|td| td.completion.as_ref())
+            .and_then(|c| c.completion_item.as_ref());
+
+        Self {
+            markdown_hover: text_document
+                .and_then(|td| td.hover.as_ref())
+                .and_then(|h| h.content_format.as_ref())
+                .is_some_and(|formats| formats.contains(&MarkupKind::Markdown)),
+            markdown_completion_documentation: completion_item
+                .and_then(|item| item.documentation_format.as_ref())
+                .is_some_and(|formats| formats.contains(&MarkupKind::Markdown)),
+            completion_tags: completion_item
+                .and_then(|item| item.tag_support.as_ref())
+                .is_some_and(|tags| tags.value_set.contains(&CompletionItemTag::DEPRECATED)),
+            completion_deprecated: completion_item
+                .and_then(|item| item.deprecated_support)
+                .unwrap_or(false),
+            diagnostic_tags: text_document
+                .and_then(|td| td.publish_diagnostics.as_ref())
+                .and_then(|p| p.tag_support.as_ref())
+                .map(|tags| tags.value_set.clone())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Plain text of the `markdown` used by the server, without emphasis and code spans.
+pub fn plain_text(markdown: &str) -> String {
+    markdown.replace("**", "").replace('`', "")
+}
+
+/// Read the feature toggle from the client settings, accepting both `{"tsm": {...}}` and flat objects.
+pub fn feature_setting(settings: &serde_json::Value, feature: Feature) -> Option<bool> {
+    let settings = settings.get("tsm").unwrap_or(settings);
+    settings.get(feature.setting()).and_then(|v| v.as_bool())
+}
+
+/// Whether a code action of `kind` was requested by the `only` filter of the code action context.
+///
+/// Kinds are hierarchical, requesting `refactor` includes `refactor.extract`. Actions without
+/// a kind are only returned if there is no filter.
+pub fn code_action_requested(
+    only: Option<&[CodeActionKind]>,
+    kind: Option<&CodeActionKind>,
+) -> bool {
+    let only = match only {
+        Some(only) => only,
+        None => return true,
+    };
+    let kind = match kind {
+        Some(kind) => kind.as_str(),
+        None => return false,
+    };
+
+    only.iter().any(|requested| {
+        kind == requested.as_str()
+            || kind
+                .strip_prefix(requested.as_str())
+                .is_some_and(|rest| rest.starts_with('.'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_action_requested() {
+        let quickfix = CodeActionKind::QUICKFIX;
+        let source = [CodeActionKind::SOURCE];
+
+        assert!(code_action_requested(None, Some(&quickfix)));
+        assert!(code_action_requested(None, None));
+        assert!(!code_action_requested(Some(&source), Some(&quickfix)));
+        assert!(!code_action_requested(Some(&source), None));
+        assert!(code_action_requested(
+            Some(&source),
+            Some(&CodeActionKind::SOURCE_FIX_ALL)
+        ));
+        assert!(code_action_requested(
+            Some(&[CodeActionKind::QUICKFIX]),
+            Some(&quickfix)
+        ));
+    }
+
+    #[test]
+    fn test_client_support() {
+        let capabilities: ClientCapabilities = serde_json::from_value(serde_json::json!({
+            "textDocument": {
+                "hover": { "contentFormat": ["markdown", "plaintext"] },
+                "completion": { "completionItem": { "deprecatedSupport": true } },
+                "publishDiagnostics": { "tagSupport": { "valueSet": [2] } },
+            }
+        }))
+        .unwrap();
+
+        let support = ClientSupport::new(&capabilities);
+        assert!(support.markdown_hover);
+        assert!(!support.completion_tags);
+        assert!(support.completion_deprecated);
+        assert_eq!(vec![DiagnosticTag::DEPRECATED], support.diagnostic_tags);
+        assert_eq!(
+            ClientSupport::default(),
+            ClientSupport::new(&ClientCapabilities::default())
+        );
+        assert_eq!("folders: 1 entries", plain_text("**folders**: 1 entries"));
+    }
+
+    #[test]
+    fn test_feature_setting() {
+        let nested = serde_json::json!({ "tsm": { "completion": false } });
+        let flat = serde_json::json!({ "codeActions": true });
+
+        assert_eq!(Some(false), feature_setting(&nested, Feature::Completion));
+        assert_eq!(Some(true), feature_setting(&flat, Feature::CodeAction));
+        assert_eq!(None, feature_setting(&flat, Feature::WatchedFiles));
+    }
+
+    #[test]
+    fn test_registration() {
+        let globs = [
+            "/project/assets/*".to_string(),
+            "**/.tsm-ls.toml".to_string(),
+        ];
+        let registration = Feature::WatchedFiles.registration(&vec![], &globs);
+        assert_eq!(
+            Some(serde_json::json!({
+                "watchers": [
+                    { "globPattern": "/project/assets/*" },
+                    { "globPattern": "**/.tsm-ls.toml" },
+                ],
+            })),
+            registration.register_options
+        );
+    }
+}
@@ -0,0 +1,650 @@
+use crate::cache;
+use crate::cli::{CheckArgs, CliArgs};
+use crate::config::Config;
+use crate::diagnostics::DiagnosticEngine;
+use crate::grammar;
+use crate::i18n::Locale;
+use crate::index::SuggestionIndex;
+use crate::indexer;
+use crate::parser::LspParser;
+use crate::paths;
+use crate::provider::Provider;
+use crate::validator;
+use clap::ValueEnum;
+use globset::{GlobBuilder, GlobSetBuilder};
+use ignore::WalkBuilder;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+
+/// Output format of the `check` subcommand.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// One line per diagnostic followed by a summary
+    #[default]
+    Text,
+    /// JUnit XML report with a test case per file
+    Junit,
+}
+
+/// Diagnostics of a single checked file.
+#[derive(Debug)]
+pub struct FileReport {
+    pub filename: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Failure policy of the `check` subcommand, counts diagnostics and decides the exit code.
+#[derive(Debug, Default)]
+pub struct Reporter {
+    /// Fail if there are more warnings than this
+    pub max_warnings: Option<usize>,
+    /// Diagnostic codes reported as errors regardless of their severity
+    pub error_on: Vec<String>,
+    /// Report all warnings as errors
+    pub warnings_as_errors: bool,
+    errors: usize,
+    warnings: usize,
+    failed: bool,
+}
+
+impl From<&CheckArgs> for Reporter {
+    fn from(check: &CheckArgs) -> Self {
+        Reporter {
+            max_warnings: check.max_warnings,
+            error_on: check.error_on.clone(),
+            warnings_as_errors: check.warnings_as_errors,
+            ..Reporter::default()
+        }
+    }
+}
+
+impl Reporter {
+    fn is_error_code(&self, diagnostic: &Diagnostic) -> bool {
+        match &diagnostic.code {
+            Some(NumberOrString::Number(code)) => self.error_on.contains(&code.to_string()),
+            Some(NumberOrString::String(code)) => self.error_on.contains(code),
+            None => false,
+        }
+    }
+
+    /// Promote the diagnostics of `report` according to the policy and count them.
+    pub fn add(&mut self, report: &mut FileReport) {
+        for diagnostic in &mut report.diagnostics {
+            let mut severity = diagnostic.severity.unwrap_or(DiagnosticSeverity::ERROR);
+            if self.is_error_code(diagnostic)
+                || (self.warnings_as_errors && severity == DiagnosticSeverity::WARNING)
+            {
+                severity = DiagnosticSeverity::ERROR;
+            }
+            diagnostic.severity = Some(severity);
+
+            match severity {
+                DiagnosticSeverity::ERROR => self.errors += 1,
+                DiagnosticSeverity::WARNING => self.warnings += 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// Record a file that could not be checked at all.
+    pub fn fail(&mut self) {
+        self.failed = true;
+    }
+
+    pub fn errors(&self) -> usize {
+        self.errors
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.warnings
+    }
+
+    /// Whether the warning count exceeds `max_warnings`.
+    pub fn too_many_warnings(&self) -> bool {
+        self.max_warnings.is_some_and(|max| self.warnings > max)
+    }
+
+    /// 2 if a file could not be checked, 1 if the policy is violated, 0 otherwise.
+    pub fn exit_code(&self) -> i32 {
+        if self.failed {
+            2
+        } else if self.errors > 0 || self.too_many_warnings() {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+/// Format a diagnostic like `app.ts:3:5: error: message [100]`, with one-based positions.
+pub fn format_diagnostic(filename: &str, diagnostic: &Diagnostic) -> String {
+    let severity = match diagnostic.severity {
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        Some(DiagnosticSeverity::INFORMATION) => "info",
+        Some(DiagnosticSeverity::HINT) => "hint",
+        _ => "error",
+    };
+    let code = match &diagnostic.code {
+        Some(NumberOrString::Number(code)) => format!(" [{}]", code),
+        Some(NumberOrString::String(code)) => format!(" [{}]", code),
+        None => String::new(),
+    };
+
+    format!(
+        "{}:{}:{}: {}: {}{}",
+        filename,
+        diagnostic.range.start.line + 1,
+        diagnostic.range.start.character + 1,
+        severity,
+        diagnostic.message,
+        code
+    )
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Render `reports` as a JUnit XML test suite, every file is a test case with one failure per
+/// diagnostic.
+pub fn junit_report(reports: &[FileReport]) -> String {
+    let failures: usize = reports.iter().map(|report| report.diagnostics.len()).sum();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"tsm-language-server\" tests=\"{}\" failures=\"{}\">\n",
+        reports.len(),
+        failures
+    ));
+    xml.push_str(&format!(
+        "  <testsuite name=\"tsm-language-server\" tests=\"{}\" failures=\"{}\">\n",
+        reports.len(),
+        failures
+    ));
+
+    for report in reports {
+        let filename = escape_xml(&report.filename);
+        if report.diagnostics.is_empty() {
+            xml.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\"/>\n",
+                filename, filename
+            ));
+            continue;
+        }
+
+        xml.push_str(&format!(
+            "    <testcase name=\"{}\" classname=\"{}\">\n",
+            filename, filename
+        ));
+        for diagnostic in &report.diagnostics {
+            xml.push_str(&format!(
+                "      <failure message=\"{}\">{}</failure>\n",
+                escape_xml(&diagnostic.message),
+                escape_xml(&format_diagnostic(&report.filename, diagnostic))
+            ));
+        }
+        xml.push_str("    </testcase>\n");
+    }
+
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{'])
+}
+
+/// All files below `root`, skipping hidden files and those excluded by ignore files.
+fn walk(root: &Path) -> impl Iterator<Item = PathBuf> {
+    WalkBuilder::new(root)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .map(|entry| entry.into_path())
+}
+
+/// Expand files, directories and glob patterns into the sorted list of files to check.
+///
+/// Directories and globs are walked honoring ignore files, directories only yield files with
+/// one of `extensions`.
+pub fn collect_files(patterns: &[String], extensions: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut files = vec![];
+    let mut globs = GlobSetBuilder::new();
+    let mut has_globs = false;
+
+    for pattern in patterns {
+        let path = Path::new(pattern);
+        if is_glob(pattern) {
+            let glob = GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .map_err(|err| err.to_string())?;
+            globs.add(glob);
+            has_globs = true;
+        } else if path.is_dir() {
+            files.extend(walk(path).filter(|file| indexer::has_extension(file, extensions)));
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    if has_globs {
+        let globs = globs.build().map_err(|err| err.to_string())?;
+        files.extend(
+            walk(Path::new("."))
+                .filter_map(|file| file.strip_prefix(".").map(Path::to_path_buf).ok())
+                .filter(|file| globs.is_match(file)),
+        );
+    }
+
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Files changed between the merge base with `base` and the working tree, relative to the
+/// current directory. Deleted files are left out.
+pub fn changed_files(base: &str) -> Result<Vec<PathBuf>, String> {
+    let output = Command::new("git")
+        .args([
+            "diff",
+            "--name-only",
+            "--relative",
+            "--diff-filter=d",
+            "--merge-base",
+            base,
+        ])
+        .output()
+        .map_err(|err| format!("Failed to run git: {}", err))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Run `check` for all `files` in parallel, results are in the order of `files`.
+fn check_files(
+    files: &[PathBuf],
+    check: impl Fn(&Path, &str) -> Vec<Diagnostic> + Sync,
+) -> Vec<Result<FileReport, String>> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(files.len()));
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(files.len().max(1));
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let path = match files.get(i) {
+                    Some(path) => path,
+                    None => break,
+                };
+
+                let result = fs::read_to_string(path)
+                    .map(|source_code| FileReport {
+                        filename: path.display().to_string(),
+                        diagnostics: check(path, &source_code),
+                    })
+                    .map_err(|err| format!("Failed to read '{}': {}", path.display(), err));
+                if let Ok(mut results) = results.lock() {
+                    results.push((i, result));
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap_or_default();
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// The index of `provider`, built once and shared by all files using it.
+fn cached_index(
+    cache: &Mutex<HashMap<String, Arc<SuggestionIndex>>>,
+    provider: &Provider,
+    byte_exact: bool,
+) -> Arc<SuggestionIndex> {
+    let mut cache = cache.lock().unwrap_or_else(|err| err.into_inner());
+    cache
+        .entry(provider.location())
+        .or_insert_with(|| {
+            let mut index =
+                SuggestionIndex::build(provider.names().unwrap_or_default(), byte_exact);
+            index.mark_files(provider.files());
+            Arc::new(index)
+        })
+        .clone()
+}
+
+/// Validate the files or stdin source given to the `check` subcommand and print all
+/// diagnostics and a summary to stdout, returns the process exit code.
+pub fn run(args: &CliArgs, check: &CheckArgs) -> i32 {
+    let config = match Config::load(Path::new(".")) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Invalid configuration file {}", err);
+            return 2;
+        }
+    };
+    if args.restrict_to_workspace {
+        if let Err(err) = config.ensure_inside(Path::new(".")) {
+            eprintln!("Invalid configuration file: {}", err);
+            return 2;
+        }
+    }
+    let provider = Provider::from_config(args, &config);
+    let names = match &args.index_cache {
+        Some(dir) => cache::names(dir, &provider),
+        None => provider.names(),
+    };
+    let folders = match names {
+        Ok(folders) => folders,
+        Err(err) => {
+            eprintln!("Failed to read folders: {}", err);
+            return 2;
+        }
+    };
+    let mut index = SuggestionIndex::build(folders, args.byte_exact);
+    index.mark_files(provider.files());
+    let validators = match validator::load_plugins(&config.plugins, Path::new(".")) {
+        Ok(validators) => validators,
+        Err(err) => {
+            eprintln!("{}", err);
+            return 2;
+        }
+    };
+    if let Err(err) = grammar::register_config(&config, Path::new(".")) {
+        eprintln!("{}", err);
+        return 2;
+    }
+    let location = provider.location();
+    let indexes = Mutex::new(HashMap::new());
+    let diagnostics = |path: &Path, source_code: &str| {
+        let inline = LspParser::parse_inline_config(source_code);
+        let matching = config.override_for(path.strip_prefix(".").unwrap_or(path));
+        let directory = |dir: &str, base: &Path| {
+            Provider::Directory {
+                dir: dir.to_string(),
+                recursive: args.recursive,
+            }
+            .resolve(base)
+        };
+        let mapped = |varname: &str| {
+            let dir = config.directories.get(varname)?;
+            Some(directory(dir, Path::new("")))
+        };
+        let is_allowed = |provider: &Provider| match provider {
+            Provider::Directory { dir, .. } if args.restrict_to_workspace => {
+                paths::is_inside(Path::new(dir), Path::new("."))
+            }
+            _ => true,
+        };
+        let varname = inline
+            .varname
+            .or_else(|| matching.and_then(|entry| entry.varname.clone()))
+            .unwrap_or_else(|| args.varname_for(path));
+        // Suggestion directories of inline configurations are relative to the file, those of
+        // overrides and directories to the working directory
+        let document_provider = inline
+            .suggestionsdir
+            .map(|dir| directory(&dir, path.parent().unwrap_or(Path::new(""))))
+            .or_else(|| {
+                let dir = matching?.suggestionsdir.as_ref()?;
+                Some(directory(dir, Path::new("")))
+            })
+            .or_else(|| mapped(&varname))
+            .filter(|provider| is_allowed(provider));
+        // The other arrays mapped to a directory are validated against theirs
+        let scopes = config
+            .directories
+            .keys()
+            .filter(|mapped_varname| **mapped_varname != varname)
+            .filter_map(|mapped_varname| {
+                let provider = mapped(mapped_varname).filter(|provider| is_allowed(provider))?;
+                Some((mapped_varname.clone(), Some(provider)))
+            });
+
+        std::iter::once((varname.clone(), document_provider))
+            .chain(scopes)
+            .flat_map(|(varname, provider)| {
+                let document_index = provider
+                    .as_ref()
+                    .map(|provider| cached_index(&indexes, provider, args.byte_exact));
+                let suggestionsdir = provider
+                    .as_ref()
+                    .map_or_else(|| location.clone(), Provider::location);
+                let engine = DiagnosticEngine {
+                    varname: &varname,
+                    suggestionsdir: &suggestionsdir,
+                    locale: Locale::default(),
+                    config: &config,
+                    validators: &validators,
+                };
+                let index = document_index.as_deref().unwrap_or(&index);
+                engine.diagnostics_with_names(
+                    source_code,
+                    |name| index.contains(name),
+                    |name| index.is_file(name),
+                    || index.names().map(String::from).collect(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut reports: Vec<FileReport> = vec![];
+    if check.stdin {
+        let mut source_code = String::new();
+        if let Err(err) = io::stdin().read_to_string(&mut source_code) {
+            eprintln!("Failed to read stdin: {}", err);
+            return 2;
+        }
+        reports.push(FileReport {
+            filename: check.stdin_filename.as_deref().unwrap_or("<stdin>").into(),
+            diagnostics: diagnostics(
+                Path::new(check.stdin_filename.as_deref().unwrap_or("<stdin>")),
+                &source_code,
+            ),
+        });
+    }
+
+    let mut files = match collect_files(&check.files, &args.extensions) {
+        Ok(files) => files,
+        Err(err) => {
+            eprintln!("Invalid pattern: {}", err);
+            return 2;
+        }
+    };
+
+    if check.changed {
+        let changed = match changed_files(check.base.as_deref().unwrap_or("main")) {
+            Ok(changed) => changed,
+            Err(err) => {
+                eprintln!("Failed to list changed files: {}", err);
+                return 2;
+            }
+        };
+        if check.files.is_empty() {
+            files = changed
+                .into_iter()
+                .filter(|file| indexer::has_extension(file, &args.extensions))
+                .collect();
+        } else {
+            // Directory arguments list files like `./src/app.ts`, git `src/app.ts`
+            let changed: HashSet<PathBuf> = changed
+                .iter()
+                .map(|file| paths::relative_to_cwd(file))
+                .collect();
+            files.retain(|file| changed.contains(&paths::relative_to_cwd(file)));
+        }
+    }
+
+    let mut reporter = Reporter::from(check);
+    for result in check_files(&files, diagnostics) {
+        match result {
+            Ok(report) => reports.push(report),
+            Err(err) => {
+                eprintln!("{}", err);
+                reporter.fail();
+            }
+        }
+    }
+    for report in &mut reports {
+        reporter.add(report);
+    }
+
+    match check.format {
+        Format::Text => {
+            for report in &reports {
+                for diagnostic in &report.diagnostics {
+                    println!("{}", format_diagnostic(&report.filename, diagnostic));
+                }
+            }
+            println!(
+                "Checked {} files: {} errors, {} warnings",
+                reports.len(),
+                reporter.errors(),
+                reporter.warnings()
+            );
+        }
+        Format::Junit => print!("{}", junit_report(&reports)),
+    }
+
+    if let Some(max) = reporter
+        .max_warnings
+        .filter(|_| reporter.too_many_warnings())
+    {
+        eprintln!(
+            "Too many warnings: {} (maximum {})",
+            reporter.warnings(),
+            max
+        );
+    }
+
+    reporter.exit_code()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::{Position, Range};
+
+    #[test]
+    fn test_format_diagnostic() {
+        let diagnostic = Diagnostic {
+            range: Range::new(Position::new(2, 4), Position::new(2, 9)),
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: Some(NumberOrString::String("100".into())),
+            message: "'asets' is not a valid folder".into(),
+            ..Diagnostic::default()
+        };
+
+        assert_eq!(
+            "app.ts:3:5: error: 'asets' is not a valid folder [100]",
+            format_diagnostic("app.ts", &diagnostic)
+        );
+    }
+
+    fn report(severity: DiagnosticSeverity, code: &str) -> FileReport {
+        FileReport {
+            filename: "app.ts".into(),
+            diagnostics: vec![Diagnostic {
+                severity: Some(severity),
+                code: Some(NumberOrString::String(code.into())),
+                ..Diagnostic::default()
+            }],
+        }
+    }
+
+    #[test]
+    fn test_reporter() {
+        let mut reporter = Reporter::default();
+        reporter.add(&mut report(DiagnosticSeverity::WARNING, "101"));
+        assert_eq!(
+            (0, 1, 0),
+            (reporter.errors(), reporter.warnings(), reporter.exit_code())
+        );
+
+        reporter.max_warnings = Some(0);
+        assert_eq!(1, reporter.exit_code());
+
+        let mut reporter = Reporter {
+            warnings_as_errors: true,
+            ..Reporter::default()
+        };
+        let mut warning = report(DiagnosticSeverity::WARNING, "101");
+        reporter.add(&mut warning);
+        assert_eq!(
+            Some(DiagnosticSeverity::ERROR),
+            warning.diagnostics[0].severity
+        );
+        assert_eq!(1, reporter.exit_code());
+
+        let mut reporter = Reporter {
+            error_on: vec!["102".into()],
+            ..Reporter::default()
+        };
+        reporter.add(&mut report(DiagnosticSeverity::HINT, "102"));
+        assert_eq!(1, reporter.errors());
+
+        reporter.fail();
+        assert_eq!(2, reporter.exit_code());
+    }
+
+    #[test]
+    fn test_junit_report() {
+        let reports = vec![
+            FileReport {
+                filename: "ok.ts".into(),
+                diagnostics: vec![],
+            },
+            FileReport {
+                filename: "app.ts".into(),
+                diagnostics: vec![Diagnostic {
+                    range: Range::new(Position::new(0, 0), Position::new(0, 1)),
+                    message: "'<a>' is not a valid folder".into(),
+                    ..Diagnostic::default()
+                }],
+            },
+        ];
+        let xml = junit_report(&reports);
+
+        assert!(xml.contains("tests=\"2\" failures=\"1\""));
+        assert!(xml.contains("<testcase name=\"ok.ts\" classname=\"ok.ts\"/>"));
+        assert!(xml.contains("<failure message=\"&apos;&lt;a&gt;&apos; is not a valid folder\">"));
+    }
+
+    #[test]
+    fn test_check_files_keeps_order() {
+        let files: Vec<PathBuf> =
+            vec!["Cargo.toml".into(), "missing.ts".into(), "README.md".into()];
+        let results = check_files(&files, |_, _| vec![]);
+
+        assert_eq!(3, results.len());
+        assert_eq!("Cargo.toml", results[0].as_ref().unwrap().filename);
+        assert!(results[1].is_err());
+        assert_eq!("README.md", results[2].as_ref().unwrap().filename);
+    }
+
+    #[test]
+    fn test_collect_files() {
+        let files = collect_files(&["src/**/check.rs".to_string()], &[]).unwrap();
+        assert_eq!(vec![PathBuf::from("src/check.rs")], files);
+    }
+}
@@ -0,0 +1,325 @@
+use crate::check::Format;
+use crate::config::{Setting, Source};
+use crate::edits::QuoteStyle;
+use crate::grammar;
+use crate::inspect;
+use crate::matcher::Algorithm;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, CommandFactory, Parser, Subcommand};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+/// tsm-language-server
+#[derive(Parser, Debug)]
+#[command(version,about,long_about=None)]
+pub struct CliArgs {
+    /// Directory to provide as suggestions
+    #[arg(
+        short,
+        long,
+        global = true,
+        default_value = ".",
+        env = "TSM_LS_SUGGESTIONSDIR"
+    )]
+    pub suggestionsdir: String,
+
+    /// Variable name to trigger completion
+    #[arg(
+        short,
+        long,
+        global = true,
+        default_value = "folders",
+        env = "TSM_LS_VARNAME"
+    )]
+    pub varname: String,
+
+    /// Language ids of documents to validate
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "typescript,typescriptreact",
+        env = "TSM_LS_LANGUAGES"
+    )]
+    pub languages: Vec<String>,
+
+    /// File extensions of documents to validate
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "ts,tsx",
+        env = "TSM_LS_EXTENSIONS"
+    )]
+    pub extensions: Vec<String>,
+
+    /// Keep published diagnostics of documents after they are closed
+    #[arg(long, env = "TSM_LS_KEEP_DIAGNOSTICS_ON_CLOSE")]
+    pub keep_diagnostics_on_close: bool,
+
+    /// Compare folder names byte by byte instead of after Unicode (NFC) normalization
+    #[arg(long, global = true, env = "TSM_LS_BYTE_EXACT")]
+    pub byte_exact: bool,
+
+    /// Algorithm used to rank quick fix suggestions
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = Algorithm::Skim,
+        env = "TSM_LS_MATCHER"
+    )]
+    pub matcher: Algorithm,
+
+    /// Validate against the names in a JSON manifest like folders.lock.json instead of the suggestions directory
+    #[arg(long, global = true, env = "TSM_LS_MANIFEST")]
+    pub manifest: Option<PathBuf>,
+
+    /// Validate against the names in a SQLite database instead of the suggestions directory
+    #[arg(
+        long,
+        global = true,
+        conflicts_with = "manifest",
+        env = "TSM_LS_SQLITE"
+    )]
+    pub sqlite: Option<PathBuf>,
+
+    /// Table of the SQLite database holding the names
+    #[arg(
+        long,
+        global = true,
+        default_value = "folders",
+        env = "TSM_LS_SQLITE_TABLE"
+    )]
+    pub sqlite_table: String,
+
+    /// Column of the SQLite table holding the names
+    #[arg(
+        long,
+        global = true,
+        default_value = "name",
+        env = "TSM_LS_SQLITE_COLUMN"
+    )]
+    pub sqlite_column: String,
+
+    /// Validate against these names instead of the suggestions directory, e.g. in CI where the
+    /// folders don't exist
+    #[arg(
+        long,
+        global = true,
+        value_delimiter = ',',
+        conflicts_with_all = ["manifest", "sqlite", "suggestions_command"],
+        env = "TSM_LS_SUGGESTIONS"
+    )]
+    pub suggestions: Vec<String>,
+
+    /// Validate against the names printed by a shell command, one per line, instead of the
+    /// suggestions directory, e.g. "curl -s https://example.com/folders.txt"
+    #[arg(
+        long,
+        global = true,
+        conflicts_with_all = ["manifest", "sqlite"],
+        env = "TSM_LS_SUGGESTIONS_COMMAND"
+    )]
+    pub suggestions_command: Option<String>,
+
+    /// Seconds the names of --suggestions-command are served before they are refreshed in the
+    /// background
+    #[arg(
+        long,
+        global = true,
+        default_value_t = 300,
+        env = "TSM_LS_SUGGESTIONS_TTL"
+    )]
+    pub suggestions_ttl: u64,
+
+    /// Directory to persist the names of the suggestions directory, manifest or database in,
+    /// reused on startup while they are unchanged, e.g. "~/.cache/tsm-language-server"
+    #[arg(long, global = true, env = "TSM_LS_INDEX_CACHE")]
+    pub index_cache: Option<PathBuf>,
+
+    /// Also suggest nested folders as paths like "assets/icons"
+    #[arg(long, global = true, env = "TSM_LS_RECURSIVE")]
+    pub recursive: bool,
+
+    /// Quote character of strings inserted by quick fixes and completions
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = QuoteStyle::Auto,
+        env = "TSM_LS_QUOTE_STYLE"
+    )]
+    pub quote_style: QuoteStyle,
+
+    /// Rank quick fix suggestions case sensitively
+    #[arg(long, global = true, env = "TSM_LS_CASE_SENSITIVE")]
+    pub case_sensitive: bool,
+
+    /// Minimum score for quick fix suggestions, Levenshtein and Jaro-Winkler scores range from 0 to 1
+    #[arg(long, global = true, env = "TSM_LS_MATCH_CUTOFF")]
+    pub match_cutoff: Option<f64>,
+
+    /// Resolve the suggestions directory relative to each document instead of the workspace root,
+    /// e.g. "./public" next to the file for per-package folders in a monorepo
+    #[arg(long, env = "TSM_LS_RELATIVE_TO_DOCUMENT")]
+    pub relative_to_document: bool,
+
+    /// Also validate the array at this key of package.json files, a dot-separated path like
+    /// "workspaces" or "config.assetDirs"
+    #[arg(long, global = true, env = "TSM_LS_PACKAGE_JSON")]
+    pub package_json: Option<String>,
+
+    /// Scan the workspace on startup and publish diagnostics for files not yet opened
+    #[arg(long, env = "TSM_LS_SCAN_WORKSPACE")]
+    pub scan_workspace: bool,
+
+    /// Ignore suggestion directories, plugins, grammars and folders outside of the workspace
+    /// root set by inline configurations and the configuration file, for untrusted repositories
+    #[arg(long, global = true, env = "TSM_LS_RESTRICT_TO_WORKSPACE")]
+    pub restrict_to_workspace: bool,
+
+    /// Export request counts, latencies, validation durations and cache hits to the
+    /// OpenTelemetry collector at this OTLP/HTTP endpoint, like "http://localhost:4318"
+    #[arg(long, env = "TSM_LS_OTLP_ENDPOINT")]
+    pub otlp_endpoint: Option<String>,
+
+    #[arg(long)]
+    pub stdio: bool, // Needed for LSP start
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl CliArgs {
+    /// Whether `path` is a package.json file with a `--package-json` key to validate.
+    pub fn is_package_json(&self, path: &Path) -> bool {
+        self.package_json.is_some() && path.file_name().is_some_and(|name| name == "package.json")
+    }
+
+    /// The values of the arguments in `matches` of these arguments, with their sources.
+    pub fn settings(matches: &ArgMatches) -> Vec<Setting> {
+        Self::command()
+            .get_arguments()
+            .filter(|arg| !matches!(arg.get_id().as_str(), "help" | "version" | "stdio"))
+            .filter_map(|arg| {
+                let name = arg.get_id().as_str();
+                let values: Vec<String> = matches
+                    .get_raw(name)?
+                    .map(|value| value.to_string_lossy().into_owned())
+                    .collect();
+                let value = if arg.get_value_delimiter().is_some() {
+                    toml::Value::Array(values.into_iter().map(toml::Value::String).collect())
+                } else if !arg.get_action().takes_values() {
+                    toml::Value::Boolean(values.first().is_some_and(|value| value == "true"))
+                } else {
+                    toml::Value::String(values.join(","))
+                };
+                let source = match matches.value_source(name) {
+                    Some(ValueSource::CommandLine) => Source::CommandLine,
+                    Some(ValueSource::EnvVariable) => Source::Environment,
+                    _ => Source::Default,
+                };
+                Some(Setting {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                    source,
+                })
+            })
+            .collect()
+    }
+
+    /// The keys of the configuration file set by these arguments, which override all other
+    /// sources.
+    pub fn config_layer(&self) -> toml::Table {
+        let mut layer = toml::Table::new();
+        if !self.suggestions.is_empty() {
+            let names = self.suggestions.iter().cloned().map(toml::Value::String);
+            layer.insert("suggestions".into(), toml::Value::Array(names.collect()));
+        }
+        layer
+    }
+
+    /// Name of the arrays validated in the file at `path`, the `--package-json` key as `$.key`
+    /// in package.json files and `grammar:varname` in files parsed with a runtime-loaded grammar.
+    pub fn varname_for(&self, path: &Path) -> String {
+        match &self.package_json {
+            Some(key) if self.is_package_json(path) => format!("$.{}", key),
+            _ => match grammar::for_path(path) {
+                Some(grammar) => format!("{}:{}", grammar, self.varname),
+                None => self.varname.clone(),
+            },
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Validate files and print diagnostics instead of starting the language server
+    Check(CheckArgs),
+    /// Print the captures of a tree-sitter query in a file, for writing custom queries
+    Query(QueryArgs),
+    /// Print the syntax tree of a file as S-expression, for writing custom queries
+    Ast(AstArgs),
+    /// Print the effective settings of the command line and the configuration file with their
+    /// sources
+    Config,
+}
+
+#[derive(Args, Debug)]
+pub struct AstArgs {
+    /// File to print the syntax tree of
+    #[arg(long)]
+    pub file: PathBuf,
+
+    /// Only print the nodes on these one-based lines, like "3:10" or "5"
+    #[arg(long, value_parser = inspect::parse_lines)]
+    pub lines: Option<RangeInclusive<usize>>,
+}
+
+#[derive(Args, Debug)]
+pub struct QueryArgs {
+    /// File to run the query against
+    #[arg(long)]
+    pub file: PathBuf,
+
+    /// File with the query to run [default: the query finding the arrays named --varname]
+    #[arg(long)]
+    pub query: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct CheckArgs {
+    /// Files, directories or glob patterns like "src/**/*.ts" to validate, honoring ignore files
+    pub files: Vec<String>,
+
+    /// Read the source to validate from stdin
+    #[arg(long, conflicts_with = "files")]
+    pub stdin: bool,
+
+    /// File name reported for source read from stdin
+    #[arg(long, requires = "stdin")]
+    pub stdin_filename: Option<String>,
+
+    /// Only validate files changed relative to the base ref, according to git
+    #[arg(long, conflicts_with = "stdin")]
+    pub changed: bool,
+
+    /// Git ref to compare against with --changed [default: main]
+    #[arg(long, requires = "changed")]
+    pub base: Option<String>,
+
+    /// Output format of the results
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+
+    /// Fail if there are more warnings than this
+    #[arg(long)]
+    pub max_warnings: Option<usize>,
+
+    /// Comma-separated diagnostic codes to report as errors regardless of their severity
+    #[arg(long, value_delimiter = ',')]
+    pub error_on: Vec<String>,
+
+    /// Report all warnings as errors
+    #[arg(long)]
+    pub warnings_as_errors: bool,
+}
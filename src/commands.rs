@@ -0,0 +1,75 @@
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// Opens the referenced folder in the OS file manager, argument is the folder name.
+pub const REVEAL_FOLDER: &str = "tsm.revealFolder";
+
+/// Creates the folder of an entry in the suggestions directory, arguments are the folder name and
+/// the document URI, followed by `{ "dryRun": true }` to return the path of the folder instead.
+pub const CREATE_FOLDER: &str = "tsm.createFolder";
+
+/// Adds the folders missing from the array of a document and removes the entries of folders
+/// which no longer exist, after asking for confirmation. Argument is the document URI, followed
+/// by `{ "dryRun": true }` to return the planned changes instead of applying them.
+pub const SYNC_FOLDERS: &str = "tsm.syncFolders";
+
+/// Creates the folders of all entries of a document which don't exist, after asking for
+/// confirmation, and returns the created folders and the failures. Argument is the document URI,
+/// followed by `{ "dryRun": true }` to return the names and paths of the folders instead.
+pub const CREATE_ALL_MISSING: &str = "tsm.createAllMissing";
+
+/// Scans the workspace and returns the locations of all invalid entries, for a quickfix list.
+/// Takes no arguments, also available as the `tsm/findAllInvalid` request.
+pub const FIND_ALL_INVALID: &str = "tsm.findAllInvalid";
+
+/// All commands provided via `workspace/executeCommand`.
+pub fn all() -> Vec<String> {
+    vec![
+        REVEAL_FOLDER.into(),
+        CREATE_FOLDER.into(),
+        SYNC_FOLDERS.into(),
+        CREATE_ALL_MISSING.into(),
+        FIND_ALL_INVALID.into(),
+    ]
+}
+
+/// Whether the options among `arguments`, an object like `{ "dryRun": true }`, ask to return
+/// the planned changes of a command instead of making them.
+pub fn is_dry_run(arguments: &[serde_json::Value]) -> bool {
+    arguments
+        .iter()
+        .any(|arg| arg.get("dryRun").and_then(|dry_run| dry_run.as_bool()) == Some(true))
+}
+
+/// Open `path` in Finder/Explorer/the default file manager without waiting for it to exit.
+pub fn reveal_folder(path: &Path) -> io::Result<()> {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(target_os = "windows") {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+
+    Command::new(opener).arg(path).spawn().map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_is_dry_run() {
+        assert!(is_dry_run(&[
+            json!("file:///app.ts"),
+            json!({ "dryRun": true })
+        ]));
+        assert!(!is_dry_run(&[
+            json!("file:///app.ts"),
+            json!({ "dryRun": false })
+        ]));
+        assert!(!is_dry_run(&[json!("file:///app.ts")]));
+    }
+}
@@ -0,0 +1,450 @@
+use crate::i18n::{Locale, Message, Noun};
+use crate::paths;
+use crate::rules::{self, Rule};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the configuration file looked up in the workspace root.
+pub const CONFIG_FILE: &str = ".tsm-ls.toml";
+
+/// Where the effective value of a setting comes from, in increasing precedence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Source {
+    Default,
+    File,
+    InitializationOptions,
+    ClientSettings,
+    Environment,
+    CommandLine,
+}
+
+impl Source {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Source::Default => "default",
+            Source::File => CONFIG_FILE,
+            Source::InitializationOptions => "initializationOptions",
+            Source::ClientSettings => "workspace/didChangeConfiguration",
+            Source::Environment => "environment",
+            Source::CommandLine => "command line",
+        }
+    }
+}
+
+/// The effective value of a setting, formatted as TOML value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Setting {
+    pub name: String,
+    pub value: String,
+    pub source: Source,
+}
+
+/// Level of the diagnostics of a rule, set per rule in the `[rules]` table.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Off,
+}
+
+/// Kind of the completion items, which editors show as icon.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemKind {
+    Folder,
+    Module,
+    Value,
+}
+
+/// Labels of the names in completions, inlay hints and messages, the `[labels]` table. Names of
+/// something else than folders, like modules or buckets, get labels of their own.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(default, deny_unknown_fields)]
+pub struct Labels {
+    /// Kind of the completion items, `folder` unless set
+    pub kind: Option<ItemKind>,
+    /// Detail of the completion items and label of the inlay hints, "Directory" unless set
+    pub detail: Option<String>,
+    /// Noun naming the entries in the messages of diagnostics, "folder" unless set
+    pub noun: Option<String>,
+    /// Plural of `noun`, the noun with an appended "s" unless set
+    pub plural: Option<String>,
+}
+
+impl Labels {
+    pub fn kind(&self) -> ItemKind {
+        self.kind.unwrap_or(ItemKind::Folder)
+    }
+
+    /// The configured detail, the translation of "Directory" to `locale` unless set.
+    pub fn detail(&self, locale: Locale) -> String {
+        self.detail
+            .clone()
+            .unwrap_or_else(|| Message::Directory.text(locale))
+    }
+
+    /// The configured noun with its plural, `None` for the folders of the messages.
+    pub fn noun(&self) -> Option<Noun> {
+        let singular = self.noun.clone()?;
+        let plural = self
+            .plural
+            .clone()
+            .unwrap_or_else(|| format!("{}s", singular));
+        Some(Noun { singular, plural })
+    }
+}
+
+/// Settings of the documents whose path matches a glob, an entry of the `[[overrides]]` array.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(default, deny_unknown_fields)]
+pub struct Override {
+    /// Glob of the document paths relative to the workspace root, like `apps/web/**`
+    pub files: String,
+    /// Suggestions directory of the documents, relative to the workspace root
+    pub suggestionsdir: Option<String>,
+    /// Name of the arrays validated in the documents
+    pub varname: Option<String>,
+}
+
+/// Settings read from the `.tsm-ls.toml` configuration file.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Deprecated folder names mapped to the folder replacing them
+    pub deprecated: BTreeMap<String, String>,
+    /// Names which are always valid, e.g. folders generated at build time
+    pub allow: BTreeSet<String>,
+    /// Names to validate against instead of the suggestions directory, e.g. in CI where the
+    /// folders don't exist
+    pub suggestions: Vec<String>,
+    /// Names which are never valid, even if the folder exists
+    pub deny: BTreeSet<String>,
+    /// WASM validator plugins, relative to the workspace root
+    pub plugins: Vec<PathBuf>,
+    /// Directory of tree-sitter grammars compiled to shared libraries, relative to the workspace
+    /// root
+    pub grammars: Option<PathBuf>,
+    /// Extensions of the files parsed with a grammar keyed by its name, like `python = ["py"]`
+    pub grammar_extensions: BTreeMap<String, Vec<String>>,
+    /// Report diagnostics silenced by suppression comments as hints instead of dropping them
+    pub report_suppressed: bool,
+    /// Complete names listed in other entries as well, tagged deprecated, instead of hiding them
+    pub complete_listed: bool,
+    /// Append the best quick fix suggestion to the message of invalid entries, like "did you
+    /// mean 'assets'?", on unless set to `false`
+    pub did_you_mean: Option<bool>,
+    /// Rank completions sharing the directories of other entries first, like `assets/icons` next
+    /// to `assets/img`, on unless set to `false`
+    pub sibling_ranking: Option<bool>,
+    /// Levels of rules keyed by their name, like `duplicate-entry = "off"`
+    pub rules: BTreeMap<String, Level>,
+    /// Regular expression all entries have to match, like `^[a-z0-9]+(-[a-z0-9]+)*$`
+    pub naming: Option<String>,
+    /// Settings of the documents matching a glob, the first matching entry applies
+    pub overrides: Vec<Override>,
+    /// Suggestions directories keyed by the name of the arrays validated against them, relative
+    /// to the workspace root, like `templates = "./tpl"`
+    pub directories: BTreeMap<String, String>,
+    /// Labels of the names, for names of something else than folders
+    pub labels: Labels,
+}
+
+impl Config {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let config: Self = toml::from_str(source).map_err(|err| err.to_string())?;
+        config.validate()
+    }
+
+    /// Check the rule names and the naming pattern, which deserializing doesn't.
+    fn validate(self) -> Result<Self, String> {
+        if let Some(name) = self.rules.keys().find(|name| rules::rule(name).is_none()) {
+            return Err(format!("unknown rule '{}'", name));
+        }
+        if let Some(naming) = &self.naming {
+            Regex::new(naming).map_err(|err| format!("invalid naming pattern: {}", err))?;
+        }
+        #[cfg(feature = "server")]
+        for entry in &self.overrides {
+            globset::Glob::new(&entry.files)
+                .map_err(|err| format!("invalid glob '{}': {}", entry.files, err))?;
+        }
+        Ok(self)
+    }
+
+    /// Load the configuration file from `dir`, the default configuration if there is none.
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let path = dir.join(CONFIG_FILE);
+        match fs::read_to_string(&path) {
+            Ok(source) => {
+                Self::parse(&source).map_err(|err| format!("{}: {}", path.display(), err))
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(format!("{}: {}", path.display(), err)),
+        }
+    }
+
+    /// The keys set in the configuration file in `dir`, an empty table if there is none.
+    pub fn file_layer(dir: &Path) -> Result<toml::Table, String> {
+        let path = dir.join(CONFIG_FILE);
+        match fs::read_to_string(&path) {
+            Ok(source) => {
+                toml::from_str(&source).map_err(|err| format!("{}: {}", path.display(), err))
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(toml::Table::new()),
+            Err(err) => Err(format!("{}: {}", path.display(), err)),
+        }
+    }
+
+    /// The keys of the configuration file among client `settings`, which are nested in a `tsm`
+    /// object or flat. Other keys, like the feature toggles, and invalid values are skipped.
+    pub fn client_layer(settings: &serde_json::Value) -> toml::Table {
+        let settings = settings.get("tsm").unwrap_or(settings);
+        let Some(settings) = settings.as_object() else {
+            return toml::Table::new();
+        };
+        settings
+            .iter()
+            .filter_map(|(key, value)| Some((key.clone(), toml::Value::try_from(value).ok()?)))
+            .filter(|(key, value)| {
+                let mut table = toml::Table::new();
+                table.insert(key.clone(), value.clone());
+                table.try_into::<Self>().is_ok()
+            })
+            .collect()
+    }
+
+    /// Resolve the configuration from `layers`, a key of a layer overriding the same key of
+    /// the layers before it. Returns the source of every key set by a layer.
+    pub fn resolve(
+        layers: &BTreeMap<Source, toml::Table>,
+    ) -> Result<(Self, BTreeMap<String, Source>), String> {
+        let mut merged = toml::Table::new();
+        let mut sources = BTreeMap::new();
+        for (source, layer) in layers {
+            for (key, value) in layer {
+                merged.insert(key.clone(), value.clone());
+                sources.insert(key.clone(), *source);
+            }
+        }
+        let config: Self = merged.try_into().map_err(|err| err.to_string())?;
+        Ok((config.validate()?, sources))
+    }
+
+    /// The settings with values and the source of their key in `sources`, the defaults if it
+    /// is missing.
+    pub fn settings(&self, sources: &BTreeMap<String, Source>) -> Vec<Setting> {
+        let table = match toml::Table::try_from(self) {
+            Ok(table) => table,
+            Err(_) => return vec![],
+        };
+        table
+            .into_iter()
+            .map(|(name, value)| Setting {
+                source: sources.get(&name).copied().unwrap_or(Source::Default),
+                value: value.to_string(),
+                name,
+            })
+            .collect()
+    }
+
+    /// Fail if a plugin or the grammars directory is outside of `root`, relative paths are
+    /// resolved against it.
+    pub fn ensure_inside(&self, root: &Path) -> Result<(), String> {
+        let outside = self
+            .plugins
+            .iter()
+            .chain(&self.grammars)
+            .map(|path| root.join(path))
+            .find(|path| !paths::is_inside(path, root));
+        match outside {
+            Some(path) => Err(format!(
+                "{} is outside of the workspace",
+                paths::display(&path)
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// The configured level of `rule`, its default level if there is none.
+    pub fn level(&self, rule: &dyn Rule) -> Level {
+        self.rules
+            .get(rule.name())
+            .copied()
+            .unwrap_or(rule.default_level())
+    }
+
+    /// The compiled `naming` pattern, validated when parsing.
+    pub fn naming(&self) -> Option<Regex> {
+        self.naming
+            .as_deref()
+            .and_then(|naming| Regex::new(naming).ok())
+    }
+
+    /// Whether the messages of invalid entries name the best suggestion, the default unless
+    /// `did_you_mean` turns it off.
+    pub fn did_you_mean(&self) -> bool {
+        self.did_you_mean.unwrap_or(true)
+    }
+
+    /// Whether completions sharing directories with other entries are ranked first, the default
+    /// unless `sibling_ranking` turns it off.
+    pub fn sibling_ranking(&self) -> bool {
+        self.sibling_ranking.unwrap_or(true)
+    }
+
+    /// The first override whose glob matches `path`, relative to the workspace root.
+    #[cfg(feature = "server")]
+    pub fn override_for(&self, path: &Path) -> Option<&Override> {
+        self.overrides.iter().find(|entry| {
+            globset::Glob::new(&entry.files).is_ok_and(|glob| glob.compile_matcher().is_match(path))
+        })
+    }
+
+    /// The folder replacing `name`, if it is deprecated.
+    pub fn replacement(&self, name: &str) -> Option<&str> {
+        self.deprecated.get(name).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse(
+            r#"
+            [deprecated]
+            old_assets = "assets"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(Some("assets"), config.replacement("old_assets"));
+        assert_eq!(None, config.replacement("assets"));
+        assert_eq!(Config::default(), Config::parse("").unwrap());
+        assert!(Config::parse("unknown = 1").is_err());
+
+        let config = Config::parse(
+            r#"allow = ["generated"]
+deny = ["tmp"]"#,
+        )
+        .unwrap();
+        assert!(config.allow.contains("generated"));
+        assert!(config.deny.contains("tmp"));
+
+        let config = Config::parse("[rules]\nduplicate-entry = \"off\"").unwrap();
+        assert_eq!(Some(&Level::Off), config.rules.get("duplicate-entry"));
+        assert!(Config::parse("[rules]\nunknown = \"off\"").is_err());
+        assert!(Config::parse("[rules]\nduplicate-entry = \"fatal\"").is_err());
+
+        let config = Config::parse("[labels]\nkind = \"module\"\nnoun = \"module\"").unwrap();
+        assert_eq!(ItemKind::Module, config.labels.kind());
+        assert_eq!("Directory", config.labels.detail(Locale::En));
+        assert_eq!("modules", config.labels.noun().unwrap().plural);
+        assert!(Config::parse("[labels]\nkind = \"file\"").is_err());
+
+        let config = Config::parse(r#"naming = "^[a-z]+$""#).unwrap();
+        assert!(config.naming().unwrap().is_match("icons"));
+        assert!(Config::parse(r#"naming = "[a-z""#).is_err());
+    }
+
+    #[test]
+    fn test_settings() {
+        let config =
+            Config::parse("deny = [\"tmp\"]\n[rules]\nunsorted-entries = \"warn\"").unwrap();
+        let sources = [
+            ("deny".to_string(), Source::File),
+            ("rules".to_string(), Source::File),
+        ]
+        .into();
+
+        let settings = config.settings(&sources);
+        let deny = settings.iter().find(|s| s.name == "deny").unwrap();
+        assert_eq!(r#"["tmp"]"#, deny.value);
+        assert_eq!(Source::File, deny.source);
+        let rules = settings.iter().find(|s| s.name == "rules").unwrap();
+        assert_eq!(r#"{ unsorted-entries = "warn" }"#, rules.value);
+        let allow = settings.iter().find(|s| s.name == "allow").unwrap();
+        assert_eq!(Source::Default, allow.source);
+        assert!(!settings.iter().any(|s| s.name == "naming"));
+    }
+
+    #[test]
+    fn test_ensure_inside() {
+        let root = Path::new("/does-not-exist/project");
+        let config = Config {
+            plugins: vec!["validators/a.wasm".into()],
+            grammars: Some("grammars".into()),
+            ..Config::default()
+        };
+        assert!(config.ensure_inside(root).is_ok());
+
+        let config = Config {
+            plugins: vec!["../shared/a.wasm".into()],
+            ..Config::default()
+        };
+        assert!(config.ensure_inside(root).is_err());
+        let config = Config {
+            grammars: Some("/usr/lib/grammars".into()),
+            ..Config::default()
+        };
+        assert!(config.ensure_inside(root).is_err());
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_override_for() {
+        let config = Config::parse(
+            r#"
+            [[overrides]]
+            files = "apps/web/**"
+            suggestionsdir = "apps/web/public"
+
+            [[overrides]]
+            files = "apps/**"
+            varname = "icons"
+            "#,
+        )
+        .unwrap();
+
+        let web = config
+            .override_for(Path::new("apps/web/src/main.ts"))
+            .unwrap();
+        assert_eq!(Some("apps/web/public"), web.suggestionsdir.as_deref());
+        assert_eq!(None, web.varname);
+        let api = config.override_for(Path::new("apps/api/main.ts")).unwrap();
+        assert_eq!(Some("icons"), api.varname.as_deref());
+        assert_eq!(None, config.override_for(Path::new("lib/main.ts")));
+        assert!(Config::parse("[[overrides]]\nfiles = \"[a-\"").is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let file: toml::Table =
+            toml::from_str("deny = [\"tmp\"]\nallow = [\"generated\"]").unwrap();
+        let client = Config::client_layer(&serde_json::json!({
+            "tsm": { "deny": ["old"], "completion": false, "naming": 1 }
+        }));
+        assert_eq!(vec!["deny"], client.keys().collect::<Vec<_>>());
+
+        let layers = BTreeMap::from([(Source::File, file), (Source::ClientSettings, client)]);
+        let (config, sources) = Config::resolve(&layers).unwrap();
+        assert!(config.deny.contains("old") && !config.deny.contains("tmp"));
+        assert!(config.allow.contains("generated"));
+        assert_eq!(Some(&Source::ClientSettings), sources.get("deny"));
+        assert_eq!(Some(&Source::File), sources.get("allow"));
+
+        let layers = BTreeMap::from([(
+            Source::InitializationOptions,
+            Config::client_layer(&serde_json::json!({ "naming": "[a-z" })),
+        )]);
+        assert!(Config::resolve(&layers).is_err());
+    }
+}
@@ -0,0 +1,591 @@
+use crate::config::{Config, Level};
+use crate::edits;
+use crate::i18n::Locale;
+use crate::parser::LspParser;
+use crate::rules::{self, Context};
+use crate::validator::Validator;
+use lsp_types::{self, Diagnostic, DiagnosticSeverity, NumberOrString, Position};
+use std::sync::Arc;
+
+/// Range of the syntax tree of the source code, whose byte columns become UTF-16 code units.
+pub struct MyRange<'a>(pub tree_sitter::Range, pub &'a str);
+
+impl From<MyRange<'_>> for lsp_types::Range {
+    fn from(value: MyRange) -> Self {
+        lsp_types::Range {
+            start: {
+                Position {
+                    line: value.0.start_point.row as u32,
+                    character: edits::utf16_column(value.1, value.0.start_point),
+                }
+            },
+            end: {
+                Position {
+                    line: value.0.end_point.row as u32,
+                    character: edits::utf16_column(value.1, value.0.end_point),
+                }
+            },
+        }
+    }
+}
+
+/// Validates the entries of tracked arrays, independent of a running language server.
+pub struct DiagnosticEngine<'a> {
+    pub varname: &'a str,
+    pub suggestionsdir: &'a str,
+    pub locale: Locale,
+    pub config: &'a Config,
+    pub validators: &'a [Arc<dyn Validator>],
+}
+
+impl DiagnosticEngine<'_> {
+    /// Diagnostics of all rules for the entries of `source_code`, `is_valid_folder` tells
+    /// whether an entry names an existing folder.
+    ///
+    /// Every diagnostic gets the level configured for its rule, diagnostics on lines silenced by
+    /// suppression comments are dropped, or reported as hints if configured.
+    pub fn diagnostics(
+        &self,
+        source_code: &str,
+        is_valid_folder: impl Fn(&str) -> bool,
+    ) -> Vec<Diagnostic> {
+        self.diagnostics_with_names(source_code, is_valid_folder, |_| false, Vec::new)
+    }
+
+    /// Like [`diagnostics`](Self::diagnostics), `is_file` tells whether a valid name is a
+    /// regular file rather than a folder, `folder_names` lists all valid names for the rules
+    /// reporting folders missing from the arrays.
+    pub fn diagnostics_with_names(
+        &self,
+        source_code: &str,
+        is_valid_folder: impl Fn(&str) -> bool,
+        is_file: impl Fn(&str) -> bool,
+        folder_names: impl Fn() -> Vec<String>,
+    ) -> Vec<Diagnostic> {
+        let entries = LspParser::parse_code(source_code, self.varname);
+        let context = Context {
+            source_code,
+            varname: self.varname,
+            suggestionsdir: self.suggestionsdir,
+            locale: self.locale,
+            config: self.config,
+            validators: self.validators,
+            validator_names: self
+                .validators
+                .iter()
+                .flat_map(|validator| validator.names())
+                .collect(),
+            naming: self.config.naming(),
+            is_valid_folder: &is_valid_folder,
+            is_file: &is_file,
+            folder_names: &folder_names,
+        };
+
+        let suppressions = LspParser::parse_suppressions(source_code);
+        let diagnostics = entries
+            .iter()
+            .filter_map(|entry| {
+                rules::registry()
+                    .iter()
+                    .find_map(|rule| rule.check_entry(entry, &context))
+            })
+            .chain(
+                // Without precedence among them, rules turned off needn't check at all
+                rules::registry()
+                    .iter()
+                    .filter(|rule| self.config.level(**rule) != Level::Off)
+                    .flat_map(|rule| rule.check_entries(&entries, &context)),
+            )
+            .filter_map(|diagnostic| self.with_level(diagnostic))
+            .filter_map(|mut diagnostic| {
+                let start = diagnostic.range.start;
+                if !suppressions.is_suppressed(start.line as usize)
+                    && !suppressions
+                        .is_ignored(start.line as usize, edits::byte_column(source_code, start))
+                {
+                    return Some(diagnostic);
+                }
+                self.config.report_suppressed.then(|| {
+                    diagnostic.severity = Some(DiagnosticSeverity::HINT);
+                    diagnostic
+                })
+            })
+            .collect();
+
+        normalize_diagnostics(diagnostics)
+    }
+
+    /// `diagnostic` with the level configured for its rule, `None` if the rule is turned off.
+    fn with_level(&self, mut diagnostic: Diagnostic) -> Option<Diagnostic> {
+        let level = rules::of(&diagnostic).map_or(Level::Error, |rule| self.config.level(rule));
+        diagnostic.severity = Some(match level {
+            Level::Error => DiagnosticSeverity::ERROR,
+            Level::Warn => DiagnosticSeverity::WARNING,
+            Level::Info => DiagnosticSeverity::INFORMATION,
+            Level::Off => return None,
+        });
+        Some(diagnostic)
+    }
+}
+
+fn diagnostic_code(diagnostic: &Diagnostic) -> String {
+    match &diagnostic.code {
+        Some(NumberOrString::Number(code)) => code.to_string(),
+        Some(NumberOrString::String(code)) => code.clone(),
+        None => String::new(),
+    }
+}
+
+/// Sort diagnostics by range, code and message and drop exact duplicates, so that unchanged documents
+/// always produce identical diagnostics.
+pub fn normalize_diagnostics(mut diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    diagnostics.sort_by_cached_key(|d| {
+        (
+            d.range.start.line,
+            d.range.start.character,
+            d.range.end.line,
+            d.range.end.character,
+            diagnostic_code(d),
+            d.message.clone(),
+        )
+    });
+    diagnostics.dedup();
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{
+        AbsolutePath, CaseMismatch, DeniedFolder, DuplicateEntry, EmptyEntry, InvalidFolder,
+        NotADirectory, PathTraversal, Rule,
+    };
+    use lsp_types::DiagnosticTag;
+
+    fn engine(config: &Config) -> DiagnosticEngine<'_> {
+        DiagnosticEngine {
+            varname: "folders",
+            suggestionsdir: "assets",
+            locale: Locale::En,
+            config,
+            validators: &[],
+        }
+    }
+
+    fn diagnostic(line: u32, code: &str) -> Diagnostic {
+        Diagnostic {
+            range: lsp_types::Range::new(Position::new(line, 0), Position::new(line, 5)),
+            code: Some(NumberOrString::String(code.into())),
+            ..Diagnostic::default()
+        }
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let config = Config::default();
+        let engine = engine(&config);
+        let source_code = r#"
+             export const folders = ["dir_a", "dir_b", "dir_c"];
+         "#;
+
+        let diagnostics = engine.diagnostics(source_code, |name| name == "dir_b");
+        assert_eq!(2, diagnostics.len());
+        assert_eq!(
+            Some(serde_json::Value::String("dir_a".into())),
+            diagnostics[0].data
+        );
+        assert_eq!(
+            Some(serde_json::Value::String("dir_c".into())),
+            diagnostics[1].data
+        );
+    }
+
+    #[test]
+    fn test_deprecated_folder() {
+        let config = Config::parse("deprecated = { dir_a = \"dir_b\" }").unwrap();
+        let engine = engine(&config);
+        let source_code = r#"export const folders = ["dir_a", "dir_b"];"#;
+
+        let diagnostics = engine.diagnostics(source_code, |_| true);
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(Some(DiagnosticSeverity::WARNING), diagnostics[0].severity);
+        assert_eq!(Some(vec![DiagnosticTag::DEPRECATED]), diagnostics[0].tags);
+        assert_eq!(
+            Some(serde_json::Value::String("dir_b".into())),
+            diagnostics[0].data
+        );
+    }
+
+    #[test]
+    fn test_allow_and_deny() {
+        let config = Config::parse(
+            r#"allow = ["generated"]
+deny = ["tmp"]"#,
+        )
+        .unwrap();
+        let engine = engine(&config);
+        let source_code = r#"export const folders = ["generated", "tmp", "assets"];"#;
+
+        let diagnostics = engine.diagnostics(source_code, |name| name != "generated");
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(
+            Some(NumberOrString::String(DeniedFolder.code().into())),
+            diagnostics[0].code
+        );
+    }
+
+    #[test]
+    fn test_suppressions() {
+        let source_code = r#"export const folders = [
+            // tsm-disable-next-line
+            "dir_a",
+            "dir_b",
+        ];"#;
+        let config = Config::default();
+        let engine = engine(&config);
+
+        let diagnostics = engine.diagnostics(source_code, |_| false);
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(
+            Some(serde_json::Value::String("dir_b".into())),
+            diagnostics[0].data
+        );
+
+        let config = Config {
+            report_suppressed: true,
+            ..Config::default()
+        };
+        let engine = DiagnosticEngine {
+            config: &config,
+            ..engine
+        };
+        let diagnostics = engine.diagnostics(source_code, |_| false);
+        assert_eq!(2, diagnostics.len());
+        assert_eq!(Some(DiagnosticSeverity::HINT), diagnostics[0].severity);
+        assert_eq!(Some(DiagnosticSeverity::ERROR), diagnostics[1].severity);
+    }
+
+    #[test]
+    fn test_ignored_entries() {
+        let source_code = r#"export const folders = [
+            "dir_a", "legacy_dir", // tsm-ignore
+        ];"#;
+        let config = Config::default();
+        let engine = engine(&config);
+
+        let diagnostics = engine.diagnostics(source_code, |_| false);
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(
+            Some(serde_json::Value::String("dir_a".into())),
+            diagnostics[0].data
+        );
+    }
+
+    #[test]
+    fn test_utf16_ranges() {
+        let source_code =
+            "export const folders = [\"\u{1f600}\", \"\u{e9}t\u{e9}\",\n  \"x\", // tsm-ignore\n];";
+        let config = Config::default();
+        let engine = engine(&config);
+
+        // The emoji takes two code units and é one, the ignored entry is the last one
+        let diagnostics = engine.diagnostics(source_code, |_| false);
+        let ranges: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.range.start.character, d.range.end.character))
+            .collect();
+        assert_eq!(vec![(24, 28), (30, 35)], ranges);
+    }
+
+    #[test]
+    fn test_not_a_directory() {
+        let source_code = r#"export const folders = ["dir_a", "notes.txt"];"#;
+        let config = Config::default();
+        let engine = engine(&config);
+
+        let diagnostics = engine.diagnostics_with_names(
+            source_code,
+            |_| true,
+            |name| name == "notes.txt",
+            Vec::new,
+        );
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(
+            Some(NumberOrString::String(NotADirectory.code().into())),
+            diagnostics[0].code
+        );
+        assert_eq!(
+            "'notes.txt' is a file, not a folder",
+            diagnostics[0].message
+        );
+        assert!(engine.diagnostics(source_code, |_| true).is_empty());
+    }
+
+    #[test]
+    fn test_rule_levels() {
+        let source_code = r#"export const folders = ["dir_a", "", "dir_a"];"#;
+        let config = Config::default();
+        let engine = engine(&config);
+
+        let codes = |diagnostics: Vec<Diagnostic>| -> Vec<(String, Option<DiagnosticSeverity>)> {
+            diagnostics
+                .iter()
+                .map(|d| (diagnostic_code(d), d.severity))
+                .collect()
+        };
+        assert_eq!(
+            vec![
+                (
+                    InvalidFolder.code().to_string(),
+                    Some(DiagnosticSeverity::ERROR)
+                ),
+                (
+                    EmptyEntry.code().to_string(),
+                    Some(DiagnosticSeverity::ERROR)
+                ),
+                (
+                    InvalidFolder.code().to_string(),
+                    Some(DiagnosticSeverity::ERROR)
+                ),
+                (
+                    DuplicateEntry.code().to_string(),
+                    Some(DiagnosticSeverity::WARNING)
+                ),
+            ],
+            codes(engine.diagnostics(source_code, |_| false))
+        );
+
+        let config = Config::parse(
+            r#"[rules]
+invalid-folder = "info"
+empty-entry = "off""#,
+        )
+        .unwrap();
+        let engine = DiagnosticEngine {
+            config: &config,
+            ..engine
+        };
+        assert_eq!(
+            vec![
+                (
+                    InvalidFolder.code().to_string(),
+                    Some(DiagnosticSeverity::INFORMATION)
+                ),
+                (
+                    InvalidFolder.code().to_string(),
+                    Some(DiagnosticSeverity::INFORMATION)
+                ),
+                (
+                    DuplicateEntry.code().to_string(),
+                    Some(DiagnosticSeverity::WARNING)
+                ),
+            ],
+            codes(engine.diagnostics(source_code, |_| false))
+        );
+    }
+
+    struct NoUnderscores;
+
+    impl Validator for NoUnderscores {
+        fn name(&self) -> &str {
+            "no-underscores"
+        }
+
+        fn names(&self) -> Vec<String> {
+            vec!["virtual".into()]
+        }
+
+        fn validate(&self, entry: &str) -> Option<String> {
+            entry.contains('_').then(|| "no underscores".into())
+        }
+    }
+
+    #[test]
+    fn test_validators() {
+        let config = Config::default();
+        let validators: Vec<Arc<dyn Validator>> = vec![Arc::new(NoUnderscores)];
+        let engine = DiagnosticEngine {
+            validators: &validators,
+            ..engine(&config)
+        };
+        let source_code = r#"export const folders = ["virtual", "dir_a", "dir-b"];"#;
+
+        let diagnostics = engine.diagnostics(source_code, |name| name != "virtual");
+        assert_eq!(1, diagnostics.len());
+        assert_eq!("no-underscores: no underscores", diagnostics[0].message);
+    }
+
+    #[test]
+    fn test_naming_convention() {
+        let config = Config::parse(r#"naming = "^[a-z0-9]+(-[a-z0-9]+)*$""#).unwrap();
+        let engine = engine(&config);
+        let source_code = r#"export const folders = ["dark-mode", "Dark_Mode", "Light_Mode"];"#;
+
+        let folders = ["dark-mode", "Dark_Mode", "Light_Mode"];
+        let diagnostics = engine.diagnostics(source_code, |name| folders.contains(&name));
+        assert_eq!(2, diagnostics.len());
+        assert_eq!(Some(DiagnosticSeverity::WARNING), diagnostics[0].severity);
+        assert_eq!(
+            Some(serde_json::Value::String("dark-mode".into())),
+            diagnostics[0].data
+        );
+        assert_eq!(None, diagnostics[1].data);
+    }
+
+    #[test]
+    fn test_unsorted_entries() {
+        let source_code = r#"export const folders = ["assets", "icons", "fonts", "sounds"];"#;
+        let config = Config::default();
+        let engine = engine(&config);
+        assert!(engine.diagnostics(source_code, |_| true).is_empty());
+
+        let config = Config::parse("[rules]\nunsorted-entries = \"warn\"").unwrap();
+        let engine = DiagnosticEngine {
+            config: &config,
+            ..engine
+        };
+        let diagnostics = engine.diagnostics(source_code, |_| true);
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(
+            "'fonts' is not in alphabetical order",
+            diagnostics[0].message
+        );
+    }
+
+    #[test]
+    fn test_paths() {
+        let config = Config::default();
+        let engine = DiagnosticEngine {
+            suggestionsdir: "/project/assets",
+            ..engine(&config)
+        };
+        let source_code = r#"export const folders = [
+            "/project/assets/icons",
+            "/etc",
+            "icons/../fonts",
+            "../public",
+        ];"#;
+
+        let diagnostics = engine.diagnostics(source_code, |_| true);
+        let codes: Vec<String> = diagnostics.iter().map(diagnostic_code).collect();
+        assert_eq!(
+            vec![
+                AbsolutePath.code(),
+                AbsolutePath.code(),
+                PathTraversal.code(),
+                PathTraversal.code()
+            ],
+            codes
+        );
+        let data: Vec<Option<serde_json::Value>> =
+            diagnostics.into_iter().map(|d| d.data).collect();
+        assert_eq!(
+            vec![
+                Some(serde_json::Value::String("icons".into())),
+                None,
+                Some(serde_json::Value::String("fonts".into())),
+                None
+            ],
+            data
+        );
+    }
+
+    #[test]
+    fn test_missing_entries() {
+        let source_code = r#"export const folders = ["fonts"];"#;
+        let config = Config::parse(
+            r#"deny = ["tmp"]
+[rules]
+missing-entries = "info""#,
+        )
+        .unwrap();
+        let engine = engine(&config);
+        let folder_names = || {
+            ["fonts", "icons", "icons/dark", "sounds", "tmp"]
+                .map(String::from)
+                .to_vec()
+        };
+
+        let diagnostics =
+            engine.diagnostics_with_names(source_code, |_| true, |_| false, folder_names);
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(
+            Some(DiagnosticSeverity::INFORMATION),
+            diagnostics[0].severity
+        );
+        assert_eq!(13, diagnostics[0].range.start.character);
+        assert_eq!(
+            Some(serde_json::Value::from(vec!["icons", "sounds"])),
+            diagnostics[0].data
+        );
+    }
+
+    #[test]
+    fn test_separate_arrays() {
+        let config = Config::parse("[rules]\nunsorted-entries = \"warn\"").unwrap();
+        let engine = engine(&config);
+        let source_code = r#"
+            function a() { const folders = ["b", "c"]; }
+            function b() { const folders = ["a", "c", "c"]; }
+        "#;
+
+        // Each array is sorted and free of duplicates on its own
+        let diagnostics = engine.diagnostics(source_code, |_| true);
+        assert_eq!(
+            vec![DuplicateEntry.code().to_string()],
+            diagnostics.iter().map(diagnostic_code).collect::<Vec<_>>()
+        );
+        assert_eq!(2, diagnostics[0].range.start.line);
+        assert_eq!(54, diagnostics[0].range.start.character);
+    }
+
+    #[test]
+    fn test_case_mismatch() {
+        let config = Config::default();
+        let engine = engine(&config);
+        let source_code = r#"export const folders = ["Fonts", "icon", "fonts"];"#;
+        let folder_names = || ["fonts", "icons"].map(String::from).to_vec();
+
+        let diagnostics = engine.diagnostics_with_names(
+            source_code,
+            |name| name == "fonts",
+            |_| false,
+            folder_names,
+        );
+        assert_eq!(
+            vec![
+                CaseMismatch.code().to_string(),
+                InvalidFolder.code().to_string()
+            ],
+            diagnostics.iter().map(diagnostic_code).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "'Fonts' differs from 'fonts' only by case",
+            diagnostics[0].message
+        );
+        assert_eq!(
+            Some(serde_json::Value::String("fonts".into())),
+            diagnostics[0].data
+        );
+    }
+
+    #[test]
+    fn test_normalize_diagnostics() {
+        let diagnostics = vec![
+            diagnostic(2, "100"),
+            diagnostic(1, "100"),
+            diagnostic(2, "100"),
+            diagnostic(1, "099"),
+        ];
+
+        let normalized = normalize_diagnostics(diagnostics);
+        assert_eq!(
+            vec![
+                diagnostic(1, "099"),
+                diagnostic(1, "100"),
+                diagnostic(2, "100")
+            ],
+            normalized
+        );
+    }
+}
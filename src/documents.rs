@@ -0,0 +1,155 @@
+//! Documents opened in the editor.
+//!
+//! A handler panicking while it holds the lock of the store poisons it. The store recovers from
+//! that instead of failing every following request, the documents are still the last ones
+//! stored, at worst missing the change of the panicking handler.
+
+use lsp_types::Url;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// An opened document together with the version reported by the client.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document {
+    /// Shared by the handlers reading it, an edit replaces it
+    pub text: Arc<str>,
+    pub version: i32,
+    /// Detected when it was opened, `None` if neither the language id nor the content told
+    pub language: Option<String>,
+}
+
+/// Opened documents keyed by their URI, which may use any scheme like `untitled:` or
+/// `vscode-vfs:` and is never converted to a path.
+#[derive(Debug, Default)]
+pub struct DocumentStore {
+    documents: RwLock<HashMap<Url, Document>>,
+}
+
+impl DocumentStore {
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<Url, Document>> {
+        self.documents.read().unwrap_or_else(|err| {
+            eprintln!("Recovered the document store from a panic of another handler");
+            self.documents.clear_poison();
+            err.into_inner()
+        })
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<Url, Document>> {
+        self.documents.write().unwrap_or_else(|err| {
+            eprintln!("Recovered the document store from a panic of another handler");
+            self.documents.clear_poison();
+            err.into_inner()
+        })
+    }
+
+    /// Store the document `uri`, replacing it if it was open already.
+    pub fn open(&self, uri: Url, text: Arc<str>, version: i32, language: Option<String>) {
+        self.write().insert(
+            uri,
+            Document {
+                text,
+                version,
+                language,
+            },
+        );
+    }
+
+    /// Replace the content of the open document `uri`, returns whether it was stored. Changes
+    /// of documents which aren't open and of versions older than the stored one are dropped.
+    pub fn change(&self, uri: &Url, text: Arc<str>, version: i32) -> bool {
+        match self.write().get_mut(uri) {
+            Some(doc) if doc.version > version => false,
+            Some(doc) => {
+                doc.text = text;
+                doc.version = version;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove the document `uri`, returning it if it was open.
+    pub fn close(&self, uri: &Url) -> Option<Document> {
+        self.write().remove(uri)
+    }
+
+    /// Remove all documents.
+    pub fn clear(&self) {
+        self.write().clear();
+    }
+
+    /// The content of the open document `uri`.
+    pub fn text(&self, uri: &Url) -> Option<Arc<str>> {
+        self.read().get(uri).map(|doc| doc.text.clone())
+    }
+
+    /// The language of the open document `uri`.
+    pub fn language(&self, uri: &Url) -> Option<String> {
+        self.read().get(uri)?.language.clone()
+    }
+
+    pub fn contains(&self, uri: &Url) -> bool {
+        self.read().contains_key(uri)
+    }
+
+    /// Whether `version` is still the latest known version of the document `uri`.
+    pub fn is_current_version(&self, uri: &Url, version: i32) -> bool {
+        self.read()
+            .get(uri)
+            .is_some_and(|doc| doc.version == version)
+    }
+
+    /// The URIs, contents and versions of all open documents.
+    pub fn snapshot(&self) -> Vec<(Url, Arc<str>, i32)> {
+        self.read()
+            .iter()
+            .map(|(uri, doc)| (uri.clone(), doc.text.clone(), doc.version))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uri() -> Url {
+        Url::parse("file:///project/app.ts").unwrap()
+    }
+
+    #[test]
+    fn test_change() {
+        let store = DocumentStore::default();
+        assert!(!store.change(&uri(), "a".into(), 1));
+
+        store.open(uri(), "a".into(), 1, Some("typescript".into()));
+        assert!(store.change(&uri(), "b".into(), 3));
+        assert!(!store.change(&uri(), "c".into(), 2));
+        assert_eq!(Some("b".into()), store.text(&uri()));
+        assert!(store.is_current_version(&uri(), 3));
+        assert_eq!(Some("typescript".into()), store.language(&uri()));
+
+        assert_eq!(3, store.close(&uri()).unwrap().version);
+        assert!(!store.contains(&uri()));
+        assert_eq!(None, store.close(&uri()));
+    }
+
+    #[test]
+    fn test_poisoned() {
+        let store = Arc::new(DocumentStore::default());
+        store.open(uri(), "a".into(), 1, None);
+
+        let panicking = store.clone();
+        let result = std::thread::spawn(move || {
+            let _guard = panicking.documents.write().unwrap();
+            panic!("handler failed");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(store.documents.is_poisoned());
+
+        assert_eq!(Some("a".into()), store.text(&uri()));
+        assert!(!store.documents.is_poisoned());
+        assert!(store.change(&uri(), "b".into(), 2));
+        assert_eq!(vec![(uri(), "b".into(), 2)], store.snapshot());
+    }
+}
@@ -0,0 +1,413 @@
+use crate::diagnostics::MyRange;
+use crate::parser::LspParser;
+use clap::ValueEnum;
+use lsp_types::{Position, Range, TextEdit};
+
+/// Quote character of strings inserted by quick fixes and completions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum QuoteStyle {
+    /// Keep the quote character of the replaced string
+    #[default]
+    Auto,
+    /// Always use 'single quotes'
+    Single,
+    /// Always use "double quotes"
+    Double,
+}
+
+impl QuoteStyle {
+    /// The quote character to use when replacing `original`, a quoted string literal.
+    pub fn quote_char(self, original: &str) -> char {
+        match self {
+            QuoteStyle::Auto if original.starts_with('\'') => '\'',
+            QuoteStyle::Auto | QuoteStyle::Double => '"',
+            QuoteStyle::Single => '\'',
+        }
+    }
+
+    /// `text` as a string literal replacing `original`, escaping the quote character.
+    pub fn quote(self, text: &str, original: &str) -> String {
+        let quote = self.quote_char(original);
+        format!("{}{}{}", quote, escape(text, quote), quote)
+    }
+}
+
+fn escape(text: &str, quote: char) -> String {
+    text.replace('\\', "\\\\")
+        .replace(quote, &format!("\\{}", quote))
+}
+
+/// Edit replacing the string literal `original` at `range` with one containing `text`.
+///
+/// Only the changed part between the quotes is replaced, so the surrounding layout and the
+/// quotes stay untouched unless `style` asks for a different quote character.
+pub fn replace_string(range: Range, original: &str, text: &str, style: QuoteStyle) -> TextEdit {
+    let quote = style.quote_char(original);
+    let contents = original
+        .strip_prefix(quote)
+        .and_then(|rest| rest.strip_suffix(quote))
+        .filter(|_| range.start.line == range.end.line);
+    let contents = match contents {
+        Some(contents) => contents,
+        None => {
+            return TextEdit {
+                range,
+                new_text: style.quote(text, original),
+            }
+        }
+    };
+
+    // The common parts in bytes to slice the contents, in UTF-16 code units to move the range
+    let new_contents = escape(text, quote);
+    let prefix: String = contents
+        .chars()
+        .zip(new_contents.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a)
+        .collect();
+    let suffix: String = contents[prefix.len()..]
+        .chars()
+        .rev()
+        .zip(new_contents[prefix.len()..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a)
+        .collect();
+
+    let mut edit_range = range;
+    edit_range.start.character += 1 + prefix.encode_utf16().count() as u32;
+    edit_range.end.character -= 1 + suffix.encode_utf16().count() as u32;
+    TextEdit {
+        range: edit_range,
+        new_text: new_contents[prefix.len()..new_contents.len() - suffix.len()].to_string(),
+    }
+}
+
+/// The text of `source` within the single line `range`, `None` for ranges spanning lines.
+pub fn text_at(source: &str, range: Range) -> Option<&str> {
+    if range.start.line != range.end.line {
+        return None;
+    }
+    source
+        .lines()
+        .nth(range.start.line as usize)?
+        .get(byte_column(source, range.start)..byte_column(source, range.end))
+}
+
+/// The byte column of `position` in its line of `source`, positions count UTF-16 code units.
+/// Positions past the end of the line are clamped to it.
+pub fn byte_column(source: &str, position: Position) -> usize {
+    let line = source
+        .lines()
+        .nth(position.line as usize)
+        .unwrap_or_default();
+    let mut units = 0;
+    for (column, c) in line.char_indices() {
+        if units >= position.character as usize {
+            return column;
+        }
+        units += c.len_utf16();
+    }
+    line.len()
+}
+
+/// The column of `point` of the syntax tree in UTF-16 code units, its column counts bytes.
+pub fn utf16_column(source: &str, point: tree_sitter::Point) -> u32 {
+    let line = source.lines().nth(point.row).unwrap_or_default();
+    let head = line.get(..point.column).unwrap_or(line);
+    head.encode_utf16().count() as u32
+}
+
+/// Edit appending `names` to the first array declared as `varname`, one per line if the array
+/// spans several lines. The quotes of the last entry are used for the new ones.
+pub fn append_entries(
+    source_code: &str,
+    varname: &str,
+    names: &[&str],
+    style: QuoteStyle,
+) -> Option<TextEdit> {
+    let (array, entries) = array_entries(source_code, varname)?;
+    let last = entries.last().map(|(range, _)| *range);
+    let original = last
+        .and_then(|range| text_at(source_code, range))
+        .unwrap_or("\"");
+    let quoted: Vec<String> = names
+        .iter()
+        .map(|name| style.quote(name, original))
+        .collect();
+
+    let (position, new_text) = match last {
+        Some(last) if array.start.line != array.end.line => {
+            let indent: String = source_code
+                .lines()
+                .nth(last.start.line as usize)
+                .unwrap_or_default()
+                .chars()
+                .take_while(|c| c.is_whitespace())
+                .collect();
+            let new_text = quoted
+                .iter()
+                .map(|quoted| format!(",\n{}{}", indent, quoted))
+                .collect();
+            (last.end, new_text)
+        }
+        Some(last) => (last.end, format!(", {}", quoted.join(", "))),
+        None => (
+            Position::new(array.start.line, array.start.character + 1),
+            quoted.join(", "),
+        ),
+    };
+    Some(TextEdit {
+        range: Range::new(position, position),
+        new_text,
+    })
+}
+
+/// Edits removing the entries of the first array declared as `varname` which `remove` selects,
+/// along with their separators.
+///
+/// An entry is removed up to the start of the next one, the last ones from the end of the entry
+/// before them, so the separators and the layout of the remaining entries stay as they are.
+pub fn remove_entries(
+    source_code: &str,
+    varname: &str,
+    remove: impl Fn(&str) -> bool,
+) -> Vec<TextEdit> {
+    let entries: Vec<(Range, bool)> = match array_entries(source_code, varname) {
+        Some((_, entries)) => entries
+            .into_iter()
+            .map(|(range, text)| (range, remove(text)))
+            .collect(),
+        None => return vec![],
+    };
+
+    let deletion = |start, end| TextEdit {
+        range: Range::new(start, end),
+        new_text: String::new(),
+    };
+    let last_kept = entries.iter().rposition(|(_, removed)| !removed);
+    let mut edits = Vec::new();
+    for (i, (range, removed)) in entries.iter().enumerate() {
+        if !removed {
+            continue;
+        }
+        match (last_kept, entries.get(i + 1)) {
+            (Some(kept), _) if i > kept => {
+                let end = entries[entries.len() - 1].0.end;
+                edits.push(deletion(entries[kept].0.end, end));
+                break;
+            }
+            (_, Some((next, _))) => edits.push(deletion(range.start, next.start)),
+            (_, None) => edits.push(deletion(range.start, range.end)),
+        }
+    }
+    edits
+}
+
+/// Edits removing the entries `remove` selects and appending `names`, the combination of
+/// [`remove_entries`] and [`append_entries`].
+pub fn sync_entries(
+    source_code: &str,
+    varname: &str,
+    names: &[&str],
+    remove: impl Fn(&str) -> bool,
+    style: QuoteStyle,
+) -> Vec<TextEdit> {
+    let entries = match array_entries(source_code, varname) {
+        Some((_, entries)) => entries,
+        None => return vec![],
+    };
+
+    // Appending after removed entries leaves a leading separator if none remain, replace them
+    if !names.is_empty() && !entries.is_empty() && entries.iter().all(|(_, text)| remove(text)) {
+        let original = text_at(source_code, entries[0].0).unwrap_or("\"");
+        let quoted: Vec<String> = names
+            .iter()
+            .map(|name| style.quote(name, original))
+            .collect();
+        return vec![TextEdit {
+            range: Range::new(entries[0].0.start, entries[entries.len() - 1].0.end),
+            new_text: quoted.join(", "),
+        }];
+    }
+
+    let mut edits = remove_entries(source_code, varname, remove);
+    if !names.is_empty() {
+        edits.extend(append_entries(source_code, varname, names, style));
+    }
+    edits
+}
+
+/// The range of the first array declared as `varname` and the ranges and texts of its entries.
+fn array_entries<'a>(
+    source_code: &'a str,
+    varname: &str,
+) -> Option<(Range, Vec<(Range, &'a str)>)> {
+    let array: Range = MyRange(
+        LspParser::parse_arrays(source_code, varname).first()?.range,
+        source_code,
+    )
+    .into();
+    let entries = LspParser::parse_code(source_code, varname)
+        .into_iter()
+        .map(|entry| (Range::from(MyRange(entry.range, source_code)), entry.text))
+        .filter(|(range, _)| array.start <= range.start && range.end <= array.end)
+        .collect();
+    Some((array, entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!("'assets'", QuoteStyle::Auto.quote("assets", "'asets'"));
+        assert_eq!("\"assets\"", QuoteStyle::Auto.quote("assets", "\"asets\""));
+        assert_eq!("\"it's\"", QuoteStyle::Double.quote("it's", "'its'"));
+        assert_eq!("'it\\'s'", QuoteStyle::Single.quote("it's", "\"its\""));
+    }
+
+    #[test]
+    fn test_replace_string() {
+        let range = Range::new(Position::new(1, 2), Position::new(1, 9));
+
+        let edit = replace_string(range, "'asets'", "assets", QuoteStyle::Auto);
+        assert_eq!(
+            Range::new(Position::new(1, 5), Position::new(1, 5)),
+            edit.range
+        );
+        assert_eq!("s", edit.new_text);
+
+        let edit = replace_string(range, "'asets'", "fonts", QuoteStyle::Auto);
+        assert_eq!(
+            Range::new(Position::new(1, 3), Position::new(1, 6)),
+            edit.range
+        );
+        assert_eq!("fon", edit.new_text);
+
+        let edit = replace_string(range, "'asets'", "assets", QuoteStyle::Double);
+        assert_eq!(range, edit.range);
+        assert_eq!("\"assets\"", edit.new_text);
+
+        // Ranges count UTF-16 code units, the emoji takes two of them
+        let range = Range::new(Position::new(1, 2), Position::new(1, 9));
+        let edit = replace_string(range, "'\u{1f600}ons'", "\u{1f600}icons", QuoteStyle::Auto);
+        assert_eq!(
+            Range::new(Position::new(1, 5), Position::new(1, 5)),
+            edit.range
+        );
+        assert_eq!("ic", edit.new_text);
+    }
+
+    fn apply(source_code: &str, mut edits: Vec<TextEdit>) -> String {
+        let mut lines: Vec<String> = source_code.lines().map(String::from).collect();
+        edits.sort_by_key(|edit| edit.range.start);
+        for edit in edits.iter().rev() {
+            let (start, end) = (edit.range.start, edit.range.end);
+            // Edits apply back to front, so the columns before them are those of the source
+            let head = &lines[start.line as usize][..byte_column(source_code, start)];
+            let tail = &lines[end.line as usize][byte_column(source_code, end)..];
+            let replaced = format!("{}{}{}", head, edit.new_text, tail);
+            lines.splice(start.line as usize..=end.line as usize, [replaced]);
+        }
+        lines.join("\n")
+    }
+
+    #[test]
+    fn test_append_entries() {
+        let append = |source_code: &str| {
+            let edit = append_entries(
+                source_code,
+                "folders",
+                &["icons", "sounds"],
+                QuoteStyle::Auto,
+            );
+            apply(source_code, edit.into_iter().collect())
+        };
+
+        assert_eq!(
+            "const folders = ['fonts', 'icons', 'sounds'];",
+            append("const folders = ['fonts'];")
+        );
+        assert_eq!(
+            "const folders = [\"icons\", \"sounds\"];",
+            append("const folders = [];")
+        );
+        assert_eq!(
+            "const folders = [\n\t\"fonts\",\n\t\"icons\",\n\t\"sounds\",\n];",
+            append("const folders = [\n\t\"fonts\",\n];")
+        );
+    }
+
+    #[test]
+    fn test_remove_entries() {
+        let remove = |source_code: &str| {
+            let edits = remove_entries(source_code, "folders", |text| text.starts_with('x'));
+            apply(source_code, edits)
+        };
+
+        assert_eq!(
+            "const folders = [\"a\", \"b\"];",
+            remove("const folders = [\"x1\", \"a\", \"x2\", \"b\", \"x3\", \"x4\"];")
+        );
+        assert_eq!(
+            "const folders = [\n  \"a\",\n];",
+            remove("const folders = [\n  \"x1\",\n  \"a\",\n  \"x2\",\n];")
+        );
+        assert_eq!(
+            "const folders = [];",
+            remove("const folders = [\"x1\", \"x2\"];")
+        );
+    }
+
+    #[test]
+    fn test_sync_entries() {
+        let sync = |source_code: &str| {
+            let edits = sync_entries(
+                source_code,
+                "folders",
+                &["icons"],
+                |text| text.starts_with('x'),
+                QuoteStyle::Auto,
+            );
+            apply(source_code, edits)
+        };
+
+        assert_eq!(
+            "const folders = ['a', 'icons'];",
+            sync("const folders = ['a', 'x1'];")
+        );
+        assert_eq!(
+            "const folders = ['icons'];",
+            sync("const folders = ['x1', 'x2'];")
+        );
+    }
+
+    #[test]
+    fn test_text_at() {
+        let source = "const folders = [\r\n  'asets',\r\n];";
+        let range = Range::new(Position::new(1, 2), Position::new(1, 9));
+
+        assert_eq!(Some("'asets'"), text_at(source, range));
+    }
+
+    #[test]
+    fn test_byte_column() {
+        let source = "const a = 1;\nconst \u{e9}\u{1f600} = [\"x\"];";
+        assert_eq!(6, byte_column(source, Position::new(1, 6)));
+        // é is 2 bytes and 1 code unit, the emoji 4 bytes and 2 code units
+        assert_eq!(8, byte_column(source, Position::new(1, 7)));
+        assert_eq!(12, byte_column(source, Position::new(1, 9)));
+        assert_eq!(21, byte_column(source, Position::new(1, 40)));
+    }
+
+    #[test]
+    fn test_utf16_column() {
+        let source = "const a = 1;\nconst \u{e9}\u{1f600} = [\"x\"];";
+        let point = |column| tree_sitter::Point::new(1, column);
+        assert_eq!(6, utf16_column(source, point(6)));
+        assert_eq!(7, utf16_column(source, point(8)));
+        assert_eq!(9, utf16_column(source, point(12)));
+        assert_eq!(18, utf16_column(source, point(21)));
+    }
+}
@@ -0,0 +1,183 @@
+use crate::config::Config;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use tree_sitter::{Language, Query};
+
+/// A tree-sitter grammar loaded at runtime, with the query finding the declared arrays.
+///
+/// The query captures the name of a declaration as `@id`, its array as `@array` and the
+/// entries of the array as `@item`. Arrays whose `@id` isn't the varname are ignored.
+pub struct Grammar {
+    pub name: String,
+    pub language: Language,
+    pub query: String,
+    /// Extensions of the files parsed with this grammar, without the leading dot
+    pub extensions: Vec<String>,
+}
+
+static GRAMMARS: RwLock<Vec<Arc<Grammar>>> = RwLock::new(Vec::new());
+
+/// Make `grammar` available to the parser, replacing a grammar of the same name.
+pub fn register(grammar: Grammar) {
+    if let Ok(mut grammars) = GRAMMARS.write() {
+        grammars.retain(|g| g.name != grammar.name);
+        grammars.push(Arc::new(grammar));
+    }
+}
+
+/// The registered grammar called `name`.
+pub fn get(name: &str) -> Option<Arc<Grammar>> {
+    GRAMMARS
+        .read()
+        .ok()?
+        .iter()
+        .find(|grammar| grammar.name == name)
+        .cloned()
+}
+
+/// Name of the registered grammar parsing the file at `path`.
+pub fn for_path(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?;
+    GRAMMARS
+        .read()
+        .ok()?
+        .iter()
+        .find(|grammar| grammar.extensions.iter().any(|ext| ext == extension))
+        .map(|grammar| grammar.name.clone())
+}
+
+/// Split a `grammar:varname` target into the registered grammar and the varname.
+pub fn split(varname: &str) -> Option<(Arc<Grammar>, &str)> {
+    let (name, varname) = varname.split_once(':')?;
+    Some((get(name)?, varname))
+}
+
+/// Load and register the grammars of `config`, a relative directory is resolved against `base`.
+pub fn register_config(config: &Config, base: &Path) -> Result<(), String> {
+    if let Some(dir) = &config.grammars {
+        load_grammars(&base.join(dir), &config.grammar_extensions)?
+            .into_iter()
+            .for_each(register);
+    }
+    Ok(())
+}
+
+/// Load the grammars compiled to shared libraries in `dir`.
+///
+/// `python.so`, `libtree-sitter-python.so` and the like export `tree_sitter_python` and need the
+/// query `python.scm` next to them. They parse files with the extensions listed for them in
+/// `extensions`, by default those with their name as extension.
+pub fn load_grammars(
+    dir: &Path,
+    extensions: &BTreeMap<String, Vec<String>>,
+) -> Result<Vec<Grammar>, String> {
+    let entries = fs::read_dir(dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+    let mut libraries: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == "so" || ext == "dylib" || ext == "dll")
+        })
+        .collect();
+    libraries.sort();
+
+    libraries
+        .iter()
+        .map(|path| {
+            load_grammar(path, extensions)
+                .map_err(|err| format!("Failed to load grammar {}: {}", path.display(), err))
+        })
+        .collect()
+}
+
+fn load_grammar(
+    path: &Path,
+    extensions: &BTreeMap<String, Vec<String>>,
+) -> Result<Grammar, String> {
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or("invalid file name")?;
+    let name = stem
+        .strip_prefix("libtree-sitter-")
+        .or_else(|| stem.strip_prefix("tree-sitter-"))
+        .unwrap_or(stem)
+        .to_string();
+
+    let query_path = path.with_file_name(format!("{}.scm", name));
+    let query = fs::read_to_string(&query_path)
+        .map_err(|err| format!("{}: {}", query_path.display(), err))?;
+    let language = load_language(path, &name)?;
+    Query::new(&language, &query).map_err(|err| format!("{}: {}", query_path.display(), err))?;
+
+    Ok(Grammar {
+        extensions: extensions
+            .get(&name)
+            .cloned()
+            .unwrap_or_else(|| vec![name.clone()]),
+        name,
+        language,
+        query,
+    })
+}
+
+#[cfg(feature = "grammars")]
+fn load_language(path: &Path, name: &str) -> Result<Language, String> {
+    use libloading::{Library, Symbol};
+    use tree_sitter::{LANGUAGE_VERSION, MIN_COMPATIBLE_LANGUAGE_VERSION};
+
+    let symbol = format!("tree_sitter_{}", name.replace('-', "_"));
+    let library = unsafe { Library::new(path) }.map_err(|err| err.to_string())?;
+    let language = unsafe {
+        let language_fn: Symbol<unsafe extern "C" fn() -> Language> = library
+            .get(symbol.as_bytes())
+            .map_err(|err| err.to_string())?;
+        language_fn()
+    };
+    // The language points into the library, which has to stay loaded
+    std::mem::forget(library);
+
+    let version = language.version();
+    if !(MIN_COMPATIBLE_LANGUAGE_VERSION..=LANGUAGE_VERSION).contains(&version) {
+        return Err(format!("incompatible language version {}", version));
+    }
+    Ok(language)
+}
+
+#[cfg(not(feature = "grammars"))]
+fn load_language(_path: &Path, _name: &str) -> Result<Language, String> {
+    Err("the server was built without the grammars feature".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register() {
+        register(Grammar {
+            name: "test-json".into(),
+            language: tree_sitter_json::language(),
+            query: String::new(),
+            extensions: vec!["test-json".into()],
+        });
+
+        assert_eq!(
+            Some("test-json".to_string()),
+            for_path(Path::new("dir/a.test-json"))
+        );
+        assert_eq!(None, for_path(Path::new("a.ts")));
+        let (grammar, varname) = split("test-json:folders").unwrap();
+        assert_eq!("test-json", grammar.name);
+        assert_eq!("folders", varname);
+        assert!(split("unknown:folders").is_none());
+        assert!(split("folders").is_none());
+    }
+
+    #[test]
+    fn test_load_grammars() {
+        assert!(load_grammars(Path::new("does-not-exist"), &BTreeMap::new()).is_err());
+    }
+}
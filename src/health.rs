@@ -0,0 +1,98 @@
+//! Health of the server, reported by the `tsm/status` request.
+//!
+//! Extensions tell users why entries aren't validated as expected, like a missing suggestions
+//! directory, rather than leaving them with a server which seems to be not working.
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    /// Not initialized yet
+    Starting,
+    /// The index is being built, lookups use the previous one
+    Indexing,
+    Ready,
+    /// Running, but entries can't be validated properly
+    Degraded,
+}
+
+impl State {
+    pub fn name(&self) -> &'static str {
+        match self {
+            State::Starting => "starting",
+            State::Indexing => "indexing",
+            State::Ready => "ready",
+            State::Degraded => "degraded",
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The state of the server with the reason it's degraded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Health {
+    pub state: State,
+    pub reason: Option<String>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            state: State::Starting,
+            reason: None,
+        }
+    }
+}
+
+impl Health {
+    /// Move to `state`, returns a message describing the transition if the state or its
+    /// reason changed.
+    pub fn transition(&mut self, state: State, reason: Option<String>) -> Option<String> {
+        if self.state == state && self.reason == reason {
+            return None;
+        }
+        let message = match &reason {
+            Some(reason) => format!("Server {} (was {}): {}", state, self.state, reason),
+            None => format!("Server {} (was {})", state, self.state),
+        };
+        self.state = state;
+        self.reason = reason;
+        Some(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transition() {
+        let mut health = Health::default();
+        assert_eq!(
+            Some("Server indexing (was starting)".into()),
+            health.transition(State::Indexing, None)
+        );
+        assert_eq!(None, health.transition(State::Indexing, None));
+        assert_eq!(
+            Some("Server degraded (was indexing): missing".into()),
+            health.transition(State::Degraded, Some("missing".into()))
+        );
+        assert_eq!(
+            Some("Server degraded (was degraded): failed".into()),
+            health.transition(State::Degraded, Some("failed".into()))
+        );
+        health.transition(State::Ready, None);
+        assert_eq!(
+            Health {
+                state: State::Ready,
+                reason: None
+            },
+            health
+        );
+    }
+}
@@ -0,0 +1,454 @@
+/// Languages with a message catalog, English is used for all other locales.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+}
+
+impl Locale {
+    /// Parse a locale tag as sent in `InitializeParams::locale`, e.g. `de`, `de-AT` or `de_DE`.
+    pub fn from_tag(tag: &str) -> Self {
+        let language = tag
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        match language.as_str() {
+            "de" => Locale::De,
+            _ => Locale::En,
+        }
+    }
+}
+
+/// The noun naming the entries in messages, like "module" for arrays of module names, instead of
+/// "folder".
+#[derive(Clone, Debug, PartialEq)]
+pub struct Noun {
+    pub singular: String,
+    pub plural: String,
+}
+
+/// User facing messages shown in the editor.
+pub enum Message<'a> {
+    InvalidFolder {
+        name: &'a str,
+        dir: &'a str,
+        noun: Option<&'a Noun>,
+    },
+    DeniedFolder(&'a str),
+    DuplicateEntry(&'a str),
+    UnsortedEntries(&'a str),
+    MissingEntries {
+        count: usize,
+        names: &'a str,
+        noun: Option<&'a Noun>,
+    },
+    EmptyEntry(Option<&'a Noun>),
+    NotADirectory(&'a str),
+    AbsolutePath(&'a str),
+    PathTraversal(&'a str),
+    NamingConvention {
+        name: &'a str,
+        pattern: &'a str,
+    },
+    DeprecatedFolder {
+        name: &'a str,
+        replacement: &'a str,
+    },
+    CaseMismatch {
+        name: &'a str,
+        folder: &'a str,
+    },
+    UseFolder(&'a str),
+    MigrateFolder(&'a str),
+    FixCasing(&'a str),
+    RenameEntry(&'a str),
+    SortEntries,
+    AddMissingEntries(usize),
+    SyncFolders {
+        added: &'a str,
+        removed: &'a str,
+    },
+    FoldersInSync,
+    CreateMissingFolders(&'a str),
+    NoMissingFolders,
+    CreatingFolders,
+    CreatedFolder(&'a str),
+    CreateFolderFailed {
+        name: &'a str,
+        error: &'a str,
+    },
+    CreatedFolders {
+        created: usize,
+        total: usize,
+    },
+    Apply,
+    Cancel,
+    FixAll,
+    ReplaceInvalidEntries(usize),
+    RevealFolder(&'a str),
+    CreateFolder(&'a str),
+    RevealFolderSummary {
+        name: &'a str,
+        count: usize,
+        size: u64,
+    },
+    RevealFolderFailed {
+        path: &'a str,
+        error: &'a str,
+    },
+    Directory,
+    MissingSuggestionsDir(&'a str),
+    UnreadableSuggestions(&'a str),
+    FolderContents {
+        count: usize,
+        names: &'a str,
+    },
+    EmptyFolder,
+    Summary {
+        varname: &'a str,
+        total: usize,
+        valid: usize,
+        invalid: usize,
+    },
+    InvalidEntries,
+    DidYouMean(&'a str),
+}
+
+impl Message<'_> {
+    pub fn text(&self, locale: Locale) -> String {
+        match locale {
+            Locale::En => en(self),
+            Locale::De => de(self),
+        }
+    }
+}
+
+fn en(message: &Message) -> String {
+    match message {
+        Message::InvalidFolder { name, dir, noun } => {
+            let (singular, plural) = singular_plural(*noun, "folder", "folders");
+            format!(
+                "'{}' is not a valid {}, valid {} are those in '{}'",
+                name, singular, plural, dir
+            )
+        }
+        Message::DeniedFolder(name) => format!("'{}' is not allowed", name),
+        Message::DuplicateEntry(name) => format!("'{}' is listed more than once", name),
+        Message::UnsortedEntries(name) => format!("'{}' is not in alphabetical order", name),
+        Message::MissingEntries { count, names, noun } => {
+            let (_, plural) = singular_plural(*noun, "folder", "folders");
+            format!("{} {} are not listed: {}", count, plural, names)
+        }
+        Message::EmptyEntry(noun) => {
+            let (_, plural) = singular_plural(*noun, "folder", "folders");
+            format!("Empty entries are not valid {}", plural)
+        }
+        Message::NotADirectory(name) => format!("'{}' is a file, not a folder", name),
+        Message::AbsolutePath(name) => format!("'{}' is an absolute path", name),
+        Message::PathTraversal(name) => {
+            format!("'{}' must not contain '..' segments", name)
+        }
+        Message::NamingConvention { name, pattern } => {
+            format!(
+                "'{}' does not match the naming convention '{}'",
+                name, pattern
+            )
+        }
+        Message::DeprecatedFolder { name, replacement } => {
+            format!("'{}' is deprecated, use '{}' instead", name, replacement)
+        }
+        Message::CaseMismatch { name, folder } => {
+            format!("'{}' differs from '{}' only by case", name, folder)
+        }
+        Message::UseFolder(name) => format!("Use folder {}", name),
+        Message::MigrateFolder(name) => format!("Migrate to folder {}", name),
+        Message::FixCasing(name) => format!("Fix casing to '{}'", name),
+        Message::RenameEntry(name) => format!("Rename to {}", name),
+        Message::SortEntries => "Sort entries alphabetically".into(),
+        Message::AddMissingEntries(count) => format!("Add {} missing folders", count),
+        Message::SyncFolders { added, removed } => format!(
+            "Sync the array with the folders on disk? Adding: {}. Removing: {}.",
+            or_none(added, "none"),
+            or_none(removed, "none")
+        ),
+        Message::FoldersInSync => "The array lists exactly the folders on disk".into(),
+        Message::CreateMissingFolders(names) => format!("Create the missing folders? {}", names),
+        Message::NoMissingFolders => "All folders of the array exist".into(),
+        Message::CreatingFolders => "Creating folders".into(),
+        Message::CreatedFolder(name) => format!("Created {}", name),
+        Message::CreateFolderFailed { name, error } => {
+            format!("Failed to create {}: {}", name, error)
+        }
+        Message::CreatedFolders { created, total } => {
+            format!("Created {} of {} folders", created, total)
+        }
+        Message::Apply => "Apply".into(),
+        Message::Cancel => "Cancel".into(),
+        Message::FixAll => "Fix all auto-fixable problems".into(),
+        Message::ReplaceInvalidEntries(count) => {
+            format!("Replace {} invalid entries with best matches", count)
+        }
+        Message::RevealFolder(name) => format!("Reveal folder {}", name),
+        Message::CreateFolder(name) => format!("Create folder {}", name),
+        Message::RevealFolderSummary { name, count, size } => format!(
+            "Reveal folder {} ({} entries, {})",
+            name,
+            count,
+            format_size(*size)
+        ),
+        Message::RevealFolderFailed { path, error } => {
+            format!("Failed to reveal folder '{}': {}", path, error)
+        }
+        Message::Directory => "Directory".into(),
+        Message::MissingSuggestionsDir(dir) => format!(
+            "The suggestions directory '{}' doesn't exist, entries aren't validated",
+            dir
+        ),
+        Message::UnreadableSuggestions(err) => format!(
+            "The suggestions couldn't be read, entries aren't validated: {}",
+            err
+        ),
+        Message::FolderContents { count, names } => format!("{} entries: {}", count, names),
+        Message::EmptyFolder => "Empty folder".into(),
+        Message::Summary {
+            varname,
+            total,
+            valid,
+            invalid,
+        } => format!(
+            "**{}**: {} entries, {} valid, {} invalid",
+            varname, total, valid, invalid
+        ),
+        Message::InvalidEntries => "Invalid entries:".into(),
+        Message::DidYouMean(suggestions) => format!("did you mean {}?", suggestions),
+    }
+}
+
+fn de(message: &Message) -> String {
+    match message {
+        // The gender of configured nouns is unknown, they are used without an article
+        Message::InvalidFolder {
+            name,
+            dir,
+            noun: Some(noun),
+        } => format!(
+            "'{}' ist ungültig ({}), gültige {} sind die in '{}'",
+            name, noun.singular, noun.plural, dir
+        ),
+        Message::InvalidFolder {
+            name,
+            dir,
+            noun: None,
+        } => format!(
+            "'{}' ist kein gültiger Ordner, gültige Ordner sind die in '{}'",
+            name, dir
+        ),
+        Message::DeniedFolder(name) => format!("'{}' ist nicht erlaubt", name),
+        Message::DuplicateEntry(name) => format!("'{}' ist mehrfach aufgeführt", name),
+        Message::UnsortedEntries(name) => {
+            format!("'{}' ist nicht alphabetisch einsortiert", name)
+        }
+        Message::MissingEntries { count, names, noun } => {
+            let (_, plural) = singular_plural(*noun, "Ordner", "Ordner");
+            format!("{} {} sind nicht aufgeführt: {}", count, plural, names)
+        }
+        Message::EmptyEntry(noun) => {
+            let (_, plural) = singular_plural(*noun, "Ordner", "Ordner");
+            format!("Leere Einträge sind keine gültigen {}", plural)
+        }
+        Message::NotADirectory(name) => format!("'{}' ist eine Datei, kein Ordner", name),
+        Message::AbsolutePath(name) => format!("'{}' ist ein absoluter Pfad", name),
+        Message::PathTraversal(name) => {
+            format!("'{}' darf keine '..'-Segmente enthalten", name)
+        }
+        Message::NamingConvention { name, pattern } => format!(
+            "'{}' entspricht nicht der Namenskonvention '{}'",
+            name, pattern
+        ),
+        Message::DeprecatedFolder { name, replacement } => format!(
+            "'{}' ist veraltet, stattdessen '{}' verwenden",
+            name, replacement
+        ),
+        Message::CaseMismatch { name, folder } => format!(
+            "'{}' unterscheidet sich von '{}' nur in der Groß-/Kleinschreibung",
+            name, folder
+        ),
+        Message::UseFolder(name) => format!("Ordner {} verwenden", name),
+        Message::MigrateFolder(name) => format!("Zu Ordner {} migrieren", name),
+        Message::FixCasing(name) => format!("Schreibweise zu '{}' korrigieren", name),
+        Message::RenameEntry(name) => format!("In {} umbenennen", name),
+        Message::SortEntries => "Einträge alphabetisch sortieren".into(),
+        Message::AddMissingEntries(count) => format!("{} fehlende Ordner hinzufügen", count),
+        Message::SyncFolders { added, removed } => format!(
+            "Array mit den Ordnern auf der Festplatte abgleichen? Hinzufügen: {}. Entfernen: {}.",
+            or_none(added, "keine"),
+            or_none(removed, "keine")
+        ),
+        Message::FoldersInSync => "Das Array führt genau die Ordner auf der Festplatte auf".into(),
+        Message::CreateMissingFolders(names) => format!("Fehlende Ordner anlegen? {}", names),
+        Message::NoMissingFolders => "Alle Ordner des Arrays existieren".into(),
+        Message::CreatingFolders => "Ordner werden angelegt".into(),
+        Message::CreatedFolder(name) => format!("{} angelegt", name),
+        Message::CreateFolderFailed { name, error } => {
+            format!("{} konnte nicht angelegt werden: {}", name, error)
+        }
+        Message::CreatedFolders { created, total } => {
+            format!("{} von {} Ordnern angelegt", created, total)
+        }
+        Message::Apply => "Anwenden".into(),
+        Message::Cancel => "Abbrechen".into(),
+        Message::FixAll => "Alle automatisch behebbaren Probleme beheben".into(),
+        Message::ReplaceInvalidEntries(count) => {
+            format!("{} ungültige Einträge durch beste Treffer ersetzen", count)
+        }
+        Message::RevealFolder(name) => format!("Ordner {} anzeigen", name),
+        Message::CreateFolder(name) => format!("Ordner {} anlegen", name),
+        Message::RevealFolderSummary { name, count, size } => format!(
+            "Ordner {} anzeigen ({} Einträge, {})",
+            name,
+            count,
+            format_size(*size).replace('.', ",")
+        ),
+        Message::RevealFolderFailed { path, error } => {
+            format!("Ordner '{}' konnte nicht angezeigt werden: {}", path, error)
+        }
+        Message::Directory => "Verzeichnis".into(),
+        Message::MissingSuggestionsDir(dir) => format!(
+            "Das Vorschlagsverzeichnis '{}' existiert nicht, Einträge werden nicht geprüft",
+            dir
+        ),
+        Message::UnreadableSuggestions(err) => format!(
+            "Die Vorschläge konnten nicht gelesen werden, Einträge werden nicht geprüft: {}",
+            err
+        ),
+        Message::FolderContents { count, names } => format!("{} Einträge: {}", count, names),
+        Message::EmptyFolder => "Leerer Ordner".into(),
+        Message::Summary {
+            varname,
+            total,
+            valid,
+            invalid,
+        } => format!(
+            "**{}**: {} Einträge, {} gültig, {} ungültig",
+            varname, total, valid, invalid
+        ),
+        Message::InvalidEntries => "Ungültige Einträge:".into(),
+        Message::DidYouMean(suggestions) => format!("meinten Sie {}?", suggestions),
+    }
+}
+
+/// `bytes` in the largest unit keeping the value at least 1, with one decimal beyond bytes.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Singular and plural of `noun`, `singular` and `plural` if there is none.
+fn singular_plural<'a>(
+    noun: Option<&'a Noun>,
+    singular: &'a str,
+    plural: &'a str,
+) -> (&'a str, &'a str) {
+    match noun {
+        Some(noun) => (&noun.singular, &noun.plural),
+        None => (singular, plural),
+    }
+}
+
+/// `list`, or `none` if it is empty.
+fn or_none<'a>(list: &'a str, none: &'a str) -> &'a str {
+    if list.is_empty() {
+        none
+    } else {
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_tag() {
+        assert_eq!(Locale::De, Locale::from_tag("de"));
+        assert_eq!(Locale::De, Locale::from_tag("de-AT"));
+        assert_eq!(Locale::De, Locale::from_tag("DE_de"));
+        assert_eq!(Locale::En, Locale::from_tag("en-US"));
+        assert_eq!(Locale::En, Locale::from_tag("fr"));
+        assert_eq!(Locale::En, Locale::from_tag(""));
+    }
+
+    #[test]
+    fn test_catalogs() {
+        assert_eq!(
+            "Use folder dir_a",
+            Message::UseFolder("dir_a").text(Locale::En)
+        );
+        assert_eq!(
+            "Ordner dir_a verwenden",
+            Message::UseFolder("dir_a").text(Locale::De)
+        );
+        let summary = Message::RevealFolderSummary {
+            name: "assets",
+            count: 3,
+            size: 1536,
+        };
+        assert_eq!(
+            "Reveal folder assets (3 entries, 1.5 KB)",
+            summary.text(Locale::En)
+        );
+        assert_eq!(
+            "Ordner assets anzeigen (3 Einträge, 1,5 KB)",
+            summary.text(Locale::De)
+        );
+    }
+
+    #[test]
+    fn test_noun() {
+        let noun = Noun {
+            singular: "module".into(),
+            plural: "modules".into(),
+        };
+        let message = |noun| Message::InvalidFolder {
+            name: "ui",
+            dir: "src",
+            noun,
+        };
+        assert_eq!(
+            "'ui' is not a valid folder, valid folders are those in 'src'",
+            message(None).text(Locale::En)
+        );
+        assert_eq!(
+            "'ui' is not a valid module, valid modules are those in 'src'",
+            message(Some(&noun)).text(Locale::En)
+        );
+        assert_eq!(
+            "'ui' ist ungültig (module), gültige modules sind die in 'src'",
+            message(Some(&noun)).text(Locale::De)
+        );
+        assert_eq!(
+            "Empty entries are not valid modules",
+            Message::EmptyEntry(Some(&noun)).text(Locale::En)
+        );
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!("0 B", format_size(0));
+        assert_eq!("1023 B", format_size(1023));
+        assert_eq!("1.0 KB", format_size(1024));
+        assert_eq!("2.5 MB", format_size(5 * 512 * 1024));
+    }
+}
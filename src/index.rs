@@ -0,0 +1,219 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
+use std::sync::Arc;
+use unicode_normalization::UnicodeNormalization;
+
+/// In-memory index over the available folder names.
+///
+/// Names are kept sorted for prefix queries, and a bigram index narrows down the candidates
+/// for approximate queries, so neither completion nor quick fixes need to scan every name.
+#[derive(Debug, Default)]
+pub struct SuggestionIndex {
+    names: BTreeSet<Arc<str>>,
+    /// Normalized names with the number of names normalizing to them, NFC-equal names like
+    /// composed and decomposed accents may both be listed
+    normalized: HashMap<String, usize>,
+    bigrams: HashMap<(char, char), BTreeSet<Arc<str>>>,
+    /// Names of regular files rather than folders, listed among the names nonetheless
+    files: HashSet<Arc<str>>,
+    byte_exact: bool,
+}
+
+fn bigrams(name: &str) -> HashSet<(char, char)> {
+    let chars: Vec<char> = name.chars().flat_map(|c| c.to_lowercase()).collect();
+    chars.windows(2).map(|w| (w[0], w[1])).collect()
+}
+
+impl SuggestionIndex {
+    pub fn new(byte_exact: bool) -> Self {
+        Self {
+            byte_exact,
+            ..Self::default()
+        }
+    }
+
+    pub fn build(names: impl IntoIterator<Item = String>, byte_exact: bool) -> Self {
+        let mut index = Self::new(byte_exact);
+        for name in names {
+            index.insert(&name);
+        }
+        index
+    }
+
+    fn normalize(&self, name: &str) -> String {
+        if self.byte_exact {
+            name.to_string()
+        } else {
+            name.nfc().collect()
+        }
+    }
+
+    pub fn insert(&mut self, name: &str) {
+        let name: Arc<str> = Arc::from(name);
+        if !self.names.insert(name.clone()) {
+            return;
+        }
+
+        *self.normalized.entry(self.normalize(&name)).or_default() += 1;
+        for bigram in bigrams(&name) {
+            self.bigrams.entry(bigram).or_default().insert(name.clone());
+        }
+    }
+
+    /// Mark the names among `files` as regular files rather than folders.
+    pub fn mark_files(&mut self, files: impl IntoIterator<Item = String>) {
+        for file in files {
+            if let Some(name) = self.names.get(file.as_str()) {
+                self.files.insert(name.clone());
+            }
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        if !self.names.remove(name) {
+            return;
+        }
+        self.files.remove(name);
+
+        let normalized = self.normalize(name);
+        if let Some(count) = self.normalized.get_mut(&normalized) {
+            *count -= 1;
+            if *count == 0 {
+                self.normalized.remove(&normalized);
+            }
+        }
+        for bigram in bigrams(name) {
+            if let Some(names) = self.bigrams.get_mut(&bigram) {
+                names.remove(name);
+                if names.is_empty() {
+                    self.bigrams.remove(&bigram);
+                }
+            }
+        }
+    }
+
+    /// Whether `name` is an available folder, compared after normalization.
+    pub fn contains(&self, name: &str) -> bool {
+        self.normalized.contains_key(&self.normalize(name))
+    }
+
+    /// Whether `name` is listed, but as a regular file rather than a folder.
+    pub fn is_file(&self, name: &str) -> bool {
+        self.files.contains(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// All names in sorted order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(|name| name.as_ref())
+    }
+
+    /// All names starting with `prefix`, in sorted order.
+    pub fn prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a str> {
+        self.names
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .map(|name| name.as_ref())
+            .take_while(move |name: &&str| name.starts_with(prefix))
+    }
+
+    /// Names sharing at least one (case-insensitive) bigram with `input`, in sorted order.
+    ///
+    /// Inputs too short to form a bigram match every name.
+    pub fn candidates(&self, input: &str) -> Vec<&str> {
+        let input_bigrams = bigrams(input);
+        if input_bigrams.is_empty() {
+            return self.names.iter().map(|name| name.as_ref()).collect();
+        }
+
+        let candidates: BTreeSet<&str> = input_bigrams
+            .iter()
+            .filter_map(|bigram| self.bigrams.get(bigram))
+            .flat_map(|names| names.iter().map(|name| name.as_ref()))
+            .collect();
+        candidates.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> SuggestionIndex {
+        SuggestionIndex::build(
+            ["assets", "assets_old", "fonts", "images"].map(String::from),
+            false,
+        )
+    }
+
+    #[test]
+    fn test_prefix() {
+        let index = index();
+        assert_eq!(
+            vec!["assets", "assets_old"],
+            index.prefix("ass").collect::<Vec<&str>>()
+        );
+        assert_eq!(4, index.prefix("").count());
+        assert_eq!(0, index.prefix("x").count());
+    }
+
+    #[test]
+    fn test_candidates() {
+        let index = index();
+        assert_eq!(vec!["fonts"], index.candidates("FONT"));
+        assert_eq!(4, index.candidates("a").len());
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let mut index = index();
+        index.insert("icons");
+        assert!(index.contains("icons"));
+        assert_eq!(vec!["fonts", "icons"], index.candidates("icon"));
+
+        index.remove("icons");
+        assert!(!index.contains("icons"));
+        assert_eq!(vec!["fonts"], index.candidates("icon"));
+        assert_eq!(4, index.len());
+    }
+
+    #[test]
+    fn test_mark_files() {
+        let mut index = index();
+        index.mark_files(["fonts", "readme.md"].map(String::from));
+        assert!(index.is_file("fonts"));
+        assert!(!index.is_file("assets"));
+        assert!(!index.is_file("readme.md"));
+
+        index.remove("fonts");
+        index.insert("fonts");
+        assert!(!index.is_file("fonts"));
+    }
+
+    #[test]
+    fn test_contains_normalized() {
+        let index = SuggestionIndex::build(["caf\u{e9}".to_string()], false);
+        assert!(index.contains("cafe\u{301}"));
+
+        let index = SuggestionIndex::build(["caf\u{e9}".to_string()], true);
+        assert!(!index.contains("cafe\u{301}"));
+    }
+
+    #[test]
+    fn test_remove_normalized_twin() {
+        let mut index =
+            SuggestionIndex::build(["caf\u{e9}".to_string(), "cafe\u{301}".to_string()], false);
+        index.remove("cafe\u{301}");
+        assert!(index.contains("caf\u{e9}"));
+        assert!(index.contains("cafe\u{301}"));
+
+        index.remove("caf\u{e9}");
+        assert!(!index.contains("caf\u{e9}"));
+    }
+}
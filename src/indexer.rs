@@ -0,0 +1,696 @@
+use crate::cache;
+use crate::index::SuggestionIndex;
+use crate::paths;
+use crate::provider::Provider;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use tower_lsp::lsp_types::{FileChangeType, FileEvent};
+use walkdir::{DirEntry, WalkDir};
+
+/// Directories which are never descended into when scanning the workspace.
+const IGNORED_DIRS: [&str; 3] = ["node_modules", "target", "dist"];
+
+/// How long a failed read of the names is reported before they are read again, instead of on
+/// every keystroke, e.g. while a network mount is unavailable.
+const FAILURE_TTL: Duration = Duration::from_secs(30);
+
+/// Settings the indexer needs to scan the filesystem.
+#[derive(Clone, Debug)]
+pub struct IndexerConfig {
+    pub provider: Provider,
+    pub byte_exact: bool,
+    pub extensions: Vec<String>,
+    /// How long the names of an expiring provider are served before they are refreshed
+    pub ttl: Duration,
+    /// Directory persisting the names between sessions
+    pub cache_dir: Option<PathBuf>,
+}
+
+enum Request {
+    Rebuild(
+        oneshot::Sender<Result<usize, String>>,
+        mpsc::UnboundedSender<IndexProgress>,
+    ),
+    FilesChanged(Vec<FileEvent>, oneshot::Sender<()>),
+    SetProvider(Provider),
+    Load(Provider, oneshot::Sender<()>),
+    Unload(Provider),
+    Revalidate,
+    Stop(oneshot::Sender<()>),
+    ScanWorkspace {
+        roots: Vec<PathBuf>,
+        needle: String,
+        reply: oneshot::Sender<Vec<(PathBuf, String)>>,
+    },
+}
+
+/// Progress of an index rebuild.
+#[derive(Clone, Debug)]
+pub struct IndexProgress {
+    pub percentage: u32,
+    pub path: String,
+}
+
+/// Handle to the background indexer task, which owns all filesystem scanning.
+///
+/// Request handlers only read the shared index, every update goes through the task.
+#[derive(Clone)]
+pub struct Indexer {
+    sender: mpsc::UnboundedSender<Request>,
+    index: Arc<RwLock<SuggestionIndex>>,
+    /// Indexes of further providers, e.g. suggestion directories next to documents, keyed by
+    /// their location
+    documents: DocumentIndexes,
+    /// Incremented whenever the index changed
+    generation: Arc<AtomicU64>,
+    /// Errors of the last reads of providers which failed, with the time they were last tried
+    failures: Failures,
+}
+
+type DocumentIndexes = Arc<RwLock<HashMap<String, Arc<RwLock<SuggestionIndex>>>>>;
+
+type Failures = Arc<RwLock<HashMap<String, (String, Instant)>>>;
+
+impl Indexer {
+    /// Start the indexer task on the current tokio runtime.
+    pub fn spawn(config: IndexerConfig) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let index = Arc::new(RwLock::new(SuggestionIndex::new(config.byte_exact)));
+        let documents = DocumentIndexes::default();
+        let generation = Arc::new(AtomicU64::new(0));
+        let failures = Failures::default();
+        tokio::spawn(run(
+            receiver,
+            config,
+            index.clone(),
+            documents.clone(),
+            generation.clone(),
+            failures.clone(),
+        ));
+
+        Self {
+            sender,
+            index,
+            documents,
+            generation,
+            failures,
+        }
+    }
+
+    pub fn index(&self) -> &RwLock<SuggestionIndex> {
+        &self.index
+    }
+
+    /// Changes whenever the index changed, e.g. by a rebuild or a refresh in the background.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// Refresh the index in the background if the names of the provider expired, lookups are
+    /// answered from the previous index meanwhile.
+    pub fn revalidate(&self) {
+        let _ = self.sender.send(Request::Revalidate);
+    }
+
+    /// Why the names of `provider` couldn't be read the last time, its index is empty or
+    /// outdated meanwhile.
+    pub fn failure(&self, provider: &Provider) -> Option<String> {
+        self.failures.read().ok().and_then(|failures| {
+            failures
+                .get(&provider.location())
+                .map(|(err, _)| err.clone())
+        })
+    }
+
+    /// The index of `provider` if it was loaded with [`Indexer::load`].
+    pub fn document_index(&self, provider: &Provider) -> Option<Arc<RwLock<SuggestionIndex>>> {
+        self.documents
+            .read()
+            .ok()
+            .and_then(|documents| documents.get(&provider.location()).cloned())
+    }
+
+    /// Build an index of `provider` unless it exists, it's kept up to date by file events.
+    pub async fn load(&self, provider: Provider) {
+        let (reply, response) = oneshot::channel();
+        if self.sender.send(Request::Load(provider, reply)).is_ok() {
+            let _ = response.await;
+        }
+    }
+
+    /// Forget the index of `provider`, e.g. once no opened document uses it. It's built again
+    /// when it's loaded next.
+    pub fn unload(&self, provider: Provider) {
+        let _ = self.sender.send(Request::Unload(provider));
+    }
+
+    /// Rebuild the index from the provider, returns the number of indexed folders.
+    ///
+    /// The previous index keeps answering lookups until the rebuild finished, or if the
+    /// provider failed.
+    pub async fn rebuild(
+        &self,
+        progress: mpsc::UnboundedSender<IndexProgress>,
+    ) -> Result<usize, String> {
+        let (reply, response) = oneshot::channel();
+        if self.sender.send(Request::Rebuild(reply, progress)).is_err() {
+            return Err("The indexer stopped".into());
+        }
+        response
+            .await
+            .unwrap_or_else(|_| Err("The indexer stopped".into()))
+    }
+
+    /// Stop the indexer task once the requests sent before are done, lookups keep answering
+    /// from the last index.
+    pub async fn stop(&self) {
+        let (reply, response) = oneshot::channel();
+        if self.sender.send(Request::Stop(reply)).is_ok() {
+            let _ = response.await;
+        }
+    }
+
+    /// Use `provider` for the following rebuilds and file events.
+    pub fn set_provider(&self, provider: Provider) {
+        let _ = self.sender.send(Request::SetProvider(provider));
+    }
+
+    /// Apply watched file events and wait until the index reflects them.
+    pub async fn files_changed(&self, changes: Vec<FileEvent>) {
+        let (reply, response) = oneshot::channel();
+        if self
+            .sender
+            .send(Request::FilesChanged(changes, reply))
+            .is_ok()
+        {
+            let _ = response.await;
+        }
+    }
+
+    /// Paths and contents of all workspace files below `roots` containing `needle`.
+    pub async fn scan_workspace(
+        &self,
+        roots: Vec<PathBuf>,
+        needle: String,
+    ) -> Vec<(PathBuf, String)> {
+        let (reply, response) = oneshot::channel();
+        let request = Request::ScanWorkspace {
+            roots,
+            needle,
+            reply,
+        };
+        if self.sender.send(request).is_err() {
+            return vec![];
+        }
+        response.await.unwrap_or_default()
+    }
+}
+
+async fn run(
+    mut receiver: mpsc::UnboundedReceiver<Request>,
+    mut config: IndexerConfig,
+    index: Arc<RwLock<SuggestionIndex>>,
+    documents: DocumentIndexes,
+    generation: Arc<AtomicU64>,
+    failures: Failures,
+) {
+    let mut document_providers: Vec<Provider> = vec![];
+    // Start of the last build of the index, also of one still running in the background
+    let mut built_at = Some(Instant::now());
+    while let Some(request) = receiver.recv().await {
+        match request {
+            Request::Rebuild(reply, progress) => {
+                built_at = Some(Instant::now());
+                let provider = config.provider.clone();
+                let byte_exact = config.byte_exact;
+                let cache_dir = config.cache_dir.clone();
+                let rebuilt = tokio::task::spawn_blocking(move || {
+                    build_index(&provider, byte_exact, cache_dir.as_deref(), progress)
+                })
+                .await
+                .unwrap_or_else(|err| Err(err.to_string()));
+                record_read(&failures, &config.provider, rebuilt.as_ref().err());
+
+                let len = rebuilt.and_then(|rebuilt| match index.write() {
+                    Ok(mut current) => {
+                        *current = rebuilt;
+                        generation.fetch_add(1, Ordering::Relaxed);
+                        Ok(current.len())
+                    }
+                    Err(_) => Err("The index is poisoned".into()),
+                });
+                let _ = reply.send(len);
+            }
+            Request::FilesChanged(changes, reply) => {
+                apply_changes(&config.provider, config.byte_exact, &index, &changes);
+                if config.provider.expires() && !changes.is_empty() {
+                    built_at = None;
+                }
+                for provider in &document_providers {
+                    if let Some(index) = documents
+                        .read()
+                        .ok()
+                        .and_then(|documents| documents.get(&provider.location()).cloned())
+                    {
+                        apply_changes(provider, config.byte_exact, &index, &changes);
+                    }
+                }
+                generation.fetch_add(1, Ordering::Relaxed);
+                let _ = reply.send(());
+            }
+            Request::Revalidate => {
+                let expired = built_at.is_none_or(|built_at| built_at.elapsed() >= config.ttl);
+                // Names which failed to be read are read again once the failure expired
+                let retry = retry_due(&failures, &config.provider);
+                if (config.provider.expires() && expired) || retry {
+                    built_at = Some(Instant::now());
+                    tokio::spawn(refresh(
+                        config.provider.clone(),
+                        config.byte_exact,
+                        index.clone(),
+                        generation.clone(),
+                        failures.clone(),
+                    ));
+                }
+            }
+            Request::Stop(reply) => {
+                let _ = reply.send(());
+                break;
+            }
+            Request::SetProvider(provider) => config.provider = provider,
+            Request::Load(provider, reply) => {
+                let known = document_providers.contains(&provider);
+                let failed = failures
+                    .read()
+                    .is_ok_and(|failures| failures.contains_key(&provider.location()));
+                // Failed reads are retried once the failure expired, not on every load
+                if (!known && !failed) || retry_due(&failures, &provider) {
+                    let byte_exact = config.byte_exact;
+                    let cache_dir = config.cache_dir.clone();
+                    let loaded = provider.clone();
+                    let built = tokio::task::spawn_blocking(move || {
+                        let (progress, _) = mpsc::unbounded_channel();
+                        build_index(&loaded, byte_exact, cache_dir.as_deref(), progress)
+                    })
+                    .await
+                    .unwrap_or_else(|err| Err(err.to_string()));
+                    record_read(&failures, &provider, built.as_ref().err());
+                    // A known provider keeps its previous index if reading it again failed
+                    if let Ok(built) = built {
+                        if let Ok(mut documents) = documents.write() {
+                            documents.insert(provider.location(), Arc::new(RwLock::new(built)));
+                        }
+                        generation.fetch_add(1, Ordering::Relaxed);
+                        if !known {
+                            document_providers.push(provider);
+                        }
+                    }
+                }
+                let _ = reply.send(());
+            }
+            Request::Unload(provider) => {
+                document_providers.retain(|known| *known != provider);
+                if let Ok(mut documents) = documents.write() {
+                    documents.remove(&provider.location());
+                }
+                if let Ok(mut failures) = failures.write() {
+                    failures.remove(&provider.location());
+                }
+                generation.fetch_add(1, Ordering::Relaxed);
+            }
+            Request::ScanWorkspace {
+                roots,
+                needle,
+                reply,
+            } => {
+                let extensions = config.extensions.clone();
+                let files = tokio::task::spawn_blocking(move || {
+                    scan_workspace(&roots, &extensions, &needle)
+                })
+                .await
+                .unwrap_or_default();
+                let _ = reply.send(files);
+            }
+        }
+    }
+}
+
+/// Replace `index` with a new index of the names of `provider`, keeping it if that failed.
+async fn refresh(
+    provider: Provider,
+    byte_exact: bool,
+    index: Arc<RwLock<SuggestionIndex>>,
+    generation: Arc<AtomicU64>,
+    failures: Failures,
+) {
+    let read = provider.clone();
+    let refreshed = tokio::task::spawn_blocking(move || {
+        let (progress, _) = mpsc::unbounded_channel();
+        // Expiring names aren't cached
+        build_index(&read, byte_exact, None, progress)
+    })
+    .await
+    .unwrap_or_else(|err| Err(err.to_string()));
+    record_read(&failures, &provider, refreshed.as_ref().err());
+    if let (Ok(refreshed), Ok(mut current)) = (refreshed, index.write()) {
+        *current = refreshed;
+        generation.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Remember the error of reading the names of `provider`, or forget it after a successful read.
+fn record_read(failures: &Failures, provider: &Provider, err: Option<&String>) {
+    if let Ok(mut failures) = failures.write() {
+        match err {
+            Some(err) => {
+                failures.insert(provider.location(), (err.clone(), Instant::now()));
+            }
+            None => {
+                failures.remove(&provider.location());
+            }
+        }
+    }
+}
+
+/// Whether the names of `provider` failed to be read long enough ago to try again, which
+/// restarts the expiry so only one retry runs at a time.
+fn retry_due(failures: &Failures, provider: &Provider) -> bool {
+    let mut failures = match failures.write() {
+        Ok(failures) => failures,
+        Err(_) => return false,
+    };
+    match failures.get_mut(&provider.location()) {
+        Some((_, tried)) if tried.elapsed() >= FAILURE_TTL => {
+            *tried = Instant::now();
+            true
+        }
+        _ => false,
+    }
+}
+
+pub fn get_files(root: &str) -> Vec<String> {
+    read_names(root).unwrap_or_default()
+}
+
+/// Names of the entries of the directory `root`, none if it doesn't exist, which is reported
+/// on its own.
+pub fn read_names(root: &str) -> Result<Vec<String>, String> {
+    match fs::read_dir(root) {
+        Ok(paths) => Ok(paths
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .map(|d| d.file_name().to_string_lossy().to_string())
+            .collect::<Vec<String>>()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+        Err(err) => Err(format!("{}: {}", root, err)),
+    }
+}
+
+/// All entries below `root` as '/' separated paths relative to it, like "assets/icons".
+pub fn get_files_recursive(root: &str) -> Vec<String> {
+    WalkDir::new(root)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| !is_ignored_dir(e))
+        .filter_map(|e| e.ok())
+        .filter_map(|e| relative_name(Path::new(root), e.path()))
+        .collect()
+}
+
+/// The entries of `root` which are regular files rather than folders, nested ones as well if
+/// `recursive`.
+pub fn get_regular_files(root: &str, recursive: bool) -> Vec<String> {
+    WalkDir::new(root)
+        .min_depth(1)
+        .max_depth(if recursive { usize::MAX } else { 1 })
+        .into_iter()
+        .filter_entry(|e| !is_ignored_dir(e))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| relative_name(Path::new(root), e.path()))
+        .collect()
+}
+
+/// `path` relative to `root` with '/' separators, `None` if it isn't below `root`.
+fn relative_name(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let segments: Vec<_> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    (!segments.is_empty()).then(|| segments.join("/"))
+}
+
+/// Index of the names of `provider`, read from the cache in `cache_dir` if they are unchanged.
+fn build_index(
+    provider: &Provider,
+    byte_exact: bool,
+    cache_dir: Option<&Path>,
+    progress: mpsc::UnboundedSender<IndexProgress>,
+) -> Result<SuggestionIndex, String> {
+    let names = match cache_dir {
+        Some(dir) => cache::names(dir, provider)?,
+        None => provider.names()?,
+    };
+    let total = names.len().max(1);
+    let step = (total / 100).max(1);
+
+    let names = names.into_iter().enumerate().map(|(i, name)| {
+        if i % step == 0 {
+            let _ = progress.send(IndexProgress {
+                percentage: (i * 100 / total) as u32,
+                path: provider.describe(&name),
+            });
+        }
+        name
+    });
+    let mut index = SuggestionIndex::build(names, byte_exact);
+    index.mark_files(provider.files());
+    Ok(index)
+}
+
+pub fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.iter().any(|e| e == ext))
+}
+
+/// Apply watched file events to the index of `provider`.
+fn apply_changes(
+    provider: &Provider,
+    byte_exact: bool,
+    index: &RwLock<SuggestionIndex>,
+    changes: &[FileEvent],
+) {
+    match provider {
+        Provider::Directory { dir, recursive } => {
+            apply_directory_changes(dir, *recursive, index, changes)
+        }
+        // Changes only expire the names, which are refreshed in the background
+        Provider::Command { .. } => {}
+        Provider::List(_) => {}
+        provider => {
+            let source = provider
+                .source_file()
+                .and_then(|path| fs::canonicalize(path).ok());
+            let changed = changes.iter().any(|change| {
+                paths::from_uri(&change.uri).and_then(|path| fs::canonicalize(path).ok()) == source
+            });
+            if changed {
+                if let (Ok(names), Ok(mut index)) = (provider.names(), index.write()) {
+                    *index = SuggestionIndex::build(names, byte_exact);
+                }
+            }
+        }
+    }
+}
+
+/// Apply watched file events for entries of the suggestions directory to the index, only for
+/// direct children unless `recursive`.
+fn apply_directory_changes(
+    dir: &str,
+    recursive: bool,
+    index: &RwLock<SuggestionIndex>,
+    changes: &[FileEvent],
+) {
+    let suggestionsdir = match fs::canonicalize(dir) {
+        Ok(dir) => dir,
+        Err(_) => return,
+    };
+
+    let mut index = match index.write() {
+        Ok(index) => index,
+        Err(_) => return,
+    };
+
+    for change in changes {
+        let path = match paths::from_uri(&change.uri) {
+            Some(path) => path,
+            None => continue,
+        };
+        let name = match path
+            .parent()
+            .and_then(|parent| fs::canonicalize(parent).ok())
+            .zip(path.file_name())
+            .and_then(|(parent, name)| relative_name(&suggestionsdir, &parent.join(name)))
+        {
+            Some(name) if recursive || !name.contains('/') => name,
+            _ => continue,
+        };
+
+        if change.typ == FileChangeType::CREATED {
+            index.insert(&name);
+            if path.is_file() {
+                index.mark_files([name]);
+            }
+        } else if change.typ == FileChangeType::DELETED {
+            let nested_prefix = format!("{}/", name);
+            let nested: Vec<String> = index.prefix(&nested_prefix).map(String::from).collect();
+            for nested in nested {
+                index.remove(&nested);
+            }
+            index.remove(&name);
+        }
+    }
+}
+
+fn is_ignored_dir(entry: &DirEntry) -> bool {
+    let name = entry.file_name().to_string_lossy();
+    entry.depth() > 0
+        && entry.file_type().is_dir()
+        && (name.starts_with('.') || IGNORED_DIRS.contains(&&*name))
+}
+
+/// Collect all files below `roots` with one of `extensions` whose content contains `needle`.
+fn scan_workspace(
+    roots: &[PathBuf],
+    extensions: &[String],
+    needle: &str,
+) -> Vec<(PathBuf, String)> {
+    roots
+        .iter()
+        .flat_map(|root| {
+            WalkDir::new(root)
+                .into_iter()
+                .filter_entry(|e| !is_ignored_dir(e))
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && has_extension(e.path(), extensions))
+        .filter_map(|e| {
+            let content = fs::read_to_string(e.path()).ok()?;
+            content.contains(needle).then(|| (e.into_path(), content))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_files_recursive() {
+        let root = std::env::temp_dir().join(format!("tsm-recursive-{}", std::process::id()));
+        fs::create_dir_all(root.join("assets").join("icons")).unwrap();
+        fs::create_dir_all(root.join("node_modules").join("lib")).unwrap();
+        fs::write(root.join("assets").join("readme.md"), "").unwrap();
+
+        let mut files = get_files_recursive(&root.to_string_lossy());
+        let regular_files = get_regular_files(&root.to_string_lossy(), true);
+        let direct_files = get_regular_files(&root.join("assets").to_string_lossy(), false);
+        fs::remove_dir_all(&root).unwrap();
+        files.sort();
+
+        assert_eq!(vec!["assets", "assets/icons", "assets/readme.md"], files);
+        assert_eq!(vec!["assets/readme.md"], regular_files);
+        assert_eq!(vec!["readme.md"], direct_files);
+    }
+
+    #[test]
+    fn test_read_names() {
+        let root = std::env::temp_dir().join(format!("tsm-read-names-{}", std::process::id()));
+        fs::create_dir_all(root.join("assets")).unwrap();
+        fs::write(root.join("file"), "").unwrap();
+
+        let names = read_names(&root.to_string_lossy());
+        let missing = read_names(&root.join("missing").to_string_lossy());
+        let failed = read_names(&root.join("file").to_string_lossy());
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(2, names.unwrap().len());
+        assert_eq!(Ok(vec![]), missing);
+        assert!(failed.is_err());
+    }
+
+    #[test]
+    fn test_retry_due() {
+        let failures = Failures::default();
+        let provider = Provider::List(vec![]);
+        assert!(!retry_due(&failures, &provider));
+
+        record_read(&failures, &provider, Some(&"denied".to_string()));
+        assert!(!retry_due(&failures, &provider));
+        if let Some((_, tried)) = failures.write().unwrap().get_mut(&provider.location()) {
+            *tried -= FAILURE_TTL;
+        }
+        assert!(retry_due(&failures, &provider));
+        assert!(!retry_due(&failures, &provider));
+
+        record_read(&failures, &provider, None);
+        assert!(failures.read().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_scan_workspace() {
+        let root = std::env::temp_dir().join(format!("tsm-indexer-{}", std::process::id()));
+        fs::create_dir_all(root.join("node_modules")).unwrap();
+        fs::write(root.join("a.ts"), "const folders = [\"a\"];").unwrap();
+        fs::write(root.join("b.ts"), "const other = [\"b\"];").unwrap();
+        fs::write(root.join("c.md"), "folders").unwrap();
+        fs::write(root.join("node_modules").join("d.ts"), "folders").unwrap();
+
+        let files = scan_workspace(std::slice::from_ref(&root), &["ts".to_string()], "folders");
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(1, files.len());
+        assert_eq!(root.join("a.ts"), files[0].0);
+    }
+
+    #[tokio::test]
+    async fn test_load_unload() {
+        let root = std::env::temp_dir().join(format!("tsm-indexer-load-{}", std::process::id()));
+        fs::create_dir_all(root.join("assets")).unwrap();
+        fs::write(root.join("file"), "").unwrap();
+        let directory = |dir: &Path| Provider::Directory {
+            dir: dir.to_string_lossy().to_string(),
+            recursive: false,
+        };
+        let indexer = Indexer::spawn(IndexerConfig {
+            provider: Provider::List(vec![]),
+            byte_exact: false,
+            extensions: vec![],
+            ttl: Duration::from_secs(300),
+            cache_dir: None,
+        });
+
+        // Failed reads aren't cached as empty indexes
+        let failing = directory(&root.join("file"));
+        indexer.load(failing.clone()).await;
+        assert!(indexer.document_index(&failing).is_none());
+        assert!(indexer.failure(&failing).is_some());
+
+        let provider = directory(&root);
+        indexer.load(provider.clone()).await;
+        assert!(indexer.document_index(&provider).is_some());
+        indexer.unload(provider.clone());
+        indexer.files_changed(vec![]).await;
+        assert!(indexer.document_index(&provider).is_none());
+
+        indexer.stop().await;
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,220 @@
+use crate::cli::{AstArgs, CliArgs, QueryArgs};
+use crate::config::{Config, Setting, Source};
+use crate::grammar;
+use crate::parser::{Capture, LspParser};
+use clap::ArgMatches;
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+/// Format a capture like `2:14-2:21 @item "dir_a"`, with one-based positions.
+pub fn format_capture(capture: &Capture) -> String {
+    format!(
+        "{}:{}-{}:{} @{} {:?}",
+        capture.range.start_point.row + 1,
+        capture.range.start_point.column + 1,
+        capture.range.end_point.row + 1,
+        capture.range.end_point.column + 1,
+        capture.name,
+        capture.text
+    )
+}
+
+/// Format a setting like `varname = "folders" # command line`.
+pub fn format_setting(setting: &Setting) -> String {
+    format!(
+        "{} = {} # {}",
+        setting.name,
+        setting.value,
+        setting.source.name()
+    )
+}
+
+/// Parse one-based lines like `3:10` or `5` into zero-based rows.
+pub fn parse_lines(lines: &str) -> Result<RangeInclusive<usize>, String> {
+    let (start, end) = lines.split_once(':').unwrap_or((lines, lines));
+    let parse = |line: &str| match line.trim().parse::<usize>() {
+        Ok(line) if line > 0 => Ok(line - 1),
+        _ => Err(format!("invalid line '{}'", line)),
+    };
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start > end {
+        return Err(format!("line {} is after line {}", start + 1, end + 1));
+    }
+    Ok(start..=end)
+}
+
+/// Read `path` and the name of the arrays it declares, honoring its inline configuration and
+/// the runtime-loaded grammars of the configuration file.
+fn read_source(args: &CliArgs, path: &Path) -> Result<(String, String), String> {
+    let config = Config::load(Path::new("."))
+        .map_err(|err| format!("Invalid configuration file {}", err))?;
+    grammar::register_config(&config, Path::new("."))?;
+
+    let source_code = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read '{}': {}", path.display(), err))?;
+    let varname = LspParser::parse_inline_config(&source_code)
+        .varname
+        .unwrap_or_else(|| args.varname_for(path));
+    Ok((source_code, varname))
+}
+
+/// Print the captures of the query given to the `query` subcommand, returns the process exit
+/// code.
+pub fn query(args: &CliArgs, query: &QueryArgs) -> i32 {
+    let (source_code, varname) = match read_source(args, &query.file) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("{}", err);
+            return 2;
+        }
+    };
+    let (language, declaration) = LspParser::declaration(&varname);
+    let query_source = match &query.query {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(query_source) => query_source,
+            Err(err) => {
+                eprintln!("Failed to read '{}': {}", path.display(), err);
+                return 2;
+            }
+        },
+        None => declaration,
+    };
+
+    match LspParser::captures(&language, &source_code, &query_source) {
+        Ok(captures) => {
+            for capture in &captures {
+                println!("{}", format_capture(capture));
+            }
+            0
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    }
+}
+
+/// Print the syntax tree of the file given to the `ast` subcommand, returns the process exit
+/// code.
+pub fn ast(args: &CliArgs, ast: &AstArgs) -> i32 {
+    let (source_code, varname) = match read_source(args, &ast.file) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("{}", err);
+            return 2;
+        }
+    };
+    let (language, _) = LspParser::declaration(&varname);
+    let rows = ast.lines.clone().unwrap_or(0..=usize::MAX);
+
+    match LspParser::syntax_tree(&language, &source_code, rows) {
+        Ok(tree) => {
+            println!("{}", tree);
+            0
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    }
+}
+
+/// Print the effective settings of the command line in `matches` and of the configuration,
+/// resolved from the configuration file and the arguments, for the `config` subcommand.
+/// Returns the process exit code.
+pub fn config(args: &CliArgs, matches: &ArgMatches) -> i32 {
+    let resolved = Config::file_layer(Path::new(".")).and_then(|file| {
+        let layers = BTreeMap::from([
+            (Source::File, file),
+            (Source::CommandLine, args.config_layer()),
+        ]);
+        Config::resolve(&layers)
+    });
+    let (config, sources) = match resolved {
+        Ok(resolved) => resolved,
+        Err(err) => {
+            eprintln!("Invalid configuration {}", err);
+            return 2;
+        }
+    };
+
+    println!("[command-line]");
+    for setting in CliArgs::settings(matches) {
+        println!("{}", format_setting(&setting));
+    }
+    println!();
+    println!("[configuration]");
+    for setting in config.settings(&sources) {
+        println!("{}", format_setting(&setting));
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_settings() {
+        let matches = CliArgs::command().get_matches_from(["tsm", "--varname", "dirs", "config"]);
+        let settings = CliArgs::settings(&matches);
+        let lines: Vec<String> = settings.iter().map(format_setting).collect();
+        assert!(lines.contains(&r#"varname = "dirs" # command line"#.to_string()));
+        assert!(lines.contains(&r#"suggestionsdir = "." # default"#.to_string()));
+        assert!(lines.contains(&r#"extensions = ["ts", "tsx"] # default"#.to_string()));
+        assert!(lines.contains(&"byte_exact = false # default".to_string()));
+        assert!(!settings
+            .iter()
+            .any(|s| s.name == "manifest" || s.name == "stdio"));
+    }
+
+    #[test]
+    fn test_environment_settings() {
+        // No other test reads the table, setting it doesn't race with them
+        std::env::set_var("TSM_LS_SQLITE_TABLE", "dirs");
+        let matches = CliArgs::command().get_matches_from(["tsm", "config"]);
+        let lines: Vec<String> = CliArgs::settings(&matches)
+            .iter()
+            .map(format_setting)
+            .collect();
+        assert!(lines.contains(&r#"sqlite_table = "dirs" # environment"#.to_string()));
+
+        let matches =
+            CliArgs::command().get_matches_from(["tsm", "--sqlite-table", "names", "config"]);
+        let lines: Vec<String> = CliArgs::settings(&matches)
+            .iter()
+            .map(format_setting)
+            .collect();
+        assert!(lines.contains(&r#"sqlite_table = "names" # command line"#.to_string()));
+        std::env::remove_var("TSM_LS_SQLITE_TABLE");
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        assert_eq!(Ok(2..=9), parse_lines("3:10"));
+        assert_eq!(Ok(4..=4), parse_lines("5"));
+        assert!(parse_lines("0").is_err());
+        assert!(parse_lines("10:3").is_err());
+        assert!(parse_lines("a:3").is_err());
+    }
+
+    #[test]
+    fn test_format_capture() {
+        let source_code = "const folders = [\n  \"dir_a\",\n];";
+        let (language, query) = LspParser::declaration("folders");
+        let captures = LspParser::captures(&language, source_code, &query).unwrap();
+
+        let lines: Vec<String> = captures.iter().map(format_capture).collect();
+        assert_eq!(
+            vec![
+                "1:7-1:14 @id \"folders\"",
+                "1:17-3:2 @array \"[\\n  \\\"dir_a\\\",\\n]\"",
+                "2:3-2:10 @item \"\\\"dir_a\\\"\"",
+            ],
+            lines
+        );
+    }
+}
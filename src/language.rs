@@ -0,0 +1,137 @@
+//! Language of opened documents.
+//!
+//! Clients don't always send a language id the server knows, plugins open documents with ids
+//! like `typescript.tsx` or none at all. The language is detected from the extension and the
+//! content of the document then.
+
+use std::path::Path;
+
+/// Language ids of the file extensions.
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("ts", "typescript"),
+    ("mts", "typescript"),
+    ("cts", "typescript"),
+    ("tsx", "typescriptreact"),
+    ("js", "javascript"),
+    ("mjs", "javascript"),
+    ("cjs", "javascript"),
+    ("jsx", "javascriptreact"),
+    ("json", "json"),
+];
+
+/// Interpreters of shebang lines with the language of their scripts.
+const INTERPRETERS: &[(&str, &str)] = &[
+    ("deno", "typescript"),
+    ("ts-node", "typescript"),
+    ("tsx", "typescript"),
+    ("bun", "typescript"),
+    ("node", "javascript"),
+];
+
+/// Language ids telling nothing about the language.
+const UNKNOWN: &[&str] = &["", "plaintext", "text", "unknown"];
+
+/// The language of the document at `path` opened with `language_id`, detected from its
+/// extension and `source` if the language id is unknown.
+pub fn detect(language_id: &str, path: &Path, source: &str) -> Option<String> {
+    normalize(language_id)
+        .or_else(|| by_extension(path))
+        .or_else(|| by_content(source))
+}
+
+/// The language id without variants, `typescript.tsx` is `typescriptreact` and `ts` is
+/// `typescript`, `None` for unknown ones.
+pub fn normalize(language_id: &str) -> Option<String> {
+    let language_id = language_id.trim().to_lowercase();
+    if UNKNOWN.contains(&language_id.as_str()) {
+        return None;
+    }
+    // Variants name the extension after the language
+    let (language, variant) = language_id
+        .split_once('.')
+        .unwrap_or((language_id.as_str(), language_id.as_str()));
+    let language = extension_language(variant)
+        .or_else(|| extension_language(language))
+        .unwrap_or(language);
+    Some(language.to_string())
+}
+
+fn extension_language(extension: &str) -> Option<&'static str> {
+    EXTENSIONS
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, language)| *language)
+}
+
+fn by_extension(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    extension_language(&extension).map(String::from)
+}
+
+fn by_content(source: &str) -> Option<String> {
+    if let Some(shebang) = source
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("#!"))
+    {
+        // `#!/usr/bin/env -S deno run` as well as `#!/usr/local/bin/node`
+        let interpreter = shebang
+            .split_whitespace()
+            .map(|word| word.rsplit('/').next().unwrap_or(word))
+            .find(|word| *word != "env" && !word.starts_with('-'))?;
+        return INTERPRETERS
+            .iter()
+            .find(|(name, _)| *name == interpreter)
+            .map(|(_, language)| language.to_string());
+    }
+    let trimmed = source.trim_start();
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(source).is_ok()
+    {
+        return Some("json".into());
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(Some("typescript".into()), normalize("typescript"));
+        assert_eq!(Some("typescriptreact".into()), normalize("typescript.tsx"));
+        assert_eq!(Some("typescript".into()), normalize("TS"));
+        assert_eq!(Some("javascriptreact".into()), normalize("javascript.jsx"));
+        assert_eq!(Some("python".into()), normalize("python"));
+        assert_eq!(None, normalize(""));
+        assert_eq!(None, normalize("plaintext"));
+    }
+
+    #[test]
+    fn test_detect() {
+        let path = Path::new("src/app.tsx");
+        assert_eq!(Some("vue".into()), detect("vue", path, ""));
+        assert_eq!(Some("typescriptreact".into()), detect("", path, ""));
+
+        let untitled = Path::new("Untitled-1");
+        assert_eq!(
+            Some("typescript".into()),
+            detect(
+                "",
+                untitled,
+                "#!/usr/bin/env -S deno run\nconst folders = [];"
+            )
+        );
+        assert_eq!(
+            Some("javascript".into()),
+            detect("", untitled, "#!/usr/local/bin/node\n")
+        );
+        assert_eq!(None, detect("", untitled, "#!/bin/sh\n"));
+        assert_eq!(
+            Some("json".into()),
+            detect("", untitled, r#"{ "workspaces": [] }"#)
+        );
+        assert_eq!(None, detect("", untitled, "const folders = [];"));
+    }
+}
@@ -0,0 +1,94 @@
+//! Validation of string arrays referencing folders, usable as a library or through the
+//! `tsm-language-server` binary.
+//!
+//! The language server, the indexer and the command line need the default `server` feature.
+//! Without it only the core analysis is built, which also compiles to WebAssembly.
+
+pub mod analysis;
+pub use analysis::{analyze, AnalysisConfig, AnalysisResult};
+
+#[cfg(feature = "server")]
+pub mod backend;
+#[cfg(feature = "server")]
+pub use backend::Backend;
+
+#[cfg(feature = "server")]
+mod cache;
+
+#[cfg(feature = "server")]
+mod capabilities;
+
+#[cfg(feature = "server")]
+pub mod check;
+
+#[cfg(feature = "server")]
+pub mod cli;
+#[cfg(feature = "server")]
+pub use cli::CliArgs;
+
+pub mod config;
+pub use config::Config;
+
+#[cfg(feature = "server")]
+mod commands;
+
+pub mod diagnostics;
+
+#[cfg(feature = "server")]
+pub mod documents;
+
+pub mod edits;
+
+pub mod grammar;
+
+#[cfg(feature = "server")]
+pub mod health;
+
+pub mod i18n;
+
+pub mod index;
+
+#[cfg(feature = "server")]
+pub mod indexer;
+
+#[cfg(feature = "server")]
+pub mod inspect;
+
+pub mod language;
+
+pub mod matcher;
+
+#[cfg(feature = "server")]
+pub mod metrics;
+
+#[cfg(feature = "server")]
+pub mod panic;
+
+pub mod parser;
+
+pub mod paths;
+
+#[cfg(feature = "server")]
+pub mod project;
+
+pub mod rules;
+
+#[cfg(feature = "server")]
+mod semantic;
+
+#[cfg(feature = "server")]
+mod process;
+
+#[cfg(feature = "server")]
+pub mod provider;
+#[cfg(feature = "server")]
+pub use provider::Provider;
+
+pub mod validator;
+pub use validator::Validator;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(all(feature = "server", any(test, feature = "test-harness")))]
+pub mod testing;
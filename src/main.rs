@@ -1,34 +1,48 @@
-use clap::{arg, command, Parser};
+use clap::{CommandFactory, Parser};
+use std::sync::atomic::Ordering;
 use tower_lsp::{LspService, Server};
-
-mod backend;
-use backend::Backend;
-
-mod parser;
-
-/// tsm-language-server
-#[derive(Parser, Debug)]
-#[command(version,about,long_about=None)]
-pub struct CliArgs {
-    /// Directory to provide as suggestions
-    #[arg(short, long, default_value = ".")]
-    suggestionsdir: String,
-
-    /// Variable name to trigger completion
-    #[arg(short, long, default_value = "folders")]
-    varname: String,
-
-    #[arg(short, long)]
-    stdio: bool, // Needed for LSP start
-}
+use tsm_language_server::cli::Command;
+use tsm_language_server::metrics::{self, Instrumented};
+use tsm_language_server::panic::CatchPanic;
+use tsm_language_server::{check, inspect, Backend, CliArgs};
 
 #[tokio::main]
 async fn main() {
     let args = CliArgs::parse();
 
+    match &args.command {
+        Some(Command::Check(check_args)) => std::process::exit(check::run(&args, check_args)),
+        Some(Command::Query(query_args)) => std::process::exit(inspect::query(&args, query_args)),
+        Some(Command::Ast(ast_args)) => std::process::exit(inspect::ast(&args, ast_args)),
+        // The matches tell where the values come from, which the parsed arguments don't
+        Some(Command::Config) => {
+            std::process::exit(inspect::config(&args, &CliArgs::command().get_matches()))
+        }
+        None => {}
+    }
+
+    if let Some(endpoint) = &args.otlp_endpoint {
+        if let Err(err) = metrics::start_export(endpoint) {
+            eprintln!("Invalid --otlp-endpoint {}", err);
+            std::process::exit(2);
+        }
+    }
+
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, socket) = LspService::new(|client| Backend::new(client, args));
-    Server::new(stdin, stdout, socket).serve(service).await;
+    let (service, socket) = LspService::build(|client| Backend::new(client, args))
+        .custom_method("tsm/status", Backend::status)
+        .custom_method("tsm/previewFolder", Backend::preview_folder)
+        .custom_method("tsm/findAllInvalid", Backend::find_all_invalid)
+        .finish();
+    let shutdown_requested = service.inner().shutdown_requested();
+    let client = service.inner().client();
+    Server::new(stdin, stdout, socket)
+        .serve(Instrumented::new(CatchPanic::new(service, client)))
+        .await;
+
+    // The server stops on the exit notification or when the client closed the connection
+    let clean = shutdown_requested.load(Ordering::Relaxed);
+    std::process::exit(if clean { 0 } else { 1 });
 }
@@ -0,0 +1,294 @@
+use clap::ValueEnum;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+
+/// Algorithm used to rank suggestions for invalid entries.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Algorithm {
+    /// Skim fuzzy matching, scores are unbounded
+    #[default]
+    Skim,
+    /// Normalized Levenshtein similarity between 0 and 1
+    Levenshtein,
+    /// Jaro-Winkler similarity between 0 and 1
+    JaroWinkler,
+}
+
+impl Algorithm {
+    /// `score` of this algorithm for titles, similarities as percentage.
+    pub fn format_score(&self, score: f64) -> String {
+        match self {
+            Algorithm::Skim => format!("{:.0}", score),
+            Algorithm::Levenshtein | Algorithm::JaroWinkler => format!("{:.0}%", score * 100.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Matcher {
+    pub algorithm: Algorithm,
+    pub case_sensitive: bool,
+    /// Candidates scoring below the cutoff are not suggested.
+    pub cutoff: Option<f64>,
+}
+
+impl Matcher {
+    /// Score `candidate` against `input`, `None` if it doesn't match at all.
+    ///
+    /// Nested paths like "assets/icons" score as well as their best matching part, the whole
+    /// path or its final segment.
+    pub fn score(&self, candidate: &str, input: &str) -> Option<f64> {
+        let segment = candidate
+            .rsplit('/')
+            .next()
+            .filter(|segment| segment.len() < candidate.len());
+
+        [Some(candidate), segment]
+            .into_iter()
+            .flatten()
+            .filter_map(|candidate| self.raw_score(candidate, input))
+            .max_by(f64::total_cmp)
+            .filter(|score| self.cutoff.is_none_or(|cutoff| *score >= cutoff))
+    }
+
+    fn raw_score(&self, candidate: &str, input: &str) -> Option<f64> {
+        match self.algorithm {
+            Algorithm::Skim => {
+                let matcher = if self.case_sensitive {
+                    SkimMatcherV2::default().respect_case()
+                } else {
+                    SkimMatcherV2::default().ignore_case()
+                };
+                matcher
+                    .fuzzy_match(candidate, input)
+                    .map(|score| score as f64)
+            }
+            Algorithm::Levenshtein | Algorithm::JaroWinkler => {
+                let (candidate, input) = if self.case_sensitive {
+                    (candidate.to_string(), input.to_string())
+                } else {
+                    (candidate.to_lowercase(), input.to_lowercase())
+                };
+                let similarity = if self.algorithm == Algorithm::Levenshtein {
+                    levenshtein_similarity(&candidate, &input)
+                } else {
+                    jaro_winkler(&candidate, &input)
+                };
+                Some(similarity).filter(|s| *s > 0.0)
+            }
+        }
+    }
+
+    /// The `top_n` best matching candidates with their scores, best first.
+    pub fn scored_matches(
+        &self,
+        user_input: &str,
+        possible_matches: &[&str],
+        top_n: usize,
+    ) -> Vec<(String, f64)> {
+        let mut matches_with_scores: Vec<(&str, f64)> = possible_matches
+            .iter()
+            .filter_map(|&s| self.score(s, user_input).map(|score| (s, score)))
+            .collect();
+
+        matches_with_scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        matches_with_scores
+            .into_iter()
+            .take(top_n)
+            .map(|(s, score)| (s.to_string(), score))
+            .collect()
+    }
+
+    pub fn best_matches(
+        &self,
+        user_input: &str,
+        possible_matches: &[&str],
+        top_n: usize,
+    ) -> Vec<String> {
+        self.scored_matches(user_input, possible_matches, top_n)
+            .into_iter()
+            .map(|(s, _)| s)
+            .collect()
+    }
+}
+
+/// Number of leading directories `candidate` shares with the closest of `siblings`, e.g. 1 for
+/// `assets/icons` next to `assets/img`. Completions sharing more likely are the sibling the user
+/// is adding.
+pub fn sibling_proximity(candidate: &str, siblings: &[&str]) -> usize {
+    let directories = |path: &str| -> Vec<String> {
+        let mut segments: Vec<String> = path.split('/').map(String::from).collect();
+        segments.pop();
+        segments
+    };
+    let candidate = directories(candidate);
+    siblings
+        .iter()
+        .map(|sibling| {
+            directories(sibling)
+                .iter()
+                .zip(&candidate)
+                .take_while(|(a, b)| a == b)
+                .count()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Levenshtein distance normalized to a similarity between 0 (different) and 1 (equal).
+fn levenshtein_similarity(a: &str, b: &str) -> f64 {
+    let max_len = a.chars().count().max(b.chars().count());
+    if max_len == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / max_len as f64
+}
+
+fn jaro(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let window = (a.len().max(b.len()) / 2).saturating_sub(1);
+    let mut a_matched = vec![false; a.len()];
+    let mut b_matched = vec![false; b.len()];
+    let mut matches = 0;
+
+    for (i, ca) in a.iter().enumerate() {
+        let start = i.saturating_sub(window);
+        let end = (i + window + 1).min(b.len());
+        for j in start..end {
+            if !b_matched[j] && b[j] == *ca {
+                a_matched[i] = true;
+                b_matched[j] = true;
+                matches += 1;
+                break;
+            }
+        }
+    }
+
+    if matches == 0 {
+        return 0.0;
+    }
+
+    let a_chars = a
+        .iter()
+        .zip(&a_matched)
+        .filter(|(_, m)| **m)
+        .map(|(c, _)| c);
+    let b_chars = b
+        .iter()
+        .zip(&b_matched)
+        .filter(|(_, m)| **m)
+        .map(|(c, _)| c);
+    let transpositions = a_chars.zip(b_chars).filter(|(ca, cb)| ca != cb).count() / 2;
+
+    let matches = matches as f64;
+    (matches / a.len() as f64
+        + matches / b.len() as f64
+        + (matches - transpositions as f64) / matches)
+        / 3.0
+}
+
+fn jaro_winkler(a: &str, b: &str) -> f64 {
+    let jaro = jaro(a, b);
+    let prefix = a
+        .chars()
+        .zip(b.chars())
+        .take(4)
+        .take_while(|(ca, cb)| ca == cb)
+        .count();
+
+    jaro + prefix as f64 * 0.1 * (1.0 - jaro)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(0, levenshtein("assets", "assets"));
+        assert_eq!(1, levenshtein("assets", "asset"));
+        assert_eq!(3, levenshtein("kitten", "sitting"));
+        assert_eq!(1.0, levenshtein_similarity("", ""));
+    }
+
+    #[test]
+    fn test_jaro_winkler() {
+        assert_eq!(1.0, jaro_winkler("assets", "assets"));
+        assert_eq!(0.0, jaro_winkler("abc", "xyz"));
+        assert!((jaro_winkler("martha", "marhta") - 0.961).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_cutoff_and_case() {
+        let matcher = Matcher {
+            algorithm: Algorithm::Levenshtein,
+            case_sensitive: false,
+            cutoff: Some(0.5),
+        };
+        let candidates = ["Assets", "fonts", "images"];
+
+        assert_eq!(
+            vec!["Assets".to_string()],
+            matcher.best_matches("assts", &candidates, 5)
+        );
+    }
+
+    #[test]
+    fn test_format_score() {
+        assert_eq!("87", Algorithm::Skim.format_score(87.0));
+        assert_eq!("92%", Algorithm::Levenshtein.format_score(0.916));
+        assert_eq!("100%", Algorithm::JaroWinkler.format_score(1.0));
+    }
+
+    #[test]
+    fn test_sibling_proximity() {
+        let siblings = ["assets/img", "assets/fonts", "docs"];
+
+        assert_eq!(1, sibling_proximity("assets/icons", &siblings));
+        assert_eq!(0, sibling_proximity("fonts", &siblings));
+        assert_eq!(0, sibling_proximity("media/icons", &siblings));
+        assert_eq!(2, sibling_proximity("a/b/c", &["a/b/d", "a/e"]));
+        assert_eq!(0, sibling_proximity("assets/icons", &[]));
+    }
+
+    #[test]
+    fn test_nested_path_segment() {
+        let matcher = Matcher {
+            algorithm: Algorithm::Levenshtein,
+            case_sensitive: false,
+            cutoff: Some(0.8),
+        };
+        let candidates = ["assets", "assets/icons", "fonts"];
+
+        assert_eq!(
+            vec!["assets/icons".to_string()],
+            matcher.best_matches("icons", &candidates, 5)
+        );
+    }
+}
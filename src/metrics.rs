@@ -0,0 +1,347 @@
+//! Server metrics exported to an OpenTelemetry collector with `--otlp-endpoint`.
+//!
+//! Request counts and latencies, document validation durations and cache hits are aggregated in
+//! memory and sent as cumulative OTLP metrics in the JSON encoding over HTTP once a minute.
+//! Nothing is recorded unless the export was started.
+
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tower_lsp::jsonrpc::{Request, Response};
+use tower_service::Service;
+
+/// Interval between two exports.
+const EXPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Upper bounds in milliseconds of the buckets of the duration histograms.
+const BOUNDS: [f64; 8] = [1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 500.0, 1000.0];
+
+/// `AGGREGATION_TEMPORALITY_CUMULATIVE`, values since the start of the server.
+const CUMULATIVE: u8 = 2;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static METRICS: Mutex<Metrics> = Mutex::new(Metrics::new());
+
+/// Durations in milliseconds, bucketed by [`BOUNDS`].
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Histogram {
+    count: u64,
+    sum: f64,
+    buckets: [u64; BOUNDS.len() + 1],
+}
+
+impl Histogram {
+    fn record(&mut self, duration: Duration) {
+        let millis = duration.as_secs_f64() * 1000.0;
+        let bucket = BOUNDS
+            .iter()
+            .position(|bound| millis <= *bound)
+            .unwrap_or(BOUNDS.len());
+        self.count += 1;
+        self.sum += millis;
+        self.buckets[bucket] += 1;
+    }
+}
+
+#[derive(Debug)]
+struct Metrics {
+    /// Request and notification durations keyed by their method
+    requests: BTreeMap<String, Histogram>,
+    parses: Histogram,
+    /// Hits and misses keyed by the name of the cache
+    caches: BTreeMap<&'static str, (u64, u64)>,
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            requests: BTreeMap::new(),
+            parses: Histogram {
+                count: 0,
+                sum: 0.0,
+                buckets: [0; BOUNDS.len() + 1],
+            },
+            caches: BTreeMap::new(),
+        }
+    }
+}
+
+fn record(update: impl FnOnce(&mut Metrics)) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Ok(mut metrics) = METRICS.lock() {
+        update(&mut metrics);
+    }
+}
+
+/// Record that the message `method` was handled in `duration`.
+pub fn record_request(method: &str, duration: Duration) {
+    record(|metrics| {
+        metrics
+            .requests
+            .entry(method.to_string())
+            .or_default()
+            .record(duration)
+    });
+}
+
+/// Record that a document was parsed and validated in `duration`.
+pub fn record_parse(duration: Duration) {
+    record(|metrics| metrics.parses.record(duration));
+}
+
+/// Record a lookup in the cache called `cache`, `hit` if it had the value.
+pub fn record_cache(cache: &'static str, hit: bool) {
+    record(|metrics| {
+        let (hits, misses) = metrics.caches.entry(cache).or_default();
+        if hit {
+            *hits += 1;
+        } else {
+            *misses += 1;
+        }
+    });
+}
+
+/// Wraps the language service to record the method and duration of every message.
+pub struct Instrumented<S> {
+    inner: S,
+}
+
+impl<S> Instrumented<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S> Service<Request> for Instrumented<S>
+where
+    S: Service<Request, Response = Option<Response>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let method = request.method().to_string();
+        let start = Instant::now();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await;
+            record_request(&method, start.elapsed());
+            response
+        })
+    }
+}
+
+/// Address, host header and path of an `http://host:port/path` endpoint, the path defaulting
+/// to the OTLP metrics path.
+fn parse_endpoint(endpoint: &str) -> Result<(String, String, String), String> {
+    let rest = endpoint
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("{}: only http:// endpoints are supported", endpoint))?;
+    let (host, path) = match rest.find('/') {
+        Some(i) if i + 1 < rest.len() => (&rest[..i], rest[i..].to_string()),
+        Some(i) => (&rest[..i], "/v1/metrics".to_string()),
+        None => (rest, "/v1/metrics".to_string()),
+    };
+    if host.is_empty() {
+        return Err(format!("{}: missing host", endpoint));
+    }
+    let address = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:80", host)
+    };
+    Ok((address, host.to_string(), path))
+}
+
+/// Start exporting the metrics to `endpoint` in the background, fails if it isn't an `http://`
+/// URL.
+pub fn start_export(endpoint: &str) -> Result<(), String> {
+    let target = parse_endpoint(endpoint)?;
+    ENABLED.store(true, Ordering::Relaxed);
+    let start = unix_nanos();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(EXPORT_INTERVAL);
+        // The first tick completes immediately, with nothing recorded yet
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let exported = match METRICS.lock() {
+                Ok(metrics) => payload(&metrics, &start, &unix_nanos()),
+                Err(_) => break,
+            };
+            let target = target.clone();
+            // A collector which is down loses this export, the next one has all values again
+            let _ = tokio::task::spawn_blocking(move || post(&target, &exported)).await;
+        }
+    });
+    Ok(())
+}
+
+fn unix_nanos() -> String {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos())
+        .to_string()
+}
+
+fn post((address, host, path): &(String, String, String), payload: &Value) -> std::io::Result<()> {
+    let body = payload.to_string();
+    let mut stream = TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path,
+        host,
+        body.len(),
+        body
+    )?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(())
+}
+
+/// The OTLP `ExportMetricsServiceRequest` of `metrics`, cumulative since `start`.
+fn payload(metrics: &Metrics, start: &str, now: &str) -> Value {
+    let histogram = |histogram: &Histogram, attributes: Value| {
+        json!({
+            "attributes": attributes,
+            "startTimeUnixNano": start,
+            "timeUnixNano": now,
+            "count": histogram.count.to_string(),
+            "sum": histogram.sum,
+            "bucketCounts": histogram.buckets.iter().map(u64::to_string).collect::<Vec<_>>(),
+            "explicitBounds": BOUNDS,
+        })
+    };
+    let attribute =
+        |key: &str, value: &str| json!([{ "key": key, "value": { "stringValue": value } }]);
+    let counter = |value: u64, attributes: Value| {
+        json!({
+            "attributes": attributes,
+            "startTimeUnixNano": start,
+            "timeUnixNano": now,
+            "asInt": value.to_string(),
+        })
+    };
+    let requests: Vec<Value> = metrics
+        .requests
+        .iter()
+        .map(|(method, durations)| histogram(durations, attribute("method", method)))
+        .collect();
+    let hits: Vec<Value> = metrics
+        .caches
+        .iter()
+        .map(|(cache, (hits, _))| counter(*hits, attribute("cache", cache)))
+        .collect();
+    let misses: Vec<Value> = metrics
+        .caches
+        .iter()
+        .map(|(cache, (_, misses))| counter(*misses, attribute("cache", cache)))
+        .collect();
+
+    json!({
+        "resourceMetrics": [{
+            "resource": {
+                "attributes": attribute("service.name", env!("CARGO_PKG_NAME")),
+            },
+            "scopeMetrics": [{
+                "scope": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+                "metrics": [
+                    {
+                        "name": "tsm.request.duration",
+                        "description": "Duration of handling a request or notification, the count of the histogram counts them",
+                        "unit": "ms",
+                        "histogram": { "dataPoints": requests, "aggregationTemporality": CUMULATIVE },
+                    },
+                    {
+                        "name": "tsm.parse.duration",
+                        "description": "Duration of parsing and validating a document",
+                        "unit": "ms",
+                        "histogram": {
+                            "dataPoints": [histogram(&metrics.parses, json!([]))],
+                            "aggregationTemporality": CUMULATIVE,
+                        },
+                    },
+                    {
+                        "name": "tsm.cache.hits",
+                        "sum": { "dataPoints": hits, "aggregationTemporality": CUMULATIVE, "isMonotonic": true },
+                    },
+                    {
+                        "name": "tsm.cache.misses",
+                        "sum": { "dataPoints": misses, "aggregationTemporality": CUMULATIVE, "isMonotonic": true },
+                    },
+                ],
+            }],
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_endpoint() {
+        assert_eq!(
+            Ok((
+                "localhost:4318".to_string(),
+                "localhost:4318".to_string(),
+                "/v1/metrics".to_string()
+            )),
+            parse_endpoint("http://localhost:4318")
+        );
+        assert_eq!(
+            Ok((
+                "collector:80".to_string(),
+                "collector".to_string(),
+                "/otlp/v1/metrics".to_string()
+            )),
+            parse_endpoint("http://collector/otlp/v1/metrics")
+        );
+        assert!(parse_endpoint("https://collector:4318").is_err());
+        assert!(parse_endpoint("http:///v1/metrics").is_err());
+    }
+
+    #[test]
+    fn test_payload() {
+        let mut metrics = Metrics::new();
+        let mut durations = Histogram::default();
+        durations.record(Duration::from_millis(3));
+        durations.record(Duration::from_secs(2));
+        metrics
+            .requests
+            .insert("textDocument/completion".into(), durations);
+        metrics.caches.insert("diagnostics", (3, 1));
+
+        let exported = payload(&metrics, "1", "2");
+        let metrics = &exported["resourceMetrics"][0]["scopeMetrics"][0]["metrics"];
+        let point = &metrics[0]["histogram"]["dataPoints"][0];
+        assert_eq!(json!("2"), point["count"]);
+        assert_eq!(
+            json!(["0", "1", "0", "0", "0", "0", "0", "0", "1"]),
+            point["bucketCounts"]
+        );
+        assert_eq!(
+            json!("textDocument/completion"),
+            point["attributes"][0]["value"]["stringValue"]
+        );
+        assert_eq!(json!("3"), metrics[2]["sum"]["dataPoints"][0]["asInt"]);
+        assert_eq!(json!("1"), metrics[3]["sum"]["dataPoints"][0]["asInt"]);
+    }
+}
@@ -0,0 +1,127 @@
+//! Isolation of panics in message handlers.
+//!
+//! A panic while handling one message would otherwise unwind through the server loop and end the
+//! process, taking the tooling of every open document with it. Instead it's logged, shown to
+//! the user and answered with an internal error.
+
+use std::any::Any;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower_lsp::jsonrpc::{Error, Request, Response};
+use tower_lsp::lsp_types::MessageType;
+use tower_lsp::Client;
+use tower_service::Service;
+
+/// Wraps the language service to catch panics of the handlers.
+pub struct CatchPanic<S> {
+    inner: S,
+    client: Client,
+}
+
+impl<S> CatchPanic<S> {
+    pub fn new(inner: S, client: Client) -> Self {
+        Self { inner, client }
+    }
+}
+
+impl<S> Service<Request> for CatchPanic<S>
+where
+    S: Service<Request, Response = Option<Response>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let method = request.method().to_string();
+        let id = request.id().cloned();
+        let client = self.client.clone();
+        // Handlers may also panic before returning their future
+        let response = panic::catch_unwind(AssertUnwindSafe(|| self.inner.call(request)));
+        Box::pin(async move {
+            let caught = match response {
+                Ok(response) => {
+                    CatchUnwind {
+                        inner: Box::pin(response),
+                    }
+                    .await
+                }
+                Err(payload) => Err(payload),
+            };
+            let payload = match caught {
+                Ok(response) => return response,
+                Err(payload) => payload,
+            };
+
+            let message = format!("Handling {} failed: {}", method, panic_message(&*payload));
+            client.log_message(MessageType::ERROR, &message).await;
+            client.show_message(MessageType::ERROR, &message).await;
+            let mut error = Error::internal_error();
+            error.message = message.into();
+            // Notifications have no response to carry the error
+            Ok(id.map(|id| Response::from_error(id, error)))
+        })
+    }
+}
+
+/// Resolves to the output of `inner`, or to the payload of the panic polling it.
+struct CatchUnwind<F> {
+    inner: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = Result<F::Output, Box<dyn Any + Send>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = self.inner.as_mut();
+        match panic::catch_unwind(AssertUnwindSafe(|| inner.poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+/// The message of a panic, passed to `panic!` as a string literal or formatted.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_message() {
+        let literal = panic::catch_unwind(|| panic!("bad unwrap")).unwrap_err();
+        assert_eq!("bad unwrap", panic_message(&*literal));
+        let formatted = panic::catch_unwind(|| panic!("index {} out of range", 3)).unwrap_err();
+        assert_eq!("index 3 out of range", panic_message(&*formatted));
+    }
+
+    #[tokio::test]
+    async fn test_catch_unwind() {
+        let caught = CatchUnwind {
+            inner: Box::pin(async { panic!("in handler") }),
+        }
+        .await;
+        assert_eq!("in handler", panic_message(&*caught.unwrap_err()));
+        let completed = CatchUnwind {
+            inner: Box::pin(async { 1 }),
+        }
+        .await;
+        assert_eq!(1, completed.unwrap());
+    }
+}
@@ -1,66 +1,496 @@
-use tree_sitter::{Parser, Query, QueryCursor, Range};
+use crate::grammar::{self, Grammar};
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+use tree_sitter::{Language, Parser, Query, QueryCursor, Range, TreeCursor};
 
+/// A captured node with its text, borrowed from the source without quotes.
 #[derive(Debug)]
-pub struct PositionalText {
+pub struct PositionalText<'a> {
+    pub text: &'a str,
+    pub range: Range,
+}
+
+/// A node captured by a query, with the verbatim source text.
+#[derive(Debug)]
+pub struct Capture {
+    pub name: String,
     pub text: String,
     pub range: Range,
 }
 
+/// Settings overridden by `// tsm-<setting>: <value>` comments at the top of a file.
+#[derive(Debug, Default, PartialEq)]
+pub struct InlineConfig {
+    /// Suggestions directory, relative to the file
+    pub suggestionsdir: Option<String>,
+    pub varname: Option<String>,
+}
+
+/// Lines silenced by `tsm-disable-next-line` comments and `tsm-disable` ... `tsm-enable` blocks,
+/// and entries followed by a `tsm-ignore` comment.
+#[derive(Debug, Default, PartialEq)]
+pub struct Suppressions {
+    lines: HashSet<usize>,
+    /// Rows of `tsm-disable` and the following `tsm-enable` comments, `usize::MAX` without one
+    blocks: Vec<(usize, usize)>,
+    /// Start rows and columns of the ignored entries
+    entries: HashSet<(usize, usize)>,
+}
+
+impl Suppressions {
+    /// Whether diagnostics starting on `row` are suppressed.
+    pub fn is_suppressed(&self, row: usize) -> bool {
+        self.lines.contains(&row)
+            || self
+                .blocks
+                .iter()
+                .any(|(start, end)| (*start..*end).contains(&row))
+    }
+
+    /// Whether diagnostics of the entry starting at `row` and `column` are suppressed.
+    pub fn is_ignored(&self, row: usize, column: usize) -> bool {
+        self.entries.contains(&(row, column))
+    }
+}
+
 pub struct LspParser {}
 
 impl LspParser {
     /// Extract the text of tree-sitter captured node from source.
-    fn node_text(node: tree_sitter::Node, src: &str) -> String {
-        src[node.start_byte()..node.end_byte()]
-            .to_string()
-            .trim_matches('"')
-            .into()
+    fn node_text<'a>(node: tree_sitter::Node, src: &'a str) -> &'a str {
+        src[node.start_byte()..node.end_byte()].trim_matches(|c| c == '"' || c == '\'')
     }
 
-    pub(crate) fn node_string(node: tree_sitter::Node, src: &str) -> String {
-        Self::node_text(node, src)
+    /// Escape `value` for use inside a double quoted string of a tree-sitter query.
+    fn escape_query_string(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
     }
 
-    pub fn parse_code(source_code: &str, varname: &str) -> Vec<PositionalText> {
+    /// Run `query_source` against `source_code` and collect the nodes captured as `capture`.
+    ///
+    /// Sources which can't be parsed and invalid queries yield no captures.
+    fn run_query<'a>(
+        language: &Language,
+        source_code: &'a str,
+        query_source: &str,
+        capture: &str,
+    ) -> Vec<PositionalText<'a>> {
         let mut parser = Parser::new();
         parser
-            .set_language(&tree_sitter_typescript::language_typescript())
-            .expect("Error loading typescript grammar");
-        let tree = parser.parse(source_code, None).unwrap();
+            .set_language(language)
+            .expect("Error loading grammar");
+        let tree = match parser.parse(source_code, None) {
+            Some(tree) => tree,
+            None => return vec![],
+        };
 
-        let user_query = format!(
-            r#"
-            (variable_declarator
-            name: ((identifier) @id (#eq? @id "{varname}"))
-            value: ((array ((string) @item))))
-        "#
-        );
-        let query = Query::new(
-            &tree_sitter_typescript::language_typescript(),
-            user_query.as_str(),
-        )
-        .unwrap();
+        let query = match Query::new(language, query_source) {
+            Ok(query) => query,
+            Err(_) => return vec![],
+        };
+        let capture_index = match query.capture_index_for_name(capture) {
+            Some(index) => index,
+            None => return vec![],
+        };
 
         let mut query_cursor = QueryCursor::new();
-        let matches = query_cursor.matches(&query, tree.root_node(), source_code.as_bytes());
+        let mut texts = query_cursor
+            .matches(&query, tree.root_node(), source_code.as_bytes())
+            .flat_map(|m| {
+                m.captures
+                    .iter()
+                    .filter(move |cap| cap.index == capture_index)
+                    .map(move |cap| PositionalText {
+                        text: Self::node_text(cap.node, source_code),
+                        range: cap.node.range(),
+                    })
+            })
+            .collect::<Vec<PositionalText>>();
+        // Matches of the patterns for variables and class fields interleave
+        texts.sort_by_key(|text| text.range.start_byte);
+        texts
+    }
+
+    /// Collect the captures `capture` of the user query of a runtime-loaded grammar, from the
+    /// arrays declared as `varname`.
+    fn run_grammar_query<'a>(
+        grammar: &Grammar,
+        source_code: &'a str,
+        varname: &str,
+        capture: &str,
+    ) -> Vec<PositionalText<'a>> {
+        let mut parser = Parser::new();
+        if parser.set_language(&grammar.language).is_err() {
+            return vec![];
+        }
+        let tree = match parser.parse(source_code, None) {
+            Some(tree) => tree,
+            None => return vec![],
+        };
+        let query = match Query::new(&grammar.language, &grammar.query) {
+            Ok(query) => query,
+            Err(_) => return vec![],
+        };
+        let (id_index, capture_index) = match (
+            query.capture_index_for_name("id"),
+            query.capture_index_for_name(capture),
+        ) {
+            (Some(id_index), Some(capture_index)) => (id_index, capture_index),
+            _ => return vec![],
+        };
+
+        let mut seen = HashSet::new();
+        let mut query_cursor = QueryCursor::new();
+        query_cursor
+            .matches(&query, tree.root_node(), source_code.as_bytes())
+            .filter(|m| {
+                m.captures.iter().any(|cap| {
+                    cap.index == id_index && Self::node_text(cap.node, source_code) == varname
+                })
+            })
+            .flat_map(|m| m.captures.iter())
+            // Patterns matching every item separately capture the same array once per item
+            .filter(|cap| cap.index == capture_index && seen.insert(cap.node.id()))
+            .map(|cap| PositionalText {
+                text: Self::node_text(cap.node, source_code),
+                range: cap.node.range(),
+            })
+            .collect()
+    }
+
+    /// Collect the captures `capture` of `array` in the arrays declared as `varname`, with the
+    /// user query of the grammar for `grammar:varname` names.
+    fn parse_declarations<'a>(
+        source_code: &'a str,
+        varname: &str,
+        array: &str,
+        capture: &str,
+    ) -> Vec<PositionalText<'a>> {
+        if let Some((grammar, varname)) = grammar::split(varname) {
+            return Self::run_grammar_query(&grammar, source_code, varname, capture);
+        }
+        let user_query = Self::declaration_query(varname, array);
+
+        Self::run_query(&Self::language(varname), source_code, &user_query, capture)
+    }
 
-        // Find the capture index for capture @item
-        let array_item_index = query
-            .capture_index_for_name("item")
-            .expect("couldn't find capture index for `@item`");
+    /// Grammar of the documents declaring arrays named `varname` and the query capturing the
+    /// names of the declarations as `@id`, the arrays as `@array` and their entries as `@item`.
+    pub fn declaration(varname: &str) -> (Language, String) {
+        if let Some((grammar, _)) = grammar::split(varname) {
+            return (grammar.language.clone(), grammar.query.clone());
+        }
+        // Quantified captures like `(string)* @item` stop at the first comma, so every entry is
+        // matched on its own
+        let query = format!(
+            "{}\n{}",
+            Self::declaration_query(varname, "((array) @array)"),
+            Self::declaration_query(varname, "(array (string) @item)")
+        );
+
+        (Self::language(varname), query)
+    }
+
+    /// Run `query_source` against `source_code` and collect all captures in document order.
+    ///
+    /// Captures are collected from every match, `QueryCursor::captures` only yields the first
+    /// node of quantified captures. Nodes captured by several matches are listed once.
+    pub fn captures(
+        language: &Language,
+        source_code: &str,
+        query_source: &str,
+    ) -> Result<Vec<Capture>, String> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(language)
+            .map_err(|err| err.to_string())?;
+        let tree = parser
+            .parse(source_code, None)
+            .ok_or("Failed to parse the source")?;
+        let query =
+            Query::new(language, query_source).map_err(|err| format!("Invalid query: {}", err))?;
 
-        // let query_matches = matches.collect::<Vec<QueryMatch>>();
-        matches
+        let mut query_cursor = QueryCursor::new();
+        let mut captures: Vec<Capture> = query_cursor
+            .matches(&query, tree.root_node(), source_code.as_bytes())
             .flat_map(|m| {
                 m.captures
                     .iter()
-                    .filter(|cap| cap.index == array_item_index)
-                    .map(|cap| PositionalText {
-                        text: Self::node_string(cap.node, source_code),
-                        range: cap.node.range(),
+                    .map(|capture| Capture {
+                        name: query.capture_names()[capture.index as usize].to_string(),
+                        text: source_code[capture.node.byte_range()].to_string(),
+                        range: capture.node.range(),
                     })
+                    .collect::<Vec<_>>()
             })
-            .collect::<Vec<PositionalText>>()
+            .collect();
+        captures.sort_by_key(|capture| capture.range.start_byte);
+        let mut seen = HashSet::new();
+        captures.retain(|capture| {
+            seen.insert((
+                capture.name.clone(),
+                capture.range.start_byte,
+                capture.range.end_byte,
+            ))
+        });
+        Ok(captures)
+    }
+
+    /// Print the syntax tree of `source_code` as indented S-expression with the field names and
+    /// one-based ranges of the nodes, leaving out the nodes outside the zero-based `rows`.
+    pub fn syntax_tree(
+        language: &Language,
+        source_code: &str,
+        rows: RangeInclusive<usize>,
+    ) -> Result<String, String> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(language)
+            .map_err(|err| err.to_string())?;
+        let tree = parser
+            .parse(source_code, None)
+            .ok_or("Failed to parse the source")?;
+
+        let mut out = String::new();
+        Self::write_node(&mut tree.walk(), 0, &rows, &mut out);
+        Ok(out)
+    }
+
+    fn write_node(
+        cursor: &mut TreeCursor,
+        depth: usize,
+        rows: &RangeInclusive<usize>,
+        out: &mut String,
+    ) {
+        let node = cursor.node();
+        if (!node.is_named() && !node.is_missing())
+            || node.end_position().row < *rows.start()
+            || node.start_position().row > *rows.end()
+        {
+            return;
+        }
+
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&"  ".repeat(depth));
+        if let Some(field) = cursor.field_name() {
+            out.push_str(&format!("{}: ", field));
+        }
+        let missing = if node.is_missing() { "MISSING " } else { "" };
+        out.push_str(&format!(
+            "({}{} [{}:{}-{}:{}]",
+            missing,
+            node.kind(),
+            node.start_position().row + 1,
+            node.start_position().column + 1,
+            node.end_position().row + 1,
+            node.end_position().column + 1
+        ));
+        if cursor.goto_first_child() {
+            loop {
+                Self::write_node(cursor, depth + 1, rows, out);
+                if !cursor.goto_next_sibling() {
+                    break;
+                }
+            }
+            cursor.goto_parent();
+        }
+        out.push(')');
+    }
+
+    /// Grammar of the documents declaring arrays named `varname`, JSON for `$.path` names.
+    fn language(varname: &str) -> Language {
+        if varname.starts_with("$.") {
+            tree_sitter_json::language()
+        } else {
+            tree_sitter_typescript::language_typescript()
+        }
+    }
+
+    /// Query matching the arrays named `varname` with the pattern `array`, capturing the name of
+    /// the declaration as `@id`.
+    ///
+    /// `varname` is either the name of a variable, `variable.path.to.property` matching the
+    /// property of the object literal a variable is initialized with, like
+    /// `export const variable = { path: ... }`, `@Decorator.path.to.property` matching the
+    /// property of an object literal passed to a decorator, like `@Decorator({ path: ... })`, or
+    /// `$.path.to.property` matching the property of a JSON document like package.json.
+    fn declaration_query(varname: &str, array: &str) -> String {
+        if let Some(path) = varname.strip_prefix("$.") {
+            let value = Self::property_query(path, "string", "\"", array);
+            return format!("(document {value})");
+        }
+        let object_property = varname
+            .split_once('.')
+            .filter(|_| !varname.starts_with('@'));
+        if let Some((variable, path)) = object_property {
+            let value = Self::property_query(path, "property_identifier", "", array);
+            let variable = Self::escape_query_string(variable);
+            return format!(
+                r#"
+                (variable_declarator
+                name: ((identifier) @variable (#eq? @variable "{variable}"))
+                value: {value})
+            "#
+            );
+        }
+        let (decorator, path) = match varname
+            .strip_prefix('@')
+            .and_then(|target| target.split_once('.'))
+        {
+            Some(decorator_path) => decorator_path,
+            None => {
+                // Variables and class fields, including `static` and `readonly` ones
+                let varname = Self::escape_query_string(varname);
+                return format!(
+                    r#"
+                    (variable_declarator
+                    name: ((identifier) @id (#eq? @id "{varname}"))
+                    value: {array})
+                    (public_field_definition
+                    name: ((property_identifier) @id (#eq? @id "{varname}"))
+                    value: {array})
+                "#
+                );
+            }
+        };
+
+        let value = Self::property_query(path, "property_identifier", "", array);
+        let decorator = Self::escape_query_string(decorator);
+        format!(
+            r#"
+            (decorator (call_expression
+            function: ((identifier) @decorator (#eq? @decorator "{decorator}"))
+            arguments: (arguments {value})))
+        "#
+        )
+    }
+
+    /// Pattern of nested objects along the dot-separated property `path` holding `array`, the
+    /// keys are `key_node`s with the text of the key in `quote`s, the last one captured as `@id`.
+    fn property_query(path: &str, key_node: &str, quote: &str, array: &str) -> String {
+        // Nest the objects of the property path from the innermost one holding the array
+        let keys: Vec<&str> = path.split('.').collect();
+        let mut value = array.to_string();
+        for (i, key) in keys.iter().enumerate().rev() {
+            let capture = if i + 1 == keys.len() {
+                "id".to_string()
+            } else {
+                format!("key{}", i)
+            };
+            let key = Self::escape_query_string(&format!("{quote}{key}{quote}"));
+            value = format!(
+                r#"(object (pair
+                    key: (({key_node}) @{capture} (#eq? @{capture} "{key}"))
+                    value: {value}))"#
+            );
+        }
+        value
+    }
+
+    pub fn parse_code<'a>(source_code: &'a str, varname: &str) -> Vec<PositionalText<'a>> {
+        Self::parse_declarations(source_code, varname, "((array ((string) @item)))", "item")
+    }
+
+    /// Read the inline configuration from the comments before the first line of code.
+    pub fn parse_inline_config(source_code: &str) -> InlineConfig {
+        let mut config = InlineConfig::default();
+        for line in source_code.lines().map(str::trim) {
+            if line.is_empty() {
+                continue;
+            }
+            let comment = match line.strip_prefix("//") {
+                Some(comment) => comment.trim(),
+                None => break,
+            };
+
+            let setting = comment
+                .strip_prefix("tsm-")
+                .and_then(|setting| setting.split_once(':'))
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .filter(|(_, value)| !value.is_empty());
+            match setting {
+                Some(("suggestionsdir", value)) => config.suggestionsdir = Some(value.into()),
+                Some(("varname", value)) => config.varname = Some(value.into()),
+                _ => {}
+            }
+        }
+        config
+    }
+
+    /// The first word of `comment`, like `tsm-disable-next-line`.
+    fn directive(comment: &str) -> Option<&str> {
+        comment
+            .trim_start_matches("//")
+            .trim_start_matches("/*")
+            .trim_end_matches("*/")
+            .split_whitespace()
+            .next()
+    }
+
+    /// Find the lines and entries silenced by suppression comments.
+    pub fn parse_suppressions(source_code: &str) -> Suppressions {
+        let mut suppressions = Suppressions::default();
+        let mut disabled_from = None;
+        let language = tree_sitter_typescript::language_typescript();
+        for comment in Self::run_query(&language, source_code, "(comment) @comment", "comment") {
+            match Self::directive(comment.text) {
+                Some("tsm-disable-next-line") => {
+                    suppressions.lines.insert(comment.range.end_point.row + 1);
+                }
+                Some("tsm-disable") => {
+                    disabled_from.get_or_insert(comment.range.start_point.row);
+                }
+                Some("tsm-enable") => {
+                    if let Some(start) = disabled_from.take() {
+                        suppressions
+                            .blocks
+                            .push((start, comment.range.start_point.row));
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(start) = disabled_from {
+            suppressions.blocks.push((start, usize::MAX));
+        }
+
+        // Entries followed by a comment on their line, commas between them don't count
+        let query = "(array (string) @entry . (comment) @comment)";
+        let captures = Self::captures(&language, source_code, query).unwrap_or_default();
+        for pair in captures.windows(2) {
+            let (entry, comment) = (&pair[0], &pair[1]);
+            if entry.name == "entry"
+                && comment.name == "comment"
+                && comment.range.start_point.row == entry.range.end_point.row
+                && Self::directive(&comment.text) == Some("tsm-ignore")
+            {
+                let start = entry.range.start_point;
+                suppressions.entries.insert((start.row, start.column));
+            }
+        }
+        suppressions
+    }
+
+    /// Text every file declaring arrays named `varname` contains, the innermost property of
+    /// paths, to find them without parsing every file.
+    pub fn needle(varname: &str) -> &str {
+        varname.rsplit(['.', ':']).next().unwrap_or(varname)
+    }
+
+    /// Find the arrays declared as `varname`.
+    pub fn parse_arrays<'a>(source_code: &'a str, varname: &str) -> Vec<PositionalText<'a>> {
+        Self::parse_declarations(source_code, varname, "(array) @array", "array")
+    }
+
+    /// Find the identifiers of all array declarations named `varname`, the property names for
+    /// decorator arguments.
+    pub fn parse_identifiers<'a>(source_code: &'a str, varname: &str) -> Vec<PositionalText<'a>> {
+        Self::parse_declarations(source_code, varname, "(array)", "id")
     }
 }
 
@@ -91,4 +521,275 @@ mod tests {
         assert_eq!(1, used_folders.len());
         assert_eq!("", used_folders[0].text);
     }
+
+    #[test]
+    fn test_single_quotes() {
+        let source_code = "export const folders = ['dir_a', \"dir_b\"];";
+
+        let used_folders = LspParser::parse_code(source_code, "folders");
+        assert_eq!("dir_a", used_folders[0].text);
+        assert_eq!("dir_b", used_folders[1].text);
+    }
+
+    #[test]
+    fn test_parse_identifiers() {
+        let source_code = r#"
+             export const folders = ["dir_a", "dir_b"];
+             export const other = ["other"];
+         "#;
+
+        let identifiers = LspParser::parse_identifiers(source_code, "folders");
+        assert_eq!(1, identifiers.len());
+        assert_eq!("folders", identifiers[0].text);
+        assert_eq!(1, identifiers[0].range.start_point.row);
+    }
+
+    #[test]
+    fn test_class_fields() {
+        let source_code = r#"
+             class Assets {
+                 static folders = ["dir_a"];
+                 readonly folders = ["dir_b"];
+                 other = ["other"];
+             }
+             export const folders = ["dir_c"];
+         "#;
+
+        let used_folders = LspParser::parse_code(source_code, "folders");
+        let texts: Vec<&str> = used_folders.iter().map(|folder| folder.text).collect();
+        assert_eq!(vec!["dir_a", "dir_b", "dir_c"], texts);
+        assert_eq!(
+            3,
+            LspParser::parse_identifiers(source_code, "folders").len()
+        );
+    }
+
+    #[test]
+    fn test_decorator_arguments() {
+        let source_code = r#"
+             @Component({ selector: "app", assets: { folders: ["dir_a", "dir_b"] } })
+             class App {}
+             @Other({ assets: { folders: ["other"] } })
+             class Other {}
+         "#;
+
+        let used_folders = LspParser::parse_code(source_code, "@Component.assets.folders");
+        assert_eq!(2, used_folders.len());
+        assert_eq!("dir_a", used_folders[0].text);
+        assert_eq!("dir_b", used_folders[1].text);
+
+        let identifiers = LspParser::parse_identifiers(source_code, "@Component.assets.folders");
+        assert_eq!(1, identifiers.len());
+        assert_eq!("folders", identifiers[0].text);
+        assert_eq!(
+            1,
+            LspParser::parse_arrays(source_code, "@Other.assets.folders").len()
+        );
+        assert!(LspParser::parse_code(source_code, "@Component.folders").is_empty());
+    }
+
+    #[test]
+    fn test_object_properties() {
+        let source_code = r#"
+             export const config = { name: "app", assets: { folders: ["dir_a", "dir_b"] } };
+             const other = { assets: { folders: ["other"] } };
+             const folders = ["top"];
+         "#;
+
+        let used_folders = LspParser::parse_code(source_code, "config.assets.folders");
+        assert_eq!(2, used_folders.len());
+        assert_eq!("dir_a", used_folders[0].text);
+        assert_eq!("dir_b", used_folders[1].text);
+
+        let identifiers = LspParser::parse_identifiers(source_code, "config.assets.folders");
+        assert_eq!(1, identifiers.len());
+        assert_eq!("folders", identifiers[0].text);
+        assert_eq!(
+            1,
+            LspParser::parse_arrays(source_code, "other.assets.folders").len()
+        );
+        assert!(LspParser::parse_code(source_code, "config.folders").is_empty());
+        assert_eq!("folders", LspParser::needle("config.assets.folders"));
+        assert_eq!("folders", LspParser::needle("@Component.assets.folders"));
+        assert_eq!("folders", LspParser::needle("folders"));
+    }
+
+    #[test]
+    fn test_json_properties() {
+        let source_code = r#"{
+  "name": "app",
+  "config": { "assetDirs": ["icons", "fonts"] },
+  "assetDirs": ["top"]
+}"#;
+
+        let used_folders = LspParser::parse_code(source_code, "$.config.assetDirs");
+        assert_eq!(2, used_folders.len());
+        assert_eq!("icons", used_folders[0].text);
+        assert_eq!(2, used_folders[0].range.start_point.row);
+
+        let identifiers = LspParser::parse_identifiers(source_code, "$.assetDirs");
+        assert_eq!(1, identifiers.len());
+        assert_eq!("assetDirs", identifiers[0].text);
+        assert_eq!(3, identifiers[0].range.start_point.row);
+        assert!(LspParser::parse_code(source_code, "$.name").is_empty());
+    }
+
+    #[test]
+    fn test_runtime_grammar() {
+        grammar::register(Grammar {
+            name: "parser-json".into(),
+            language: tree_sitter_json::language(),
+            query: "(pair key: (string) @id value: (array (string) @item) @array)".into(),
+            extensions: vec![],
+        });
+        let source_code = r#"{ "folders": ["dir_a", "dir_b"], "other": ["other"] }"#;
+
+        let used_folders = LspParser::parse_code(source_code, "parser-json:folders");
+        assert_eq!(2, used_folders.len());
+        assert_eq!("dir_a", used_folders[0].text);
+        assert_eq!("dir_b", used_folders[1].text);
+        assert_eq!(
+            1,
+            LspParser::parse_arrays(source_code, "parser-json:folders").len()
+        );
+        assert_eq!(
+            1,
+            LspParser::parse_identifiers(source_code, "parser-json:other").len()
+        );
+    }
+
+    #[test]
+    fn test_captures() {
+        let source_code = r#"export const folders = ["dir_a", 'dir_b'];"#;
+
+        let (language, query) = LspParser::declaration("folders");
+        let captures = LspParser::captures(&language, source_code, &query).unwrap();
+        let names: Vec<&str> = captures.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(vec!["id", "array", "item", "item"], names);
+        assert_eq!("'dir_b'", captures[3].text);
+        assert_eq!(33, captures[3].range.start_point.column);
+
+        // Entries after other entries and non-string ones are captured as well
+        let source_code = r#"const folders = ["dir_a", "dir_b", other, "dir_c"];"#;
+        let captures = LspParser::captures(&language, source_code, &query).unwrap();
+        let items: Vec<&str> = captures
+            .iter()
+            .filter(|c| c.name == "item")
+            .map(|c| c.text.as_str())
+            .collect();
+        assert_eq!(vec![r#""dir_a""#, r#""dir_b""#, r#""dir_c""#], items);
+
+        assert!(LspParser::captures(&language, source_code, "(unknown_node)").is_err());
+    }
+
+    #[test]
+    fn test_syntax_tree() {
+        let source_code = "let a = 1;\nconst folders = [\"dir_a\"];";
+        let language = tree_sitter_typescript::language_typescript();
+
+        let tree = LspParser::syntax_tree(&language, source_code, 1..=1).unwrap();
+        assert_eq!(
+            r#"(program [1:1-2:27]
+  (lexical_declaration [2:1-2:27]
+    (variable_declarator [2:7-2:26]
+      name: (identifier [2:7-2:14])
+      value: (array [2:17-2:26]
+        (string [2:18-2:25]
+          (string_fragment [2:19-2:24]))))))"#,
+            tree
+        );
+        assert!(
+            LspParser::syntax_tree(&language, source_code, 0..=usize::MAX)
+                .unwrap()
+                .contains("(number [1:9-1:10])")
+        );
+    }
+
+    #[test]
+    fn test_parse_arrays() {
+        let source_code = r#"export const folders = ["dir_a",
+    "dir_b"];"#;
+
+        let arrays = LspParser::parse_arrays(source_code, "folders");
+        assert_eq!(1, arrays.len());
+        assert_eq!(23, arrays[0].range.start_point.column);
+        assert_eq!(1, arrays[0].range.end_point.row);
+        assert!(LspParser::parse_arrays(source_code, "other").is_empty());
+    }
+
+    #[test]
+    fn test_parse_inline_config() {
+        let source_code = r#"
+             // Generated folder list
+             // tsm-suggestionsdir: ../assets
+             // tsm-varname: dirs
+
+             export const dirs = ["dir_a"];
+             // tsm-varname: ignored
+         "#;
+
+        assert_eq!(
+            InlineConfig {
+                suggestionsdir: Some("../assets".into()),
+                varname: Some("dirs".into()),
+            },
+            LspParser::parse_inline_config(source_code)
+        );
+        assert_eq!(
+            InlineConfig::default(),
+            LspParser::parse_inline_config("// tsm-varname:\nconst folders = [];")
+        );
+    }
+
+    #[test]
+    fn test_parse_suppressions() {
+        let source_code = r#"
+             export const folders = [
+                 // tsm-disable-next-line -- generated at build time
+                 "dir_a",
+                 "dir_b",
+                 /* tsm-disable */
+                 "dir_c",
+                 // tsm-enable
+                 "dir_d",
+             ];
+         "#;
+
+        let suppressions = LspParser::parse_suppressions(source_code);
+        let suppressed: Vec<usize> = (0..12)
+            .filter(|row| suppressions.is_suppressed(*row))
+            .collect();
+        assert_eq!(vec![3, 5, 6], suppressed);
+
+        let suppressions = LspParser::parse_suppressions("// tsm-disable\nconst folders = [];");
+        assert!(suppressions.is_suppressed(1000));
+    }
+
+    #[test]
+    fn test_parse_ignored_entries() {
+        let source_code = r#"
+             export const folders = [
+                 "legacy_dir", // tsm-ignore
+                 "dir_a", "dir_b" /* tsm-ignore -- generated */,
+                 "dir_c",
+                 // tsm-ignore
+                 "dir_d" // other
+             ];
+         "#;
+
+        let suppressions = LspParser::parse_suppressions(source_code);
+        assert!(suppressions.is_ignored(2, 17));
+        assert!(!suppressions.is_ignored(3, 17));
+        assert!(suppressions.is_ignored(3, 26));
+        assert!(!suppressions.is_ignored(4, 17));
+        assert!(!suppressions.is_ignored(6, 17));
+        assert!(!suppressions.is_suppressed(2));
+    }
+
+    #[test]
+    fn test_malformed_input() {
+        assert!(LspParser::parse_code("const folders = [\"a\", ", "folders").is_empty());
+        assert!(LspParser::parse_code("\u{0}\u{ffff}", "folders").is_empty());
+        assert!(LspParser::parse_code(r#"const x = ["a"];"#, "x\") \"").is_empty());
+    }
 }
@@ -0,0 +1,185 @@
+//! Conversions between document URIs, filesystem paths and the paths shown in messages, which
+//! on Windows may carry drive letters in either case and UNC shares.
+
+use lsp_types::Url;
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+/// The file path of `uri`, normalized like [`normalize`].
+///
+/// Drive letters with an encoded colon like `file:///c%3A/project`, as VS Code sends them, are
+/// accepted as well.
+pub fn from_uri(uri: &Url) -> Option<PathBuf> {
+    let path = uri.to_file_path().ok().or_else(|| {
+        let decoded = uri.as_str().replacen("%3A", ":", 1).replacen("%3a", ":", 1);
+        Url::parse(&decoded).ok()?.to_file_path().ok()
+    })?;
+    Some(normalize(&path))
+}
+
+/// `path` without the verbatim prefix `canonicalize` adds on Windows and with an upper case
+/// drive letter, unchanged elsewhere.
+pub fn normalize(path: &Path) -> PathBuf {
+    if cfg!(windows) {
+        PathBuf::from(normalize_windows(&path.to_string_lossy()))
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// `path` as shown in messages.
+pub fn display(path: &Path) -> String {
+    normalize(path).display().to_string()
+}
+
+/// `path` with '/' separators for glob patterns, which treat backslashes as escapes.
+pub fn glob(path: &str) -> String {
+    if cfg!(windows) {
+        path.replace('\\', "/")
+    } else {
+        path.to_string()
+    }
+}
+
+/// Whether `path` is `root` or below it, after following symbolic links of existing paths and
+/// resolving `..` segments. Relative paths are relative to the working directory.
+pub fn is_inside(path: &Path, root: &Path) -> bool {
+    let resolve = |path: &Path| {
+        let path = env::current_dir().map_or_else(|_| path.to_path_buf(), |cwd| cwd.join(path));
+        normalize(&path.canonicalize().unwrap_or_else(|_| clean(&path)))
+    };
+    resolve(path).starts_with(resolve(root))
+}
+
+/// Whether `name` only consists of plain folder names, like `assets` or the nested
+/// `assets/icons` of recursive directories, without `.` and `..` segments, roots or prefixes.
+pub fn is_folder_name(name: &str) -> bool {
+    let path = Path::new(name);
+    path.components().next().is_some()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// `path` relative to the working directory with `.` and `..` segments resolved lexically, like
+/// `git diff --relative` lists them. Paths outside of the working directory stay absolute.
+pub fn relative_to_cwd(path: &Path) -> PathBuf {
+    let cwd = match env::current_dir() {
+        Ok(cwd) => clean(&cwd),
+        Err(_) => return clean(path),
+    };
+    let path = clean(&cwd.join(path));
+    path.strip_prefix(&cwd)
+        .map(Path::to_path_buf)
+        .unwrap_or(path)
+}
+
+/// `path` with `.` segments removed and `..` segments resolved lexically.
+fn clean(path: &Path) -> PathBuf {
+    let mut cleaned = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !cleaned.pop() {
+                    cleaned.push(component);
+                }
+            }
+            _ => cleaned.push(component),
+        }
+    }
+    cleaned
+}
+
+/// `\\?\C:\dir` as `C:\dir`, `\\?\UNC\server\share` as `\\server\share` and `c:\dir` as
+/// `C:\dir`.
+fn normalize_windows(path: &str) -> String {
+    let path = match path.strip_prefix(r"\\?\UNC\") {
+        Some(share) => format!(r"\\{}", share),
+        None => path.strip_prefix(r"\\?\").unwrap_or(path).to_string(),
+    };
+    let mut chars = path.chars();
+    match (chars.next(), chars.next()) {
+        (Some(drive), Some(':')) if drive.is_ascii_alphabetic() => {
+            format!("{}{}", drive.to_ascii_uppercase(), &path[1..])
+        }
+        _ => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_windows() {
+        assert_eq!(r"C:\project", normalize_windows(r"c:\project"));
+        assert_eq!(r"C:\project", normalize_windows(r"\\?\C:\project"));
+        assert_eq!(
+            r"\\server\share\assets",
+            normalize_windows(r"\\?\UNC\server\share\assets")
+        );
+        assert_eq!(r"\\server\share", normalize_windows(r"\\server\share"));
+        assert_eq!("/project/assets", normalize_windows("/project/assets"));
+        assert_eq!("assets", normalize_windows("assets"));
+    }
+
+    #[test]
+    fn test_is_inside() {
+        let root = Path::new("/does-not-exist/project");
+        assert!(is_inside(&root.join("assets"), root));
+        assert!(is_inside(root, root));
+        assert!(is_inside(&root.join("a/../assets"), root));
+        assert!(!is_inside(&root.join("../other"), root));
+        assert!(!is_inside(Path::new("/does-not-exist/project-2"), root));
+        assert!(!is_inside(Path::new("/etc"), root));
+    }
+
+    #[test]
+    fn test_is_folder_name() {
+        assert!(is_folder_name("assets"));
+        assert!(is_folder_name("assets/icons"));
+        assert!(!is_folder_name(""));
+        assert!(!is_folder_name(".."));
+        assert!(!is_folder_name("assets/../.."));
+        assert!(!is_folder_name("./assets"));
+        assert!(!is_folder_name("/etc"));
+    }
+
+    #[test]
+    fn test_relative_to_cwd() {
+        let expected = PathBuf::from("src").join("app.ts");
+        assert_eq!(expected, relative_to_cwd(Path::new("./src/app.ts")));
+        assert_eq!(expected, relative_to_cwd(Path::new("src/../src/app.ts")));
+        let cwd = env::current_dir().unwrap();
+        assert_eq!(expected, relative_to_cwd(&cwd.join("src").join("app.ts")));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_from_uri() {
+        let path = |uri: &str| from_uri(&Url::parse(uri).unwrap());
+        assert_eq!(
+            Some(PathBuf::from(r"C:\project\app.ts")),
+            path("file:///c%3A/project/app.ts")
+        );
+        assert_eq!(
+            Some(PathBuf::from(r"C:\project\app.ts")),
+            path("file:///C:/project/app.ts")
+        );
+        assert_eq!(
+            Some(PathBuf::from(r"\\server\share\app.ts")),
+            path("file://server/share/app.ts")
+        );
+        assert_eq!(None, path("untitled:Untitled-1"));
+        assert_eq!("C:/project/assets/*", glob(r"C:\project\assets/*"));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_from_uri() {
+        let uri = Url::parse("file:///project/app.ts").unwrap();
+        assert_eq!(Some(PathBuf::from("/project/app.ts")), from_uri(&uri));
+        assert_eq!(r"a\b", glob(r"a\b"));
+    }
+}
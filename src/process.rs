@@ -0,0 +1,66 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Interval in which the editor process is checked.
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Whether the process `pid` is still running, assumed if it can't be checked.
+pub fn is_alive(pid: u32) -> bool {
+    let pid = pid.to_string();
+    if cfg!(target_os = "linux") {
+        return Path::new("/proc").join(&pid).exists();
+    }
+    if cfg!(windows) {
+        let filter = format!("PID eq {}", pid);
+        return Command::new("tasklist")
+            .args(["/FI", &filter, "/NH"])
+            .output()
+            .map_or(true, |output| {
+                String::from_utf8_lossy(&output.stdout)
+                    .split_whitespace()
+                    .any(|word| word == pid)
+            });
+    }
+    Command::new("kill")
+        .args(["-0", &pid])
+        .stderr(Stdio::null())
+        .status()
+        .map_or(true, |status| status.success())
+}
+
+/// Exit the server once the editor process `pid` is gone, so the servers of crashed editors
+/// don't pile up.
+pub async fn exit_with(pid: u32) {
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let alive = tokio::task::spawn_blocking(move || is_alive(pid))
+            .await
+            .unwrap_or(true);
+        if !alive {
+            std::process::exit(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_alive() {
+        assert!(is_alive(std::process::id()));
+
+        let mut child = Command::new(if cfg!(windows) { "cmd" } else { "true" })
+            .args(if cfg!(windows) {
+                &["/C", "exit"][..]
+            } else {
+                &[][..]
+            })
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        assert!(!is_alive(pid));
+    }
+}
@@ -0,0 +1,263 @@
+//! Workspace files declaring tracked arrays, with their entries parsed once.
+//!
+//! The project is the single index behind the requests spanning the workspace, references,
+//! workspace symbols, rename and workspace diagnostics. It's filled by a scan of the workspace
+//! and kept up to date incrementally by the edits of opened documents and the watched changes of
+//! the other files, without scanning the workspace again.
+
+use crate::diagnostics::MyRange;
+use crate::parser::LspParser;
+use lsp_types::{Location, Position, Range, Url};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// A tracked array declared in a file, the location of its suggestions tells which folders the
+/// entries name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Array {
+    pub varname: String,
+    pub location: String,
+}
+
+/// An entry of a tracked array.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub varname: String,
+    /// Location of the suggestions of the array
+    pub location: String,
+    /// Text of the string without quotes
+    pub name: String,
+    /// Range of the string including quotes
+    pub range: Range,
+}
+
+impl Entry {
+    /// Whether both entries name the same folder.
+    pub fn is_same_folder(&self, other: &Entry) -> bool {
+        self.name == other.name && self.location == other.location
+    }
+}
+
+/// A workspace file declaring tracked arrays, with its content parsed last.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProjectFile {
+    pub text: Arc<str>,
+    pub entries: Vec<Entry>,
+}
+
+/// Files of the workspace declaring tracked arrays keyed by their URI.
+#[derive(Debug, Default)]
+pub struct Project {
+    files: RwLock<HashMap<Url, ProjectFile>>,
+    /// The workspace was scanned since the project was last cleared
+    discovered: AtomicBool,
+}
+
+impl Project {
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<Url, ProjectFile>> {
+        self.files.read().unwrap_or_else(|err| {
+            eprintln!("Recovered the project from a panic of another handler");
+            self.files.clear_poison();
+            err.into_inner()
+        })
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<Url, ProjectFile>> {
+        self.files.write().unwrap_or_else(|err| {
+            eprintln!("Recovered the project from a panic of another handler");
+            self.files.clear_poison();
+            err.into_inner()
+        })
+    }
+
+    /// Parse `text` of the file `uri` for the entries of `arrays`, the file is forgotten if it
+    /// doesn't declare any of them.
+    pub fn update(&self, uri: Url, text: Arc<str>, arrays: &[Array]) {
+        let entries = Self::parse(&text, arrays);
+        if entries.is_empty() {
+            self.write().remove(&uri);
+        } else {
+            self.write().insert(uri, ProjectFile { text, entries });
+        }
+    }
+
+    fn parse(text: &str, arrays: &[Array]) -> Vec<Entry> {
+        arrays
+            .iter()
+            .flat_map(|array| {
+                LspParser::parse_code(text, &array.varname)
+                    .into_iter()
+                    .map(|entry| Entry {
+                        varname: array.varname.clone(),
+                        location: array.location.clone(),
+                        name: entry.text.to_string(),
+                        range: Range::from(MyRange(entry.range, text)),
+                    })
+            })
+            .collect()
+    }
+
+    pub fn remove(&self, uri: &Url) {
+        self.write().remove(uri);
+    }
+
+    /// Forget the files below the folder `uri`, watchers only report the folder when it's deleted.
+    pub fn remove_folder(&self, uri: &Url) {
+        let prefix = format!("{}/", uri.as_str().trim_end_matches('/'));
+        self.write()
+            .retain(|file, _| !file.as_str().starts_with(&prefix));
+    }
+
+    /// Forget all files, the workspace is scanned again on the next request.
+    pub fn clear(&self) {
+        self.write().clear();
+        self.discovered.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_discovered(&self) -> bool {
+        self.discovered.load(Ordering::SeqCst)
+    }
+
+    pub fn set_discovered(&self) {
+        self.discovered.store(true, Ordering::SeqCst);
+    }
+
+    pub fn contains(&self, uri: &Url) -> bool {
+        self.read().contains_key(uri)
+    }
+
+    /// The content of the file `uri` as last parsed.
+    pub fn text(&self, uri: &Url) -> Option<Arc<str>> {
+        self.read().get(uri).map(|file| file.text.clone())
+    }
+
+    /// All files with their content, sorted by URI.
+    pub fn files(&self) -> Vec<(Url, Arc<str>)> {
+        let mut files: Vec<(Url, Arc<str>)> = self
+            .read()
+            .iter()
+            .map(|(uri, file)| (uri.clone(), file.text.clone()))
+            .collect();
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        files
+    }
+
+    /// The entry of the file `uri` at `position`.
+    pub fn entry_at(&self, uri: &Url, position: Position) -> Option<Entry> {
+        self.read()
+            .get(uri)?
+            .entries
+            .iter()
+            .find(|entry| entry.range.start <= position && position <= entry.range.end)
+            .cloned()
+    }
+
+    /// Entries of all files naming the same folder as `entry`, sorted by location.
+    pub fn references(&self, entry: &Entry) -> Vec<(Location, Entry)> {
+        self.entries(|other| other.is_same_folder(entry))
+    }
+
+    /// Entries of all files containing `query`, ignoring case, sorted by location.
+    pub fn symbols(&self, query: &str) -> Vec<(Location, Entry)> {
+        let query = query.to_lowercase();
+        self.entries(|entry| entry.name.to_lowercase().contains(&query))
+    }
+
+    fn entries(&self, filter: impl Fn(&Entry) -> bool) -> Vec<(Location, Entry)> {
+        let mut entries: Vec<(Location, Entry)> = self
+            .read()
+            .iter()
+            .flat_map(|(uri, file)| {
+                file.entries
+                    .iter()
+                    .filter(|entry| filter(entry))
+                    .map(|entry| (Location::new(uri.clone(), entry.range), entry.clone()))
+            })
+            .collect();
+        entries.sort_by(|a, b| (&a.0.uri, a.0.range.start).cmp(&(&b.0.uri, b.0.range.start)));
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arrays() -> Vec<Array> {
+        vec![
+            Array {
+                varname: "folders".into(),
+                location: "/public".into(),
+            },
+            Array {
+                varname: "icons".into(),
+                location: "/icons".into(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_project() {
+        let project = Project::default();
+        let a = Url::parse("file:///a.ts").unwrap();
+        let b = Url::parse("file:///b.ts").unwrap();
+        let c = Url::parse("file:///c.ts").unwrap();
+        project.update(
+            a.clone(),
+            r#"const folders = ["assets", "fonts"];"#.into(),
+            &arrays(),
+        );
+        project.update(
+            b.clone(),
+            r#"const icons = ["assets"]; const folders = ["assets"];"#.into(),
+            &arrays(),
+        );
+        project.update(c.clone(), "const other = [];".into(), &arrays());
+        assert!(!project.contains(&c));
+
+        let entry = project.entry_at(&a, Position::new(0, 20)).unwrap();
+        assert_eq!("assets", entry.name);
+        assert_eq!(None, project.entry_at(&a, Position::new(0, 3)));
+
+        // The icons array names folders of another directory
+        let references: Vec<Location> = project
+            .references(&entry)
+            .into_iter()
+            .map(|(location, _)| location)
+            .collect();
+        assert_eq!(
+            vec![
+                Location::new(
+                    a.clone(),
+                    Range::new(Position::new(0, 17), Position::new(0, 25))
+                ),
+                Location::new(
+                    b.clone(),
+                    Range::new(Position::new(0, 43), Position::new(0, 51))
+                ),
+            ],
+            references
+        );
+        assert_eq!(3, project.symbols("ASS").len());
+        assert_eq!(1, project.symbols("font").len());
+
+        project.remove_folder(&Url::parse("file:///b").unwrap());
+        assert!(project.contains(&b));
+
+        project.update(a.clone(), "const folders = 1;".into(), &arrays());
+        assert_eq!(
+            vec![b.clone()],
+            project
+                .files()
+                .into_iter()
+                .map(|(uri, _)| uri)
+                .collect::<Vec<_>>()
+        );
+        project.remove_folder(&Url::parse("file:///").unwrap());
+        assert!(project.files().is_empty());
+        project.clear();
+        assert!(project.files().is_empty());
+        assert!(!project.is_discovered());
+    }
+}
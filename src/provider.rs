@@ -0,0 +1,397 @@
+use crate::cli::CliArgs;
+use crate::config::{Config, CONFIG_FILE};
+use crate::indexer;
+use crate::paths;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Source of the names entries are validated against.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Provider {
+    /// Entries of the suggestions directory, nested ones as paths if `recursive`
+    Directory { dir: String, recursive: bool },
+    /// Names declared in a JSON manifest, like `folders.lock.json`
+    Manifest(PathBuf),
+    /// Names stored in a column of a SQLite database table, requires the `sqlite` feature
+    Sqlite {
+        path: PathBuf,
+        table: String,
+        column: String,
+    },
+    /// Names printed by a shell command one per line, run in `dir`. They can't be watched, so
+    /// they are served from a snapshot refreshed once it expired
+    Command { command: String, dir: PathBuf },
+    /// Names listed on the command line or in the configuration file
+    List(Vec<String>),
+}
+
+impl Provider {
+    pub fn from_args(args: &CliArgs) -> Self {
+        if !args.suggestions.is_empty() {
+            Provider::List(args.suggestions.clone())
+        } else if let Some(manifest) = &args.manifest {
+            Provider::Manifest(manifest.clone())
+        } else if let Some(command) = &args.suggestions_command {
+            Provider::Command {
+                command: command.clone(),
+                dir: PathBuf::from("."),
+            }
+        } else if let Some(path) = &args.sqlite {
+            Provider::Sqlite {
+                path: path.clone(),
+                table: args.sqlite_table.clone(),
+                column: args.sqlite_column.clone(),
+            }
+        } else {
+            Provider::Directory {
+                dir: args.suggestionsdir.clone(),
+                recursive: args.recursive,
+            }
+        }
+    }
+
+    /// The provider of the command line arguments, the `suggestions` of `config` instead of the
+    /// suggestions directory.
+    pub fn from_config(args: &CliArgs, config: &Config) -> Self {
+        match Self::from_args(args) {
+            Provider::Directory { .. } if !config.suggestions.is_empty() => {
+                Provider::List(config.suggestions.clone())
+            }
+            provider => provider,
+        }
+    }
+
+    /// The provider with relative paths resolved against `base`, e.g. the workspace root.
+    pub fn resolve(self, base: &Path) -> Self {
+        match self {
+            Provider::Directory { dir, recursive } => Provider::Directory {
+                dir: paths::display(&resolve_path(base, Path::new(&dir))),
+                recursive,
+            },
+            Provider::Manifest(path) => Provider::Manifest(resolve_path(base, &path)),
+            Provider::Sqlite {
+                path,
+                table,
+                column,
+            } => Provider::Sqlite {
+                path: resolve_path(base, &path),
+                table,
+                column,
+            },
+            Provider::Command { command, dir } => Provider::Command {
+                command,
+                dir: resolve_path(base, &dir),
+            },
+            Provider::List(names) => Provider::List(names),
+        }
+    }
+
+    /// All valid names, in no particular order.
+    pub fn names(&self) -> Result<Vec<String>, String> {
+        match self {
+            Provider::Directory {
+                dir,
+                recursive: false,
+            } => indexer::read_names(dir),
+            // Entries below the directory which can't be read are skipped, it must be readable
+            Provider::Directory {
+                dir,
+                recursive: true,
+            } => indexer::read_names(dir).map(|_| indexer::get_files_recursive(dir)),
+            Provider::Manifest(path) => {
+                let manifest = fs::read_to_string(path)
+                    .map_err(|err| format!("{}: {}", path.display(), err))?;
+                parse_manifest(&manifest).map_err(|err| format!("{}: {}", path.display(), err))
+            }
+            Provider::Sqlite {
+                path,
+                table,
+                column,
+            } => query_sqlite(path, table, column)
+                .map_err(|err| format!("{}: {}", path.display(), err)),
+            Provider::Command { command, dir } => {
+                run_command(command, dir).map_err(|err| format!("{}: {}", command, err))
+            }
+            Provider::List(names) => Ok(names.clone()),
+        }
+    }
+
+    /// The names which are regular files rather than folders, only directories list files.
+    pub fn files(&self) -> Vec<String> {
+        match self {
+            Provider::Directory { dir, recursive } => indexer::get_regular_files(dir, *recursive),
+            Provider::Manifest(_)
+            | Provider::Sqlite { .. }
+            | Provider::Command { .. }
+            | Provider::List(_) => vec![],
+        }
+    }
+
+    /// The file holding the names of providers other than a directory.
+    pub fn source_file(&self) -> Option<&Path> {
+        match self {
+            Provider::Directory { .. } | Provider::Command { .. } | Provider::List(_) => None,
+            Provider::Manifest(path) | Provider::Sqlite { path, .. } => Some(path),
+        }
+    }
+
+    /// Where the names come from, for messages.
+    pub fn location(&self) -> String {
+        match self {
+            Provider::Directory { dir, .. } => dir.clone(),
+            Provider::Manifest(path) => paths::display(path),
+            Provider::Sqlite {
+                path,
+                table,
+                column,
+            } => format!("{}#{}.{}", paths::display(path), table, column),
+            Provider::Command { command, .. } => command.clone(),
+            Provider::List(names) => format!("[{}]", names.join(", ")),
+        }
+    }
+
+    /// Location of `name` for progress reports.
+    pub fn describe(&self, name: &str) -> String {
+        match self {
+            Provider::Directory { dir, .. } => paths::display(&Path::new(dir).join(name)),
+            Provider::Manifest(_)
+            | Provider::Sqlite { .. }
+            | Provider::Command { .. }
+            | Provider::List(_) => name.to_string(),
+        }
+    }
+
+    /// Glob of the files to watch for changes of the names, for commands the files of their
+    /// working directory, which expire the snapshot of their names, and the configuration file
+    /// for lists.
+    pub fn watch_glob(&self) -> String {
+        let glob = match self {
+            Provider::Directory {
+                dir,
+                recursive: false,
+            } => format!("{}/*", dir),
+            Provider::Directory {
+                dir,
+                recursive: true,
+            } => format!("{}/**", dir),
+            Provider::Manifest(path) | Provider::Sqlite { path, .. } => paths::display(path),
+            Provider::Command { dir, .. } => format!("{}/*", paths::display(dir)),
+            Provider::List(_) => format!("**/{}", CONFIG_FILE),
+        };
+        paths::glob(&glob)
+    }
+
+    /// Whether the names are served from a snapshot which expires, instead of being kept up to
+    /// date by file events.
+    pub fn expires(&self) -> bool {
+        matches!(self, Provider::Command { .. })
+    }
+}
+
+/// `path` joined to `base` unless it is absolute, without `.` components.
+fn resolve_path(base: &Path, path: &Path) -> PathBuf {
+    base.join(path).components().collect()
+}
+
+/// Quote `identifier` for use as a table or column name in SQL.
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+#[cfg(feature = "sqlite")]
+fn query_sqlite(path: &Path, table: &str, column: &str) -> Result<Vec<String>, String> {
+    use rusqlite::{Connection, OpenFlags};
+
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|err| err.to_string())?;
+    let query = format!(
+        "SELECT DISTINCT {column} FROM {table} WHERE {column} IS NOT NULL",
+        column = quote_identifier(column),
+        table = quote_identifier(table)
+    );
+    let mut statement = connection.prepare(&query).map_err(|err| err.to_string())?;
+    let rows = statement
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|err| err.to_string())?;
+    rows.collect::<Result<Vec<String>, _>>()
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn query_sqlite(_path: &Path, table: &str, column: &str) -> Result<Vec<String>, String> {
+    Err(format!(
+        "can't read {}.{}, the server was built without the sqlite feature",
+        quote_identifier(table),
+        quote_identifier(column)
+    ))
+}
+
+/// Names printed by the shell `command` run in `dir`, one per line.
+fn run_command(command: &str, dir: &Path) -> Result<Vec<String>, String> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let output = process::Command::new(shell)
+        .arg(flag)
+        .arg(command)
+        .current_dir(dir)
+        .stdin(process::Stdio::null())
+        .output()
+        .map_err(|err| err.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "{}, {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Names of a manifest, either a JSON array of strings or an object with a `folders` array.
+pub fn parse_manifest(manifest: &str) -> Result<Vec<String>, String> {
+    let value: serde_json::Value = serde_json::from_str(manifest).map_err(|err| err.to_string())?;
+    let names = match &value {
+        serde_json::Value::Array(names) => names,
+        serde_json::Value::Object(object) => match object.get("folders") {
+            Some(serde_json::Value::Array(names)) => names,
+            _ => return Err("expected a \"folders\" array".into()),
+        },
+        _ => return Err("expected an array or an object".into()),
+    };
+
+    names
+        .iter()
+        .map(|name| {
+            name.as_str()
+                .map(String::from)
+                .ok_or_else(|| format!("expected a string, found {}", name))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_parse_manifest() {
+        assert_eq!(
+            Ok(vec!["assets".to_string(), "fonts".to_string()]),
+            parse_manifest(r#"["assets", "fonts"]"#)
+        );
+        assert_eq!(
+            Ok(vec!["assets".to_string()]),
+            parse_manifest(r#"{ "folders": ["assets"] }"#)
+        );
+        assert!(parse_manifest(r#"{ "dirs": [] }"#).is_err());
+        assert!(parse_manifest(r#"[1]"#).is_err());
+        assert!(parse_manifest("").is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let base = Path::new("/workspace");
+        let directory = |dir: &str| Provider::Directory {
+            dir: dir.to_string(),
+            recursive: false,
+        };
+        assert_eq!(directory("/workspace"), directory(".").resolve(base));
+        assert_eq!(
+            directory("/workspace/assets"),
+            directory("./assets").resolve(base)
+        );
+        assert_eq!(
+            directory("/srv/assets"),
+            directory("/srv/assets").resolve(base)
+        );
+        assert_eq!(
+            Provider::Manifest(PathBuf::from("/workspace/folders.lock.json")),
+            Provider::Manifest(PathBuf::from("folders.lock.json")).resolve(base)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command() {
+        let provider = Provider::Command {
+            command: "printf 'assets\\n\\n fonts \\n'".into(),
+            dir: PathBuf::from("."),
+        };
+        assert_eq!(Ok(vec!["assets".into(), "fonts".into()]), provider.names());
+        assert!(provider.expires());
+
+        let failing = Provider::Command {
+            command: "exit 3".into(),
+            dir: PathBuf::from("."),
+        };
+        assert!(failing.names().is_err());
+    }
+
+    #[test]
+    fn test_from_config() {
+        let config = Config {
+            suggestions: vec!["assets".into()],
+            ..Config::default()
+        };
+        let args = CliArgs::parse_from(["tsm"]);
+        assert_eq!(
+            Provider::List(vec!["assets".into()]),
+            Provider::from_config(&args, &config)
+        );
+
+        let args = CliArgs::parse_from(["tsm", "--suggestions", "fonts,icons"]);
+        let provider = Provider::from_config(&args, &config);
+        assert_eq!(
+            Provider::List(vec!["fonts".into(), "icons".into()]),
+            provider
+        );
+        assert_eq!(Ok(vec!["fonts".into(), "icons".into()]), provider.names());
+
+        let args = CliArgs::parse_from(["tsm", "--manifest", "folders.lock.json"]);
+        assert_eq!(
+            Provider::Manifest(PathBuf::from("folders.lock.json")),
+            Provider::from_config(&args, &config)
+        );
+    }
+
+    #[test]
+    fn test_quote_identifier() {
+        assert_eq!("\"folders\"", quote_identifier("folders"));
+        assert_eq!("\"a\"\"b\"", quote_identifier("a\"b"));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite() {
+        let path = std::env::temp_dir().join(format!("tsm-sqlite-{}.db", std::process::id()));
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE dirs (name TEXT); INSERT INTO dirs VALUES ('assets'), ('fonts'), (NULL);",
+            )
+            .unwrap();
+        drop(connection);
+
+        let provider = Provider::Sqlite {
+            path: path.clone(),
+            table: "dirs".into(),
+            column: "name".into(),
+        };
+        let mut names = provider.names().unwrap();
+        fs::remove_file(&path).unwrap();
+        names.sort();
+
+        assert_eq!(vec!["assets", "fonts"], names);
+    }
+}
@@ -0,0 +1,35 @@
+use super::{paths, Context, Fix, FixContext, Rule};
+use crate::i18n::Message;
+use crate::parser::PositionalText;
+use lsp_types::Diagnostic;
+use std::path::Path;
+
+/// Absolute paths, which escape the suggestions directory. `data` holds the path relative to
+/// the suggestions directory if it lies inside of it.
+pub struct AbsolutePath;
+
+impl Rule for AbsolutePath {
+    fn code(&self) -> &'static str {
+        "108"
+    }
+
+    fn name(&self) -> &'static str {
+        "absolute-path"
+    }
+
+    fn check_entry(&self, entry: &PositionalText, context: &Context) -> Option<Diagnostic> {
+        if context.config.allow.contains(entry.text) || !Path::new(entry.text).has_root() {
+            return None;
+        }
+        let message = Message::AbsolutePath(entry.text).text(context.locale);
+        Some(Diagnostic {
+            data: paths::relative_to_root(entry.text, context.suggestionsdir)
+                .map(serde_json::Value::String),
+            ..super::diagnostic(self, entry, context, message)
+        })
+    }
+
+    fn fixes(&self, diagnostic: &Diagnostic, context: &FixContext) -> Vec<Fix> {
+        paths::relative_fixes(diagnostic, context)
+    }
+}
@@ -0,0 +1,67 @@
+use super::{Context, Fix, FixContext, Rule};
+use crate::edits;
+use crate::i18n::Message;
+use crate::parser::PositionalText;
+use lsp_types::Diagnostic;
+
+/// Entries differing from a folder only by case, like `"Assets"` for `assets`, `data` holds the
+/// name of the folder.
+pub struct CaseMismatch;
+
+impl Rule for CaseMismatch {
+    fn code(&self) -> &'static str {
+        "111"
+    }
+
+    fn name(&self) -> &'static str {
+        "case-mismatch"
+    }
+
+    fn check_entry(&self, entry: &PositionalText, context: &Context) -> Option<Diagnostic> {
+        let valid = context.config.allow.contains(entry.text)
+            || (context.is_valid_folder)(entry.text)
+            || context.validator_names.contains(entry.text);
+        if valid {
+            return None;
+        }
+        let lowercase = entry.text.to_lowercase();
+        let folder = (context.folder_names)()
+            .into_iter()
+            .find(|name| name.to_lowercase() == lowercase)?;
+        let message = Message::CaseMismatch {
+            name: entry.text,
+            folder: &folder,
+        };
+        Some(Diagnostic {
+            data: Some(serde_json::Value::String(folder.clone())),
+            ..super::diagnostic(self, entry, context, message.text(context.locale))
+        })
+    }
+
+    /// The folder with the right casing first, followed by the suggestions for it.
+    fn fixes(&self, diagnostic: &Diagnostic, context: &FixContext) -> Vec<Fix> {
+        let folder = match diagnostic.data.as_ref().and_then(|data| data.as_str()) {
+            Some(folder) => folder,
+            None => return vec![],
+        };
+        let original = edits::text_at(context.source_code, diagnostic.range).unwrap_or("\"");
+        let edit = edits::replace_string(diagnostic.range, original, folder, context.quote_style);
+        let mut fixes = vec![Fix {
+            title: Message::FixCasing(folder).text(context.locale),
+            edits: vec![edit],
+            is_preferred: true,
+            score: None,
+            fix_all: true,
+        }];
+        let suggestions: Vec<Fix> = super::suggestion_fixes(diagnostic, context)
+            .into_iter()
+            .filter(|fix| fix.edits != fixes[0].edits)
+            .map(|fix| Fix {
+                is_preferred: false,
+                ..fix
+            })
+            .collect();
+        fixes.extend(suggestions);
+        fixes
+    }
+}
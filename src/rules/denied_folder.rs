@@ -0,0 +1,30 @@
+use super::{Context, Fix, FixContext, Rule};
+use crate::i18n::Message;
+use crate::parser::PositionalText;
+use lsp_types::Diagnostic;
+
+/// Entries on the configured denylist, even if the folder exists.
+pub struct DeniedFolder;
+
+impl Rule for DeniedFolder {
+    fn code(&self) -> &'static str {
+        "102"
+    }
+
+    fn name(&self) -> &'static str {
+        "denied-folder"
+    }
+
+    fn check_entry(&self, entry: &PositionalText, context: &Context) -> Option<Diagnostic> {
+        let message = Message::DeniedFolder(entry.text).text(context.locale);
+        context
+            .config
+            .deny
+            .contains(entry.text)
+            .then(|| super::diagnostic(self, entry, context, message))
+    }
+
+    fn fixes(&self, diagnostic: &Diagnostic, context: &FixContext) -> Vec<Fix> {
+        super::suggestion_fixes(diagnostic, context)
+    }
+}
@@ -0,0 +1,59 @@
+use super::{Context, Fix, FixContext, Rule};
+use crate::config::Level;
+use crate::edits;
+use crate::i18n::Message;
+use crate::parser::PositionalText;
+use lsp_types::{Diagnostic, DiagnosticTag};
+
+/// Entries referencing a deprecated folder, `data` holds the replacement to migrate to.
+pub struct DeprecatedFolder;
+
+impl Rule for DeprecatedFolder {
+    fn code(&self) -> &'static str {
+        "101"
+    }
+
+    fn name(&self) -> &'static str {
+        "deprecated-folder"
+    }
+
+    fn default_level(&self) -> Level {
+        Level::Warn
+    }
+
+    fn check_entry(&self, entry: &PositionalText, context: &Context) -> Option<Diagnostic> {
+        if context.config.allow.contains(entry.text) {
+            return None;
+        }
+        let replacement = context.config.replacement(entry.text)?;
+        let message = Message::DeprecatedFolder {
+            name: entry.text,
+            replacement,
+        };
+        Some(Diagnostic {
+            tags: Some(vec![DiagnosticTag::DEPRECATED]),
+            data: Some(serde_json::Value::String(replacement.into())),
+            ..super::diagnostic(self, entry, context, message.text(context.locale))
+        })
+    }
+
+    fn fixes(&self, diagnostic: &Diagnostic, context: &FixContext) -> Vec<Fix> {
+        let replacement = match diagnostic.data.as_ref().and_then(|data| data.as_str()) {
+            Some(replacement) => replacement,
+            None => return vec![],
+        };
+        let original = edits::text_at(context.source_code, diagnostic.range).unwrap_or("\"");
+        vec![Fix {
+            title: Message::MigrateFolder(replacement).text(context.locale),
+            edits: vec![edits::replace_string(
+                diagnostic.range,
+                original,
+                replacement,
+                context.quote_style,
+            )],
+            is_preferred: true,
+            score: None,
+            fix_all: false,
+        }]
+    }
+}
@@ -0,0 +1,43 @@
+use super::{Context, Rule};
+use crate::config::Level;
+use crate::i18n::Message;
+use crate::parser::PositionalText;
+use lsp_types::Diagnostic;
+use std::collections::HashSet;
+
+/// Entries listed more than once in an array, reported for every occurrence but the first.
+pub struct DuplicateEntry;
+
+impl Rule for DuplicateEntry {
+    fn code(&self) -> &'static str {
+        "104"
+    }
+
+    fn name(&self) -> &'static str {
+        "duplicate-entry"
+    }
+
+    fn default_level(&self) -> Level {
+        Level::Warn
+    }
+
+    fn check_entries(&self, entries: &[PositionalText], context: &Context) -> Vec<Diagnostic> {
+        super::by_array(entries, context.source_code, context.varname)
+            .into_iter()
+            .flat_map(|entries| {
+                let mut seen = HashSet::new();
+                entries.iter().filter(move |entry| !seen.insert(entry.text))
+            })
+            .map(|entry| Diagnostic {
+                // Duplicates may well be valid, there is nothing to replace them with
+                data: None,
+                ..super::diagnostic(
+                    self,
+                    entry,
+                    context,
+                    Message::DuplicateEntry(entry.text).text(context.locale),
+                )
+            })
+            .collect()
+    }
+}
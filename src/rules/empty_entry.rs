@@ -0,0 +1,29 @@
+use super::{Context, Fix, FixContext, Rule};
+use crate::i18n::Message;
+use crate::parser::PositionalText;
+use lsp_types::Diagnostic;
+
+/// Empty strings, which never name a folder.
+pub struct EmptyEntry;
+
+impl Rule for EmptyEntry {
+    fn code(&self) -> &'static str {
+        "105"
+    }
+
+    fn name(&self) -> &'static str {
+        "empty-entry"
+    }
+
+    fn check_entry(&self, entry: &PositionalText, context: &Context) -> Option<Diagnostic> {
+        entry.text.is_empty().then(|| {
+            let noun = context.config.labels.noun();
+            let message = Message::EmptyEntry(noun.as_ref());
+            super::diagnostic(self, entry, context, message.text(context.locale))
+        })
+    }
+
+    fn fixes(&self, diagnostic: &Diagnostic, context: &FixContext) -> Vec<Fix> {
+        super::suggestion_fixes(diagnostic, context)
+    }
+}
@@ -0,0 +1,34 @@
+use super::{Context, Fix, FixContext, Rule};
+use crate::i18n::Message;
+use crate::parser::PositionalText;
+use lsp_types::Diagnostic;
+
+/// Entries which aren't a folder in the suggestions directory.
+pub struct InvalidFolder;
+
+impl Rule for InvalidFolder {
+    fn code(&self) -> &'static str {
+        "100"
+    }
+
+    fn name(&self) -> &'static str {
+        "invalid-folder"
+    }
+
+    fn check_entry(&self, entry: &PositionalText, context: &Context) -> Option<Diagnostic> {
+        let valid = context.config.allow.contains(entry.text)
+            || (context.is_valid_folder)(entry.text)
+            || context.validator_names.contains(entry.text);
+        let noun = context.config.labels.noun();
+        let message = Message::InvalidFolder {
+            name: entry.text,
+            dir: context.suggestionsdir,
+            noun: noun.as_ref(),
+        };
+        (!valid).then(|| super::diagnostic(self, entry, context, message.text(context.locale)))
+    }
+
+    fn fixes(&self, diagnostic: &Diagnostic, context: &FixContext) -> Vec<Fix> {
+        super::suggestion_fixes(diagnostic, context)
+    }
+}
@@ -0,0 +1,94 @@
+use super::{Context, Fix, FixContext, Rule};
+use crate::config::{Config, Level};
+use crate::edits;
+use crate::i18n::Message;
+use crate::parser::{LspParser, PositionalText};
+use lsp_types::Diagnostic;
+use std::collections::HashSet;
+
+/// Folders of the suggestions directory which aren't listed, reported on the declaration of
+/// the array with the missing names as `data`.
+pub struct MissingEntries;
+
+impl Rule for MissingEntries {
+    fn code(&self) -> &'static str {
+        "110"
+    }
+
+    fn name(&self) -> &'static str {
+        "missing-entries"
+    }
+
+    fn default_level(&self) -> Level {
+        Level::Off
+    }
+
+    fn check_entries(&self, entries: &[PositionalText], context: &Context) -> Vec<Diagnostic> {
+        let declaration = match LspParser::parse_identifiers(context.source_code, context.varname)
+            .into_iter()
+            .next()
+        {
+            Some(declaration) => declaration,
+            None => return vec![],
+        };
+        let missing = missing_names(entries, (context.folder_names)(), context.config);
+        if missing.is_empty() {
+            return vec![];
+        }
+
+        let noun = context.config.labels.noun();
+        let message = Message::MissingEntries {
+            count: missing.len(),
+            names: &missing.join(", "),
+            noun: noun.as_ref(),
+        };
+        vec![Diagnostic {
+            data: Some(missing.into()),
+            ..super::diagnostic(self, &declaration, context, message.text(context.locale))
+        }]
+    }
+
+    fn fixes(&self, diagnostic: &Diagnostic, context: &FixContext) -> Vec<Fix> {
+        let missing: Vec<&str> = diagnostic
+            .data
+            .as_ref()
+            .and_then(|data| data.as_array())
+            .map(|names| names.iter().filter_map(|name| name.as_str()).collect())
+            .unwrap_or_default();
+        if missing.is_empty() {
+            return vec![];
+        }
+        let edit = edits::append_entries(
+            context.source_code,
+            context.varname,
+            &missing,
+            context.quote_style,
+        );
+
+        edit.map(|edit| Fix {
+            title: Message::AddMissingEntries(missing.len()).text(context.locale),
+            edits: vec![edit],
+            is_preferred: true,
+            score: None,
+            fix_all: false,
+        })
+        .into_iter()
+        .collect()
+    }
+}
+
+/// The top level folders among `folder_names` which no entry lists. Nested folders are covered by
+/// their parent, denied and deprecated ones shouldn't be added.
+pub fn missing_names(
+    entries: &[PositionalText],
+    folder_names: Vec<String>,
+    config: &Config,
+) -> Vec<String> {
+    let listed: HashSet<&str> = entries.iter().map(|entry| entry.text).collect();
+    folder_names
+        .into_iter()
+        .filter(|name| !name.contains('/') && !listed.contains(name.as_str()))
+        .filter(|name| !config.deny.contains(name))
+        .filter(|name| config.replacement(name).is_none())
+        .collect()
+}
@@ -0,0 +1,237 @@
+//! Checks of tracked arrays. Every check is a self-contained [`Rule`] with its own code, level
+//! and quick fixes, and is listed in [`registry`].
+
+mod absolute_path;
+mod case_mismatch;
+mod denied_folder;
+mod deprecated_folder;
+mod duplicate_entry;
+mod empty_entry;
+mod invalid_folder;
+mod missing_entries;
+mod naming_convention;
+mod not_a_directory;
+mod path_traversal;
+mod paths;
+mod plugin;
+mod unsorted_entries;
+
+pub use absolute_path::AbsolutePath;
+pub use case_mismatch::CaseMismatch;
+pub use denied_folder::DeniedFolder;
+pub use deprecated_folder::DeprecatedFolder;
+pub use duplicate_entry::DuplicateEntry;
+pub use empty_entry::EmptyEntry;
+pub use invalid_folder::InvalidFolder;
+pub use missing_entries::{missing_names, MissingEntries};
+pub use naming_convention::NamingConvention;
+pub use not_a_directory::NotADirectory;
+pub use path_traversal::PathTraversal;
+pub use plugin::Plugin;
+pub use unsorted_entries::UnsortedEntries;
+
+use crate::config::{Config, Level};
+use crate::diagnostics::MyRange;
+use crate::edits::{self, QuoteStyle};
+use crate::i18n::{Locale, Message};
+use crate::matcher::Algorithm;
+use crate::parser::{LspParser, PositionalText};
+use crate::validator::Validator;
+use lsp_types::{Diagnostic, NumberOrString, TextEdit};
+use regex::Regex;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// What rules know about the checked document and the valid names.
+pub struct Context<'a> {
+    pub source_code: &'a str,
+    pub varname: &'a str,
+    /// Location of the valid names, the directory entries are relative to
+    pub suggestionsdir: &'a str,
+    pub locale: Locale,
+    pub config: &'a Config,
+    pub validators: &'a [Arc<dyn Validator>],
+    /// Names supplied by the validators, which are valid as well
+    pub validator_names: HashSet<String>,
+    /// Pattern all entries have to match, if configured
+    pub naming: Option<Regex>,
+    pub is_valid_folder: &'a dyn Fn(&str) -> bool,
+    /// Whether a valid name is a regular file rather than a folder
+    pub is_file: &'a dyn Fn(&str) -> bool,
+    /// All valid names, only listed by rules which need them
+    pub folder_names: &'a dyn Fn() -> Vec<String>,
+}
+
+/// What quick fixes may use besides the diagnostic.
+pub struct FixContext<'a> {
+    pub source_code: &'a str,
+    pub varname: &'a str,
+    pub locale: Locale,
+    pub quote_style: QuoteStyle,
+    /// Algorithm the scores of the suggestions come from
+    pub algorithm: Algorithm,
+    /// The best matching valid names for an input with their scores, best first
+    pub suggestions: &'a dyn Fn(&str) -> Vec<(String, f64)>,
+}
+
+/// A quick fix for a diagnostic.
+pub struct Fix {
+    pub title: String,
+    pub edits: Vec<TextEdit>,
+    /// Whether editors may apply it automatically, the one obvious fix or the best match of
+    /// several
+    pub is_preferred: bool,
+    /// Score of a fix replacing the entry with a suggested name. The first of these is the best
+    /// match, which replacing all invalid entries at once applies
+    pub score: Option<f64>,
+    /// Whether the fix is safe to apply unattended, it is offered as `source.fixAll` as well
+    pub fix_all: bool,
+}
+
+/// A check of the entries of tracked arrays.
+pub trait Rule: Send + Sync {
+    /// Code of the diagnostics, like "100"
+    fn code(&self) -> &'static str;
+
+    /// Name the level is configured by in the `[rules]` table, like "invalid-folder"
+    fn name(&self) -> &'static str;
+
+    /// Level unless configured otherwise
+    fn default_level(&self) -> Level {
+        Level::Error
+    }
+
+    /// Problem of a single entry. Only the first rule of the registry reporting a problem for
+    /// an entry is reported.
+    fn check_entry(&self, _entry: &PositionalText, _context: &Context) -> Option<Diagnostic> {
+        None
+    }
+
+    /// Problems concerning several entries of an array, like duplicates.
+    fn check_entries(&self, _entries: &[PositionalText], _context: &Context) -> Vec<Diagnostic> {
+        vec![]
+    }
+
+    /// Quick fixes for a diagnostic reported by this rule.
+    fn fixes(&self, _diagnostic: &Diagnostic, _context: &FixContext) -> Vec<Fix> {
+        vec![]
+    }
+}
+
+static RULES: &[&dyn Rule] = &[
+    &EmptyEntry,
+    &AbsolutePath,
+    &PathTraversal,
+    &DeniedFolder,
+    &DeprecatedFolder,
+    &CaseMismatch,
+    &InvalidFolder,
+    &NotADirectory,
+    &Plugin,
+    &NamingConvention,
+    &DuplicateEntry,
+    &UnsortedEntries,
+    &MissingEntries,
+];
+
+/// All rules, the ones checking single entries in order of precedence.
+pub fn registry() -> &'static [&'static dyn Rule] {
+    RULES
+}
+
+/// The rule named `name`.
+pub fn rule(name: &str) -> Option<&'static dyn Rule> {
+    RULES.iter().copied().find(|rule| rule.name() == name)
+}
+
+/// The rule which reported `diagnostic`.
+pub fn of(diagnostic: &Diagnostic) -> Option<&'static dyn Rule> {
+    match &diagnostic.code {
+        Some(NumberOrString::String(code)) => {
+            RULES.iter().copied().find(|rule| rule.code() == code)
+        }
+        _ => None,
+    }
+}
+
+/// Diagnostic of `rule` for `entry` with the entry as `data`, the engine sets its severity.
+pub fn diagnostic(
+    rule: &dyn Rule,
+    entry: &PositionalText,
+    context: &Context,
+    message: String,
+) -> Diagnostic {
+    Diagnostic {
+        range: MyRange(entry.range, context.source_code).into(),
+        code: Some(NumberOrString::String(rule.code().into())),
+        source: Some("tsm-language-server".into()),
+        message,
+        data: Some(serde_json::Value::String(entry.text.to_string())),
+        ..Diagnostic::default()
+    }
+}
+
+/// `entries` in document order split by the arrays declared as `varname`, so files declaring the
+/// array in several places are checked one array at a time.
+pub fn by_array<'e, 'a>(
+    entries: &'e [PositionalText<'a>],
+    source_code: &str,
+    varname: &str,
+) -> Vec<&'e [PositionalText<'a>]> {
+    LspParser::parse_arrays(source_code, varname)
+        .iter()
+        .map(|array| {
+            let start = entries.partition_point(|e| e.range.start_byte < array.range.start_byte);
+            let end = entries.partition_point(|e| e.range.start_byte < array.range.end_byte);
+            &entries[start..end]
+        })
+        .filter(|entries| !entries.is_empty())
+        .collect()
+}
+
+/// Fixes replacing the entry of `diagnostic`, its `data`, with the best matching valid names,
+/// the best one preferred. Their titles end with the score, e.g. "Use folder assets (92%)".
+pub fn suggestion_fixes(diagnostic: &Diagnostic, context: &FixContext) -> Vec<Fix> {
+    let user_input = diagnostic
+        .data
+        .as_ref()
+        .and_then(|data| data.as_str())
+        .unwrap_or_default();
+    let original = edits::text_at(context.source_code, diagnostic.range).unwrap_or("\"");
+
+    (context.suggestions)(user_input)
+        .into_iter()
+        .enumerate()
+        .map(|(rank, (suggestion, score))| Fix {
+            title: format!(
+                "{} ({})",
+                Message::UseFolder(&suggestion).text(context.locale),
+                context.algorithm.format_score(score)
+            ),
+            edits: vec![edits::replace_string(
+                diagnostic.range,
+                original,
+                &suggestion,
+                context.quote_style,
+            )],
+            is_preferred: rank == 0,
+            score: Some(score),
+            fix_all: false,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let codes: HashSet<&str> = registry().iter().map(|rule| rule.code()).collect();
+        let names: HashSet<&str> = registry().iter().map(|rule| rule.name()).collect();
+        assert_eq!(registry().len(), codes.len());
+        assert_eq!(registry().len(), names.len());
+        assert_eq!(Some("100"), rule("invalid-folder").map(|rule| rule.code()));
+        assert!(rule("unknown").is_none());
+    }
+}
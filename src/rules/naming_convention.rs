@@ -0,0 +1,144 @@
+use super::{Context, Fix, FixContext, Rule};
+use crate::config::Level;
+use crate::edits;
+use crate::i18n::Message;
+use crate::parser::PositionalText;
+use lsp_types::Diagnostic;
+
+/// Entries not matching the configured `naming` pattern, `data` holds the normalized name if a
+/// folder of that name exists.
+pub struct NamingConvention;
+
+impl Rule for NamingConvention {
+    fn code(&self) -> &'static str {
+        "106"
+    }
+
+    fn name(&self) -> &'static str {
+        "naming-convention"
+    }
+
+    fn default_level(&self) -> Level {
+        Level::Warn
+    }
+
+    fn check_entry(&self, entry: &PositionalText, context: &Context) -> Option<Diagnostic> {
+        let naming = context.naming.as_ref()?;
+        if context.config.allow.contains(entry.text) || naming.is_match(entry.text) {
+            return None;
+        }
+        let normalized = normalized_names(entry.text)
+            .into_iter()
+            .find(|name| naming.is_match(name) && (context.is_valid_folder)(name));
+        let message = Message::NamingConvention {
+            name: entry.text,
+            pattern: naming.as_str(),
+        };
+        Some(Diagnostic {
+            data: normalized.map(serde_json::Value::String),
+            ..super::diagnostic(self, entry, context, message.text(context.locale))
+        })
+    }
+
+    fn fixes(&self, diagnostic: &Diagnostic, context: &FixContext) -> Vec<Fix> {
+        let normalized = match diagnostic.data.as_ref().and_then(|data| data.as_str()) {
+            Some(normalized) => normalized,
+            None => return vec![],
+        };
+        let original = edits::text_at(context.source_code, diagnostic.range).unwrap_or("\"");
+        vec![Fix {
+            title: Message::RenameEntry(normalized).text(context.locale),
+            edits: vec![edits::replace_string(
+                diagnostic.range,
+                original,
+                normalized,
+                context.quote_style,
+            )],
+            is_preferred: true,
+            score: None,
+            fix_all: false,
+        }]
+    }
+}
+
+/// `name` in the common naming conventions, kebab-case first. Every segment of a nested path is
+/// converted on its own.
+fn normalized_names(name: &str) -> Vec<String> {
+    let segments: Vec<Vec<String>> = name.split('/').map(words).collect();
+    let styles: [fn(&[String]) -> String; 5] = [
+        |words| words.join("-"),
+        |words| words.join("_"),
+        |words| words.concat(),
+        |words| {
+            let mut words = words.iter();
+            let first = words.next().cloned().unwrap_or_default();
+            first + &words.map(|word| capitalized(word)).collect::<String>()
+        },
+        |words| words.iter().map(|word| capitalized(word)).collect(),
+    ];
+    let mut names: Vec<String> = vec![];
+    for style in styles {
+        let normalized = segments
+            .iter()
+            .map(|words| style(words))
+            .collect::<Vec<_>>()
+            .join("/");
+        if normalized != name && !names.contains(&normalized) {
+            names.push(normalized);
+        }
+    }
+    names
+}
+
+/// Lowercase words of `segment`, split at separators and at the humps of camelCase.
+fn words(segment: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut previous_lowercase = false;
+    for c in segment.chars() {
+        if !c.is_alphanumeric() {
+            words.push(std::mem::take(&mut word));
+            previous_lowercase = false;
+            continue;
+        }
+        if c.is_uppercase() && previous_lowercase {
+            words.push(std::mem::take(&mut word));
+        }
+        previous_lowercase = c.is_lowercase() || c.is_numeric();
+        word.extend(c.to_lowercase());
+    }
+    words.push(word);
+    words.retain(|word| !word.is_empty());
+    words
+}
+
+fn capitalized(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalized_names() {
+        assert_eq!(
+            vec!["my-icons", "my_icons", "myicons", "myIcons", "MyIcons"],
+            normalized_names("My Icons")
+        );
+        assert_eq!(
+            vec![
+                "icons/dark-mode",
+                "icons/darkmode",
+                "icons/darkMode",
+                "Icons/DarkMode"
+            ],
+            normalized_names("icons/dark_mode")
+        );
+        assert_eq!(vec!["dark", "Dark"], normalized_names("DARK"));
+    }
+}
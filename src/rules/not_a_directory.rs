@@ -0,0 +1,25 @@
+use super::{Context, Rule};
+use crate::i18n::Message;
+use crate::parser::PositionalText;
+use lsp_types::Diagnostic;
+
+/// Entries naming a regular file of the suggestions directory rather than a folder.
+pub struct NotADirectory;
+
+impl Rule for NotADirectory {
+    fn code(&self) -> &'static str {
+        "112"
+    }
+
+    fn name(&self) -> &'static str {
+        "not-a-directory"
+    }
+
+    fn check_entry(&self, entry: &PositionalText, context: &Context) -> Option<Diagnostic> {
+        if context.config.allow.contains(entry.text) || !(context.is_file)(entry.text) {
+            return None;
+        }
+        let message = Message::NotADirectory(entry.text).text(context.locale);
+        Some(super::diagnostic(self, entry, context, message))
+    }
+}
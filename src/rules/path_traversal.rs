@@ -0,0 +1,35 @@
+use super::{paths, Context, Fix, FixContext, Rule};
+use crate::i18n::Message;
+use crate::parser::PositionalText;
+use lsp_types::Diagnostic;
+
+/// Entries with `..` segments, which may escape the suggestions directory. `data` holds the
+/// path without them if it stays inside of the suggestions directory.
+pub struct PathTraversal;
+
+impl Rule for PathTraversal {
+    fn code(&self) -> &'static str {
+        "109"
+    }
+
+    fn name(&self) -> &'static str {
+        "path-traversal"
+    }
+
+    fn check_entry(&self, entry: &PositionalText, context: &Context) -> Option<Diagnostic> {
+        let traverses = entry.text.split(['/', '\\']).any(|segment| segment == "..");
+        if context.config.allow.contains(entry.text) || !traverses {
+            return None;
+        }
+        let message = Message::PathTraversal(entry.text).text(context.locale);
+        Some(Diagnostic {
+            data: paths::relative_to_root(entry.text, context.suggestionsdir)
+                .map(serde_json::Value::String),
+            ..super::diagnostic(self, entry, context, message)
+        })
+    }
+
+    fn fixes(&self, diagnostic: &Diagnostic, context: &FixContext) -> Vec<Fix> {
+        paths::relative_fixes(diagnostic, context)
+    }
+}
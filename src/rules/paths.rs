@@ -0,0 +1,90 @@
+//! Shared parts of the rules for entries escaping the suggestions directory.
+
+use super::{Fix, FixContext};
+use crate::edits;
+use crate::i18n::Message;
+use lsp_types::Diagnostic;
+use std::path::{Component, Path, PathBuf};
+
+/// `entry` relative to `root` with `.` and `..` resolved lexically, `None` if it points outside
+/// of `root` or to `root` itself.
+pub fn relative_to_root(entry: &str, root: &str) -> Option<String> {
+    let root = resolved(Path::new(root))?;
+    let path = resolved(&root.join(entry))?;
+    let relative = path.strip_prefix(&root).ok()?;
+    let segments: Vec<&str> = relative
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<_>>()?;
+    (!segments.is_empty()).then(|| segments.join("/"))
+}
+
+/// `path` without `.` and `..` components, `None` if it climbs above its start.
+fn resolved(path: &Path) -> Option<PathBuf> {
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !matches!(
+                    resolved.components().next_back(),
+                    Some(Component::Normal(_))
+                ) {
+                    return None;
+                }
+                resolved.pop();
+            }
+            component => resolved.push(component),
+        }
+    }
+    Some(resolved)
+}
+
+/// Fix replacing the entry of `diagnostic` with its relative equivalent held in `data`.
+pub fn relative_fixes(diagnostic: &Diagnostic, context: &FixContext) -> Vec<Fix> {
+    let relative = match diagnostic.data.as_ref().and_then(|data| data.as_str()) {
+        Some(relative) => relative,
+        None => return vec![],
+    };
+    let original = edits::text_at(context.source_code, diagnostic.range).unwrap_or("\"");
+    vec![Fix {
+        title: Message::UseFolder(relative).text(context.locale),
+        edits: vec![edits::replace_string(
+            diagnostic.range,
+            original,
+            relative,
+            context.quote_style,
+        )],
+        is_preferred: true,
+        score: None,
+        fix_all: true,
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_to_root() {
+        let root = "/project/assets";
+        assert_eq!(
+            Some("icons".into()),
+            relative_to_root("/project/assets/icons", root)
+        );
+        assert_eq!(
+            Some("fonts".into()),
+            relative_to_root("icons/../fonts", root)
+        );
+        assert_eq!(
+            Some("icons/dark".into()),
+            relative_to_root("../assets/./icons/dark", root)
+        );
+        assert_eq!(None, relative_to_root("../public/icons", root));
+        assert_eq!(None, relative_to_root("/etc/passwd", root));
+        assert_eq!(None, relative_to_root("icons/..", root));
+        assert_eq!(None, relative_to_root("../../../../icons", root));
+        assert_eq!(Some("icons".into()), relative_to_root("x/../icons", "."));
+        assert_eq!(None, relative_to_root("../icons", "."));
+    }
+}
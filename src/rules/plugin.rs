@@ -0,0 +1,31 @@
+use super::{Context, Fix, FixContext, Rule};
+use crate::parser::PositionalText;
+use lsp_types::Diagnostic;
+
+/// Problems validator plugins report for otherwise valid entries.
+pub struct Plugin;
+
+impl Rule for Plugin {
+    fn code(&self) -> &'static str {
+        "103"
+    }
+
+    fn name(&self) -> &'static str {
+        "plugin"
+    }
+
+    fn check_entry(&self, entry: &PositionalText, context: &Context) -> Option<Diagnostic> {
+        if context.config.allow.contains(entry.text) {
+            return None;
+        }
+        context.validators.iter().find_map(|validator| {
+            let problem = validator.validate(entry.text)?;
+            let message = format!("{}: {}", validator.name(), problem);
+            Some(super::diagnostic(self, entry, context, message))
+        })
+    }
+
+    fn fixes(&self, diagnostic: &Diagnostic, context: &FixContext) -> Vec<Fix> {
+        super::suggestion_fixes(diagnostic, context)
+    }
+}
@@ -0,0 +1,167 @@
+use super::{Context, Fix, FixContext, Rule};
+use crate::config::Level;
+use crate::diagnostics::MyRange;
+use crate::edits;
+use crate::i18n::Message;
+use crate::parser::{LspParser, PositionalText};
+use lsp_types::{Diagnostic, Range};
+
+/// Arrays whose entries aren't sorted alphabetically, reported on the first entry out of order of
+/// every array.
+pub struct UnsortedEntries;
+
+impl Rule for UnsortedEntries {
+    fn code(&self) -> &'static str {
+        "107"
+    }
+
+    fn name(&self) -> &'static str {
+        "unsorted-entries"
+    }
+
+    fn default_level(&self) -> Level {
+        Level::Off
+    }
+
+    fn check_entries(&self, entries: &[PositionalText], context: &Context) -> Vec<Diagnostic> {
+        super::by_array(entries, context.source_code, context.varname)
+            .into_iter()
+            .filter_map(|entries| {
+                entries
+                    .windows(2)
+                    .find(|pair| sort_key(pair[1].text) < sort_key(pair[0].text))
+            })
+            .map(|pair| Diagnostic {
+                // The fix sorts the whole array as it is when the fix is requested
+                data: None,
+                ..super::diagnostic(
+                    self,
+                    &pair[1],
+                    context,
+                    Message::UnsortedEntries(pair[1].text).text(context.locale),
+                )
+            })
+            .collect()
+    }
+
+    /// Sorts the array of the diagnostic.
+    fn fixes(&self, diagnostic: &Diagnostic, context: &FixContext) -> Vec<Fix> {
+        let all_entries = LspParser::parse_code(context.source_code, context.varname);
+        let entries = super::by_array(&all_entries, context.source_code, context.varname)
+            .into_iter()
+            .find(|entries| {
+                let first = Range::from(MyRange(entries[0].range, context.source_code));
+                let last = Range::from(MyRange(
+                    entries[entries.len() - 1].range,
+                    context.source_code,
+                ));
+                first.start <= diagnostic.range.start && diagnostic.range.start <= last.end
+            })
+            .unwrap_or_default();
+        let mut sorted: Vec<&str> = entries.iter().map(|entry| entry.text).collect();
+        sorted.sort_by_key(|text| sort_key(text));
+
+        // Every entry gets the text of its sorted position, keeping the layout of the array
+        let edits: Vec<_> = entries
+            .iter()
+            .zip(sorted)
+            .filter(|(entry, text)| entry.text != *text)
+            .map(|(entry, text)| {
+                let range = MyRange(entry.range, context.source_code).into();
+                let original = edits::text_at(context.source_code, range).unwrap_or("\"");
+                edits::replace_string(range, original, text, context.quote_style)
+            })
+            .collect();
+        if edits.is_empty() {
+            return vec![];
+        }
+        vec![Fix {
+            title: Message::SortEntries.text(context.locale),
+            edits,
+            is_preferred: true,
+            score: None,
+            fix_all: true,
+        }]
+    }
+}
+
+/// Case-insensitive order, ties broken by the exact text to keep the order total.
+fn sort_key(text: &str) -> (String, &str) {
+    (text.to_lowercase(), text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edits::QuoteStyle;
+    use crate::i18n::Locale;
+    use crate::matcher::Algorithm;
+    use lsp_types::Position;
+
+    fn diagnostic(line: u32, character: u32) -> Diagnostic {
+        let position = Position::new(line, character);
+        Diagnostic {
+            range: Range::new(position, position),
+            ..Diagnostic::default()
+        }
+    }
+
+    #[test]
+    fn test_fixes() {
+        let source_code = r#"export const folders = ["fonts", 'Assets', "icons", "assets"];"#;
+        let context = FixContext {
+            source_code,
+            varname: "folders",
+            locale: Locale::En,
+            quote_style: QuoteStyle::Auto,
+            algorithm: Algorithm::default(),
+            suggestions: &|_| vec![],
+        };
+
+        let fixes = UnsortedEntries.fixes(&diagnostic(0, 33), &context);
+        assert_eq!(1, fixes.len());
+        assert!(fixes[0].fix_all);
+        // The edits don't overlap and the source is a single line, apply them back to front
+        let mut fixed = source_code.to_string();
+        for edit in fixes[0].edits.iter().rev() {
+            let range = edit.range.start.character as usize..edit.range.end.character as usize;
+            fixed.replace_range(range, &edit.new_text);
+        }
+        assert_eq!(
+            r#"export const folders = ["Assets", 'assets', "fonts", "icons"];"#,
+            fixed
+        );
+
+        let sorted = r#"export const folders = ["Assets", "assets", "fonts"];"#;
+        let context = FixContext {
+            source_code: sorted,
+            ..context
+        };
+        assert!(UnsortedEntries
+            .fixes(&diagnostic(0, 34), &context)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_fixes_of_separate_arrays() {
+        let source_code = "function a() { const folders = [\"b\", \"c\"]; }\n\
+                           function b() { const folders = [\"e\", \"d\"]; }";
+        let context = FixContext {
+            source_code,
+            varname: "folders",
+            locale: Locale::En,
+            quote_style: QuoteStyle::Auto,
+            algorithm: Algorithm::default(),
+            suggestions: &|_| vec![],
+        };
+
+        // Only the array of the diagnostic is sorted, without moving names between the arrays
+        let fixes = UnsortedEntries.fixes(&diagnostic(1, 37), &context);
+        let edits: Vec<(u32, &str)> = fixes[0]
+            .edits
+            .iter()
+            .map(|edit| (edit.range.start.line, edit.new_text.as_str()))
+            .collect();
+        assert_eq!(vec![(1, "d"), (1, "e")], edits);
+    }
+}
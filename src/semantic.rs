@@ -0,0 +1,121 @@
+use tower_lsp::lsp_types::{
+    SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokensEdit,
+    SemanticTokensLegend,
+};
+
+/// Entries naming existing folders are highlighted like namespaces.
+pub const TOKEN_TYPES: [SemanticTokenType; 1] = [SemanticTokenType::NAMESPACE];
+pub const TOKEN_MODIFIERS: [SemanticTokenModifier; 1] = [SemanticTokenModifier::DEPRECATED];
+
+pub fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: TOKEN_MODIFIERS.to_vec(),
+    }
+}
+
+/// A highlighted entry on a single line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub line: u32,
+    pub start: u32,
+    pub length: u32,
+    pub deprecated: bool,
+}
+
+/// Encode `tokens` relative to their predecessors, as the protocol transfers them.
+pub fn encode(tokens: &[Token]) -> Vec<SemanticToken> {
+    let mut tokens = tokens.to_vec();
+    tokens.sort_by_key(|token| (token.line, token.start));
+
+    let mut previous = (0, 0);
+    tokens
+        .iter()
+        .map(|token| {
+            let delta_line = token.line - previous.0;
+            let delta_start = if delta_line == 0 {
+                token.start - previous.1
+            } else {
+                token.start
+            };
+            previous = (token.line, token.start);
+            SemanticToken {
+                delta_line,
+                delta_start,
+                length: token.length,
+                token_type: 0,
+                token_modifiers_bitset: u32::from(token.deprecated),
+            }
+        })
+        .collect()
+}
+
+/// Edit turning the `previous` tokens into `tokens`, replacing the run between their common
+/// prefix and suffix. Offsets count integers, five per token.
+pub fn delta(previous: &[SemanticToken], tokens: &[SemanticToken]) -> Vec<SemanticTokensEdit> {
+    let prefix = previous
+        .iter()
+        .zip(tokens)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = previous[prefix..]
+        .iter()
+        .rev()
+        .zip(tokens[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let deleted = previous.len() - prefix - suffix;
+    let inserted = &tokens[prefix..tokens.len() - suffix];
+    if deleted == 0 && inserted.is_empty() {
+        return vec![];
+    }
+    vec![SemanticTokensEdit {
+        start: (prefix * 5) as u32,
+        delete_count: (deleted * 5) as u32,
+        data: (!inserted.is_empty()).then(|| inserted.to_vec()),
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(line: u32, start: u32, deprecated: bool) -> Token {
+        Token {
+            line,
+            start,
+            length: 5,
+            deprecated,
+        }
+    }
+
+    #[test]
+    fn test_encode() {
+        let encoded = encode(&[token(2, 4, false), token(0, 10, true), token(0, 3, false)]);
+
+        let relative: Vec<(u32, u32, u32)> = encoded
+            .iter()
+            .map(|t| (t.delta_line, t.delta_start, t.token_modifiers_bitset))
+            .collect();
+        assert_eq!(vec![(0, 3, 0), (0, 7, 1), (2, 4, 0)], relative);
+    }
+
+    #[test]
+    fn test_delta() {
+        let previous = encode(&[token(0, 1, false), token(1, 1, false), token(2, 1, false)]);
+        let tokens = encode(&[token(0, 1, false), token(1, 1, true), token(2, 1, false)]);
+
+        let edits = delta(&previous, &tokens);
+        assert_eq!(1, edits.len());
+        assert_eq!(5, edits[0].start);
+        assert_eq!(5, edits[0].delete_count);
+        assert_eq!(Some(vec![tokens[1]]), edits[0].data);
+
+        assert!(delta(&tokens, &tokens).is_empty());
+        let removed = delta(&previous, &previous[..2]);
+        assert_eq!(10, removed[0].start);
+        assert_eq!(5, removed[0].delete_count);
+        assert_eq!(None, removed[0].data);
+    }
+}
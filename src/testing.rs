@@ -0,0 +1,1447 @@
+//! In-process harness driving the language server over an in-memory stream, so end-to-end
+//! behavior can be tested without an editor.
+//!
+//! Available to other crates with the `test-harness` feature.
+
+use crate::panic::CatchPanic;
+use crate::{Backend, CliArgs};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, DuplexStream};
+use tower_lsp::{LspService, Server};
+
+/// Plays the editor's part of the protocol against a server running on the current tokio runtime.
+pub struct TestClient {
+    writer: DuplexStream,
+    reader: BufReader<DuplexStream>,
+    next_id: i64,
+    notifications: VecDeque<Value>,
+}
+
+impl TestClient {
+    pub fn start(args: CliArgs) -> Self {
+        let (writer, server_stdin) = tokio::io::duplex(64 * 1024);
+        let (server_stdout, reader) = tokio::io::duplex(64 * 1024);
+
+        let (service, socket) = LspService::build(|client| Backend::new(client, args))
+            .custom_method("tsm/status", Backend::status)
+            .custom_method("tsm/previewFolder", Backend::preview_folder)
+            .custom_method("tsm/findAllInvalid", Backend::find_all_invalid)
+            .finish();
+        let client = service.inner().client();
+        let service = CatchPanic::new(service, client);
+        tokio::spawn(Server::new(server_stdin, server_stdout, socket).serve(service));
+
+        Self {
+            writer,
+            reader: BufReader::new(reader),
+            next_id: 0,
+            notifications: VecDeque::new(),
+        }
+    }
+
+    async fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        let frame = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+        self.writer
+            .write_all(frame.as_bytes())
+            .await
+            .expect("server closed the connection");
+    }
+
+    async fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            self.reader
+                .read_line(&mut line)
+                .await
+                .expect("failed to read message header");
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Content-Length: ") {
+                length = value.parse().expect("invalid Content-Length header");
+            }
+        }
+
+        let mut body = vec![0; length];
+        self.reader
+            .read_exact(&mut body)
+            .await
+            .expect("failed to read message body");
+        serde_json::from_slice(&body).expect("invalid JSON message")
+    }
+
+    /// Next message from the server, answering server-to-client requests with `null` on the way.
+    async fn next_message(&mut self) -> Value {
+        loop {
+            let message = self.receive().await;
+            match (message.get("id"), message.get("method")) {
+                (Some(id), Some(_)) => {
+                    let id = id.clone();
+                    self.send(json!({ "jsonrpc": "2.0", "id": id, "result": null }))
+                        .await;
+                }
+                _ => return message,
+            }
+        }
+    }
+
+    /// Send a request and wait for its result, notifications arriving meanwhile are queued.
+    /// `Value::Null` sends no params, as methods without params reject `null`.
+    pub async fn request(&mut self, method: &str, params: Value) -> Result<Value, Value> {
+        self.next_id += 1;
+        let id = json!(self.next_id);
+        let mut message = json!({ "jsonrpc": "2.0", "id": id, "method": method });
+        if !params.is_null() {
+            message["params"] = params;
+        }
+        self.send(message).await;
+
+        loop {
+            let message = self.next_message().await;
+            if message.get("id") == Some(&id) {
+                return match message.get("error") {
+                    Some(error) => Err(error.clone()),
+                    None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+                };
+            }
+            self.notifications.push_back(message);
+        }
+    }
+
+    /// Send a notification, without params for `Value::Null` like [`request`](Self::request).
+    pub async fn notify(&mut self, method: &str, params: Value) {
+        let mut message = json!({ "jsonrpc": "2.0", "method": method });
+        if !params.is_null() {
+            message["params"] = params;
+        }
+        self.send(message).await;
+    }
+
+    /// Params of the next notification with the given method.
+    pub async fn notification(&mut self, method: &str) -> Value {
+        let queued = self
+            .notifications
+            .iter()
+            .position(|message| message["method"] == method);
+        if let Some(message) = queued.and_then(|i| self.notifications.remove(i)) {
+            return message["params"].clone();
+        }
+
+        loop {
+            let message = self.next_message().await;
+            if message["method"] == method {
+                return message["params"].clone();
+            }
+            self.notifications.push_back(message);
+        }
+    }
+
+    /// Run the initialize handshake and wait until the folder index is built.
+    pub async fn initialize(&mut self, capabilities: Value) -> Value {
+        self.initialize_with(json!({ "capabilities": capabilities }))
+            .await
+    }
+
+    /// Like [`initialize`](Self::initialize), with `params` like the `capabilities`, `rootUri`
+    /// or `initializationOptions` replacing the defaults.
+    pub async fn initialize_with(&mut self, params: Value) -> Value {
+        let mut request = json!({ "processId": null, "rootUri": null, "capabilities": {} });
+        if let (Some(request), Some(params)) = (request.as_object_mut(), params.as_object()) {
+            request.extend(params.clone());
+        }
+        let result = self
+            .request("initialize", request)
+            .await
+            .expect("initialize failed");
+        self.notify("initialized", json!({})).await;
+
+        loop {
+            let log = self.notification("window/logMessage").await;
+            if log["message"]
+                .as_str()
+                .is_some_and(|message| message.starts_with("Indexed"))
+            {
+                break;
+            }
+        }
+
+        result
+    }
+
+    pub async fn did_open(&mut self, uri: &str, language_id: &str, text: &str) {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": uri, "languageId": language_id, "version": 1, "text": text }
+            }),
+        )
+        .await;
+    }
+
+    pub async fn shutdown(mut self) {
+        self.request("shutdown", Value::Null)
+            .await
+            .expect("shutdown failed");
+        self.notify("exit", Value::Null).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    const URI: &str = "file:///project/app.ts";
+
+    fn suggestionsdir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tsm-harness-{}-{}", name, std::process::id()));
+        fs::create_dir_all(dir.join("assets")).unwrap();
+        fs::create_dir_all(dir.join("fonts")).unwrap();
+        dir
+    }
+
+    async fn start(dir: &Path) -> TestClient {
+        let args = CliArgs::parse_from([
+            "tsm-language-server",
+            "--suggestionsdir",
+            dir.to_str().unwrap(),
+        ]);
+        let mut client = TestClient::start(args);
+        client
+            .initialize(json!({ "textDocument": { "publishDiagnostics": {} } }))
+            .await;
+        client
+    }
+
+    #[tokio::test]
+    async fn test_diagnostics_and_code_action() {
+        let dir = suggestionsdir("diagnostics");
+        let mut client = start(&dir).await;
+
+        client
+            .did_open(
+                URI,
+                "typescript",
+                r#"export const folders = ["asets", "fonts"];"#,
+            )
+            .await;
+        let published = client.notification("textDocument/publishDiagnostics").await;
+        let diagnostics = published["diagnostics"].as_array().unwrap().clone();
+        assert_eq!(1, diagnostics.len());
+        assert_eq!("asets", diagnostics[0]["data"]);
+        assert!(diagnostics[0]["message"]
+            .as_str()
+            .unwrap()
+            .ends_with(", did you mean 'assets'?"));
+
+        let actions = client
+            .request(
+                "textDocument/codeAction",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "range": diagnostics[0]["range"],
+                    "context": { "diagnostics": diagnostics },
+                }),
+            )
+            .await
+            .unwrap();
+        let titles: Vec<&str> = actions
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|action| action["title"].as_str())
+            .collect();
+        // The best match comes first with its score, editors apply it automatically
+        assert!(titles[0].starts_with("Use folder assets ("));
+        assert_eq!(json!(true), actions[0]["isPreferred"]);
+
+        let use_assets = &actions[0];
+        assert_eq!(
+            json!([{
+                "range": {
+                    "start": { "line": 0, "character": 27 },
+                    "end": { "line": 0, "character": 27 },
+                },
+                "newText": "s",
+            }]),
+            use_assets["edit"]["changes"][URI]
+        );
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bulk_code_action() {
+        let dir = suggestionsdir("bulk");
+        let mut client = start(&dir).await;
+
+        client
+            .did_open(
+                URI,
+                "typescript",
+                r#"export const folders = ["asets", "fnts"];"#,
+            )
+            .await;
+        let published = client.notification("textDocument/publishDiagnostics").await;
+        let diagnostics = published["diagnostics"].as_array().unwrap().clone();
+        assert_eq!(2, diagnostics.len());
+        // The summary of the run after indexing, without documents, may come first
+        let summary = loop {
+            let summary = client.notification("tsm/summary").await;
+            if summary["files"] != 0 {
+                break summary;
+            }
+        };
+        assert_eq!(
+            json!({ "files": 1, "invalidEntries": 2, "duplicates": 0 }),
+            summary
+        );
+
+        let actions = client
+            .request(
+                "textDocument/codeAction",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "range": {
+                        "start": { "line": 0, "character": 0 },
+                        "end": { "line": 0, "character": 41 },
+                    },
+                    "context": { "diagnostics": diagnostics },
+                }),
+            )
+            .await
+            .unwrap();
+        let bulk = actions
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|action| action["title"] == "Replace 2 invalid entries with best matches")
+            .unwrap();
+        assert_eq!(2, bulk["edit"]["changes"][URI].as_array().unwrap().len());
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_inline_config() {
+        let dir = suggestionsdir("inline");
+        let mut client = start(&dir).await;
+
+        client
+            .did_open(
+                URI,
+                "typescript",
+                r#"// tsm-varname: dirs
+export const dirs = ["asets"];
+export const folders = ["x"];"#,
+            )
+            .await;
+        let published = client.notification("textDocument/publishDiagnostics").await;
+        let diagnostics = published["diagnostics"].as_array().unwrap();
+        assert_eq!(1, diagnostics.len());
+        assert_eq!("asets", diagnostics[0]["data"]);
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_labels() {
+        let dir = suggestionsdir("labels");
+        let args = CliArgs::parse_from([
+            "tsm-language-server",
+            "--suggestionsdir",
+            dir.to_str().unwrap(),
+        ]);
+        let mut client = TestClient::start(args);
+        client
+            .initialize_with(json!({
+                "capabilities": { "textDocument": { "publishDiagnostics": {} } },
+                "initializationOptions": {
+                    "labels": { "kind": "module", "detail": "Module", "noun": "module" },
+                },
+            }))
+            .await;
+
+        client
+            .did_open(URI, "typescript", r#"export const folders = ["x", "a"];"#)
+            .await;
+        let published = client.notification("textDocument/publishDiagnostics").await;
+        let message = published["diagnostics"][0]["message"].as_str().unwrap();
+        assert!(message.starts_with("'x' is not a valid module, valid modules are those in"));
+
+        let completion = client
+            .request(
+                "textDocument/completion",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "position": { "line": 0, "character": 31 },
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!("assets", completion["items"][0]["label"]);
+        assert_eq!("Module", completion["items"][0]["detail"]);
+        assert_eq!(json!(9), completion["items"][0]["kind"]);
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_directories() {
+        let dir = suggestionsdir("directories");
+        let templates = dir.join("templates");
+        fs::create_dir_all(templates.join("card")).unwrap();
+        let args = CliArgs::parse_from([
+            "tsm-language-server",
+            "--suggestionsdir",
+            dir.to_str().unwrap(),
+        ]);
+        let mut client = TestClient::start(args);
+        client
+            .initialize_with(json!({
+                "capabilities": { "textDocument": { "publishDiagnostics": {} } },
+                "initializationOptions": {
+                    "directories": { "templates": templates.to_str().unwrap() },
+                },
+            }))
+            .await;
+
+        client
+            .did_open(
+                URI,
+                "typescript",
+                r#"export const folders = ["assets", "card"];
+export const templates = ["card", "assets"];"#,
+            )
+            .await;
+        let published = client.notification("textDocument/publishDiagnostics").await;
+        let mut invalid: Vec<(u64, &str)> = published["diagnostics"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|d| {
+                let line = d["range"]["start"]["line"].as_u64().unwrap();
+                (line, d["data"].as_str().unwrap())
+            })
+            .collect();
+        invalid.sort();
+        assert_eq!(vec![(0, "card"), (1, "assets")], invalid);
+
+        let completion = client
+            .request(
+                "textDocument/completion",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "position": { "line": 1, "character": 28 },
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!("card", completion["items"][0]["label"]);
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_missing_suggestionsdir() {
+        let dir = std::env::temp_dir().join(format!("tsm-harness-missing-{}", std::process::id()));
+        let mut client = start(&dir).await;
+        let message = client.notification("window/showMessage").await;
+        assert_eq!(2, message["type"]);
+        assert!(message["message"]
+            .as_str()
+            .unwrap()
+            .contains("doesn't exist"));
+        let status = client.request("tsm/status", Value::Null).await.unwrap();
+        assert_eq!(json!("degraded"), status["health"]["state"]);
+        assert_eq!(message["message"], status["health"]["reason"]);
+
+        client
+            .did_open(URI, "typescript", r#"export const folders = ["a", "b"];"#)
+            .await;
+        let published = client.notification("textDocument/publishDiagnostics").await;
+        let diagnostics = published["diagnostics"].as_array().unwrap();
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(json!(13), diagnostics[0]["range"]["start"]["character"]);
+
+        client.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_unopened_document() {
+        let dir = suggestionsdir("unopened");
+        let path = dir.join("app.ts");
+        fs::write(&path, r#"export const folders = ["assets", "x"];"#).unwrap();
+        let uri = tower_lsp::lsp_types::Url::from_file_path(&path).unwrap();
+        let mut client = start(&dir).await;
+
+        let lenses = client
+            .request(
+                "textDocument/codeLens",
+                json!({ "textDocument": { "uri": uri } }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(1, lenses.as_array().unwrap().len());
+        assert_eq!(Value::Null, lenses[0]["command"]);
+        fs::write(dir.join("assets/logo.svg"), "<svg/>").unwrap();
+        let lens = client
+            .request("codeLens/resolve", lenses[0].clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            "Reveal folder assets (1 entries, 6 B)",
+            lens["command"]["title"]
+        );
+        assert_eq!("tsm.revealFolder", lens["command"]["command"]);
+
+        // Names escaping the suggestions directory aren't opened
+        for name in ["..", "/etc"] {
+            let revealed = client
+                .request(
+                    "workspace/executeCommand",
+                    json!({ "command": "tsm.revealFolder", "arguments": [name] }),
+                )
+                .await;
+            assert_eq!(json!(-32602), revealed.unwrap_err()["code"]);
+        }
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_case_mismatch() {
+        let dir = suggestionsdir("case-mismatch");
+        let mut client = start(&dir).await;
+
+        client
+            .did_open(URI, "typescript", r#"export const folders = ["Assets"];"#)
+            .await;
+        let published = client.notification("textDocument/publishDiagnostics").await;
+        let diagnostics = published["diagnostics"].as_array().unwrap().clone();
+        assert_eq!(1, diagnostics.len());
+        assert_eq!("111", diagnostics[0]["code"]);
+        assert_eq!("assets", diagnostics[0]["data"]);
+
+        let actions = client
+            .request(
+                "textDocument/codeAction",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "range": diagnostics[0]["range"],
+                    "context": { "diagnostics": diagnostics },
+                }),
+            )
+            .await
+            .unwrap();
+        // Ranked above the suggestions, which don't repeat it. source.fixAll applies it as well
+        let actions: Vec<Value> = actions
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|action| action["kind"] == "quickfix")
+            .cloned()
+            .collect();
+        assert_eq!("Fix casing to 'assets'", actions[0]["title"]);
+        assert_eq!(json!(true), actions[0]["isPreferred"]);
+        assert_eq!(
+            json!([{
+                "range": {
+                    "start": { "line": 0, "character": 25 },
+                    "end": { "line": 0, "character": 26 },
+                },
+                "newText": "a",
+            }]),
+            actions[0]["edit"]["changes"][URI]
+        );
+        assert!(actions[1..].iter().all(|action| {
+            action["edit"]["changes"][URI] != actions[0]["edit"]["changes"][URI]
+                && action["isPreferred"] != json!(true)
+        }));
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sync_folders_dry_run() {
+        let dir = suggestionsdir("sync-dry-run");
+        let mut client = start(&dir).await;
+
+        client
+            .did_open(
+                URI,
+                "typescript",
+                r#"export const folders = ["assets", "gone"];"#,
+            )
+            .await;
+        let plan = client
+            .request(
+                "workspace/executeCommand",
+                json!({
+                    "command": "tsm.syncFolders",
+                    "arguments": [URI, { "dryRun": true }],
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(json!(["fonts"]), plan["added"]);
+        assert_eq!(json!(["gone"]), plan["removed"]);
+        assert!(!plan["edit"]["changes"][URI].as_array().unwrap().is_empty());
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_create_all_missing_dry_run() {
+        let dir = suggestionsdir("create-all-missing");
+        let mut client = start(&dir).await;
+
+        client
+            .did_open(
+                URI,
+                "typescript",
+                r#"export const folders = ["assets", "new", "new", "../outside", ""];"#,
+            )
+            .await;
+        let plan = client
+            .request(
+                "workspace/executeCommand",
+                json!({
+                    "command": "tsm.createAllMissing",
+                    "arguments": [URI, { "dryRun": true }],
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(json!(["new"]), plan["names"]);
+        let folders = plan["folders"].as_array().unwrap();
+        assert_eq!(1, folders.len());
+        assert!(folders[0].as_str().unwrap().ends_with("new"));
+        assert!(!dir.join("new").exists());
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_create_folder() {
+        let dir = suggestionsdir("create-folder");
+        let mut client = start(&dir).await;
+
+        client
+            .did_open(URI, "typescript", r#"export const folders = ["new"];"#)
+            .await;
+        let plan = client
+            .request(
+                "workspace/executeCommand",
+                json!({
+                    "command": "tsm.createFolder",
+                    "arguments": ["new", URI, { "dryRun": true }],
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(json!("new"), plan["name"]);
+        assert!(plan["folder"].as_str().unwrap().ends_with("new"));
+        assert!(!dir.join("new").exists());
+
+        client
+            .request(
+                "workspace/executeCommand",
+                json!({ "command": "tsm.createFolder", "arguments": ["new", URI] }),
+            )
+            .await
+            .unwrap();
+        assert!(dir.join("new").is_dir());
+
+        let escaping = client
+            .request(
+                "workspace/executeCommand",
+                json!({ "command": "tsm.createFolder", "arguments": ["../outside", URI] }),
+            )
+            .await;
+        assert!(escaping.is_err());
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_find_all_invalid() {
+        let dir = suggestionsdir("find-all-invalid");
+        let path = dir.join("app.ts");
+        fs::write(&path, r#"export const folders = ["assets", "x"];"#).unwrap();
+        let uri = tower_lsp::lsp_types::Url::from_file_path(&path).unwrap();
+        let args = CliArgs::parse_from([
+            "tsm-language-server",
+            "--suggestionsdir",
+            dir.to_str().unwrap(),
+        ]);
+        let mut client = TestClient::start(args);
+        client
+            .initialize_with(json!({
+                "rootUri": tower_lsp::lsp_types::Url::from_file_path(&dir).unwrap(),
+            }))
+            .await;
+
+        let expected = json!([{
+            "uri": uri,
+            "range": {
+                "start": { "line": 0, "character": 34 },
+                "end": { "line": 0, "character": 37 },
+            },
+        }]);
+        let locations = client
+            .request("tsm/findAllInvalid", Value::Null)
+            .await
+            .unwrap();
+        assert_eq!(expected, locations);
+        let executed = client
+            .request(
+                "workspace/executeCommand",
+                json!({ "command": "tsm.findAllInvalid", "arguments": [] }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(expected, executed);
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_project() {
+        let dir = suggestionsdir("project");
+        let app = dir.join("app.ts");
+        let other = dir.join("other.ts");
+        fs::write(&app, r#"export const folders = ["assets", "x"];"#).unwrap();
+        fs::write(&other, r#"const folders = ["fonts", "assets", "y"];"#).unwrap();
+        let app = tower_lsp::lsp_types::Url::from_file_path(&app).unwrap();
+        let other = tower_lsp::lsp_types::Url::from_file_path(&other).unwrap();
+        let args = CliArgs::parse_from([
+            "tsm-language-server",
+            "--suggestionsdir",
+            dir.to_str().unwrap(),
+        ]);
+        let mut client = TestClient::start(args);
+        client
+            .initialize_with(json!({
+                "rootUri": tower_lsp::lsp_types::Url::from_file_path(&dir).unwrap(),
+                "capabilities": { "textDocument": { "diagnostic": {} } },
+            }))
+            .await;
+        client
+            .did_open(
+                app.as_str(),
+                "typescript",
+                r#"export const folders = ["assets", "x"];"#,
+            )
+            .await;
+
+        let position = json!({
+            "textDocument": { "uri": app },
+            "position": { "line": 0, "character": 27 },
+        });
+        let references = client
+            .request(
+                "textDocument/references",
+                json!({
+                    "textDocument": { "uri": app },
+                    "position": { "line": 0, "character": 27 },
+                    "context": { "includeDeclaration": true },
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            json!([
+                {
+                    "uri": app,
+                    "range": {
+                        "start": { "line": 0, "character": 24 },
+                        "end": { "line": 0, "character": 32 },
+                    },
+                },
+                {
+                    "uri": other,
+                    "range": {
+                        "start": { "line": 0, "character": 26 },
+                        "end": { "line": 0, "character": 34 },
+                    },
+                },
+            ]),
+            references
+        );
+
+        let prepared = client
+            .request("textDocument/prepareRename", position)
+            .await
+            .unwrap();
+        assert_eq!("assets", prepared["placeholder"]);
+        let edit = client
+            .request(
+                "textDocument/rename",
+                json!({
+                    "textDocument": { "uri": app },
+                    "position": { "line": 0, "character": 27 },
+                    "newName": "icons",
+                }),
+            )
+            .await
+            .unwrap();
+        let replace = |start: u32, end: u32| {
+            json!([{
+                "range": {
+                    "start": { "line": 0, "character": start },
+                    "end": { "line": 0, "character": end },
+                },
+                "newText": "icon",
+            }])
+        };
+        assert_eq!(replace(25, 30), edit["changes"][app.as_str()]);
+        assert_eq!(replace(27, 32), edit["changes"][other.as_str()]);
+
+        let symbols = client
+            .request("workspace/symbol", json!({ "query": "ASS" }))
+            .await
+            .unwrap();
+        assert_eq!(2, symbols.as_array().unwrap().len());
+        assert_eq!("folders", symbols[0]["containerName"]);
+
+        // The opened document is pulled on its own
+        let report = client
+            .request("workspace/diagnostic", json!({ "previousResultIds": [] }))
+            .await
+            .unwrap();
+        let items = report["items"].as_array().unwrap();
+        assert_eq!(1, items.len());
+        assert_eq!(json!(other), items[0]["uri"]);
+        assert_eq!("full", items[0]["kind"]);
+        assert_eq!(
+            json!({ "line": 0, "character": 36 }),
+            items[0]["items"][0]["range"]["start"]
+        );
+        let unchanged = client
+            .request(
+                "workspace/diagnostic",
+                json!({
+                    "previousResultIds": [{ "uri": other, "value": items[0]["resultId"] }],
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!("unchanged", unchanged["items"][0]["kind"]);
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_project_watched_files() {
+        let dir = suggestionsdir("project-watched");
+        let app = dir.join("src").join("app.ts");
+        fs::create_dir_all(app.parent().unwrap()).unwrap();
+        fs::write(&app, r#"const folders = ["assets"];"#).unwrap();
+        let args = CliArgs::parse_from([
+            "tsm-language-server",
+            "--suggestionsdir",
+            dir.to_str().unwrap(),
+        ]);
+        let mut client = TestClient::start(args);
+        client
+            .initialize_with(json!({
+                "rootUri": tower_lsp::lsp_types::Url::from_file_path(&dir).unwrap(),
+            }))
+            .await;
+        async fn symbols(client: &mut TestClient) -> usize {
+            let symbols = client
+                .request("workspace/symbol", json!({ "query": "assets" }))
+                .await
+                .unwrap();
+            symbols.as_array().unwrap().len()
+        }
+        assert_eq!(1, symbols(&mut client).await);
+
+        let other = dir.join("other.ts");
+        fs::write(&other, r#"const folders = ["assets", "fonts"];"#).unwrap();
+        let other = tower_lsp::lsp_types::Url::from_file_path(&other).unwrap();
+        client
+            .notify(
+                "workspace/didChangeWatchedFiles",
+                json!({ "changes": [{ "uri": other, "type": 1 }] }),
+            )
+            .await;
+        assert_eq!(2, symbols(&mut client).await);
+
+        // Deleting the folder removes the files below it
+        fs::remove_dir_all(dir.join("src")).unwrap();
+        let src = tower_lsp::lsp_types::Url::from_file_path(dir.join("src")).unwrap();
+        client
+            .notify(
+                "workspace/didChangeWatchedFiles",
+                json!({ "changes": [{ "uri": src, "type": 3 }] }),
+            )
+            .await;
+        assert_eq!(1, symbols(&mut client).await);
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_relative_to_document() {
+        let root =
+            std::env::temp_dir().join(format!("tsm-harness-monorepo-{}", std::process::id()));
+        fs::create_dir_all(root.join("packages/web/public/logo")).unwrap();
+        fs::create_dir_all(root.join("packages/docs/public/fonts")).unwrap();
+
+        let args = CliArgs::parse_from([
+            "tsm-language-server",
+            "--suggestionsdir",
+            "public",
+            "--relative-to-document",
+        ]);
+        let mut client = TestClient::start(args);
+        client
+            .initialize(json!({ "textDocument": { "publishDiagnostics": {} } }))
+            .await;
+
+        for (package, invalid) in [("web", "fonts"), ("docs", "logo")] {
+            let path = root.join("packages").join(package).join("app.ts");
+            let uri = tower_lsp::lsp_types::Url::from_file_path(path).unwrap();
+            client
+                .did_open(
+                    uri.as_str(),
+                    "typescript",
+                    r#"export const folders = ["logo", "fonts"];"#,
+                )
+                .await;
+            let published = client.notification("textDocument/publishDiagnostics").await;
+            let diagnostics = published["diagnostics"].as_array().unwrap();
+            assert_eq!(1, diagnostics.len(), "{}", package);
+            assert_eq!(invalid, diagnostics[0]["data"]);
+        }
+
+        client.shutdown().await;
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unknown_language() {
+        let dir = suggestionsdir("unknown-language");
+        let mut client = start(&dir).await;
+
+        // Plugins open documents without a language id, the content tells
+        client
+            .did_open(
+                "untitled:Untitled-2",
+                "",
+                "#!/usr/bin/env -S deno run\nconst other = [\"asets\"];",
+            )
+            .await;
+        let published = client.notification("textDocument/publishDiagnostics").await;
+        assert_eq!("untitled:Untitled-2", published["uri"]);
+        assert_eq!(json!([]), published["diagnostics"]);
+
+        client
+            .did_open(
+                "untitled:Untitled-3",
+                "plaintext",
+                r#"export const folders = ["asets"];"#,
+            )
+            .await;
+        let published = client.notification("textDocument/publishDiagnostics").await;
+        assert_eq!("untitled:Untitled-3", published["uri"]);
+        assert_eq!(1, published["diagnostics"].as_array().unwrap().len());
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_untitled_buffer() {
+        let dir = suggestionsdir("untitled");
+        let mut client = start(&dir).await;
+        let uri = "untitled:Untitled-1";
+
+        client
+            .did_open(uri, "typescript", r#"export const folders = ["asets"];"#)
+            .await;
+        let published = client.notification("textDocument/publishDiagnostics").await;
+        assert_eq!(uri, published["uri"]);
+        let diagnostics = published["diagnostics"].as_array().unwrap().clone();
+        assert_eq!(1, diagnostics.len());
+
+        client
+            .notify(
+                "textDocument/didChange",
+                json!({
+                    "textDocument": { "uri": uri, "version": 2 },
+                    "contentChanges": [{ "text": r#"export const folders = ["assets"];"# }],
+                }),
+            )
+            .await;
+        let published = client.notification("textDocument/publishDiagnostics").await;
+        assert_eq!(uri, published["uri"]);
+        assert_eq!(json!([]), published["diagnostics"]);
+
+        let actions = client
+            .request(
+                "textDocument/codeAction",
+                json!({
+                    "textDocument": { "uri": uri },
+                    "range": diagnostics[0]["range"],
+                    "context": { "diagnostics": diagnostics },
+                }),
+            )
+            .await
+            .unwrap();
+        let use_assets = actions
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|action| {
+                action["title"]
+                    .as_str()
+                    .is_some_and(|title| title.starts_with("Use folder assets ("))
+            })
+            .unwrap();
+        assert!(use_assets["edit"]["changes"][uri].is_array());
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let dir = suggestionsdir("shutdown");
+        let mut client = start(&dir).await;
+
+        assert_eq!(
+            Ok(Value::Null),
+            client.request("shutdown", Value::Null).await
+        );
+        let hover = client
+            .request(
+                "textDocument/hover",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "position": { "line": 0, "character": 0 },
+                }),
+            )
+            .await;
+        assert_eq!(json!(-32600), hover.unwrap_err()["code"]);
+
+        client.notify("exit", Value::Null).await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_status() {
+        let dir = suggestionsdir("status");
+        let mut client = start(&dir).await;
+
+        client
+            .notify(
+                "workspace/didChangeConfiguration",
+                json!({ "settings": { "tsm": { "deny": ["fonts"], "completion": true } } }),
+            )
+            .await;
+        let status = client.request("tsm/status", Value::Null).await.unwrap();
+        let settings = status["settings"].as_array().unwrap();
+        let setting = |name: &str| {
+            settings
+                .iter()
+                .find(|setting| setting["name"] == name)
+                .cloned()
+                .unwrap()
+        };
+        assert_eq!(json!(r#"["fonts"]"#), setting("deny")["value"]);
+        assert_eq!(
+            json!("workspace/didChangeConfiguration"),
+            setting("deny")["source"]
+        );
+        assert_eq!(json!("default"), setting("allow")["source"]);
+        assert!(!settings
+            .iter()
+            .any(|setting| setting["name"] == "completion"));
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_preview_folder() {
+        let dir = suggestionsdir("preview");
+        fs::write(dir.join("assets").join("logo.svg"), "<svg/>").unwrap();
+        fs::create_dir_all(dir.join("assets").join("icons")).unwrap();
+        let mut client = start(&dir).await;
+
+        let page = client
+            .request("tsm/previewFolder", json!({ "name": "assets", "limit": 1 }))
+            .await
+            .unwrap();
+        assert_eq!(json!(2), page["total"]);
+        assert_eq!(json!(1), page["nextOffset"]);
+        assert_eq!(json!("icons"), page["entries"][0]["name"]);
+        assert_eq!(json!(true), page["entries"][0]["isDirectory"]);
+
+        let page = client
+            .request(
+                "tsm/previewFolder",
+                json!({ "name": "assets", "offset": 1, "limit": 1 }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(json!(null), page["nextOffset"]);
+        assert_eq!(json!("logo.svg"), page["entries"][0]["name"]);
+        assert_eq!(json!(6), page["entries"][0]["size"]);
+
+        let missing = client
+            .request("tsm/previewFolder", json!({ "name": "missing" }))
+            .await;
+        assert_eq!(json!(-32602), missing.unwrap_err()["code"]);
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pull_diagnostics() {
+        let dir = suggestionsdir("pull");
+        let args = CliArgs::parse_from([
+            "tsm-language-server",
+            "--suggestionsdir",
+            dir.to_str().unwrap(),
+        ]);
+        let mut client = TestClient::start(args);
+        let result = client
+            .initialize(json!({
+                "textDocument": { "publishDiagnostics": {}, "diagnostic": {} },
+                "workspace": { "diagnostic": { "refreshSupport": true } },
+            }))
+            .await;
+        assert_eq!(
+            "tsm",
+            result["capabilities"]["diagnosticProvider"]["identifier"]
+        );
+
+        client
+            .did_open(URI, "typescript", r#"export const folders = ["asets"];"#)
+            .await;
+        let report = client
+            .request(
+                "textDocument/diagnostic",
+                json!({ "textDocument": { "uri": URI } }),
+            )
+            .await
+            .unwrap();
+        assert_eq!("full", report["kind"]);
+        assert_eq!("asets", report["items"][0]["data"]);
+
+        let unchanged = client
+            .request(
+                "textDocument/diagnostic",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "previousResultId": report["resultId"],
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!("unchanged", unchanged["kind"]);
+        assert_eq!(report["resultId"], unchanged["resultId"]);
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_semantic_tokens_delta() {
+        let dir = suggestionsdir("semantic");
+        let mut client = start(&dir).await;
+
+        client
+            .did_open(
+                URI,
+                "typescript",
+                r#"export const folders = ["assets", "x"];"#,
+            )
+            .await;
+        let full = client
+            .request(
+                "textDocument/semanticTokens/full",
+                json!({ "textDocument": { "uri": URI } }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(json!([0, 24, 8, 0, 0]), full["data"]);
+
+        client
+            .notify(
+                "textDocument/didChange",
+                json!({
+                    "textDocument": { "uri": URI, "version": 2 },
+                    "contentChanges": [{ "text": r#"export const folders = ["assets", "fonts"];"# }],
+                }),
+            )
+            .await;
+        let delta = client
+            .request(
+                "textDocument/semanticTokens/full/delta",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "previousResultId": full["resultId"],
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            json!([{ "start": 5, "deleteCount": 0, "data": [0, 10, 7, 0, 0] }]),
+            delta["edits"]
+        );
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_inlay_hint_resolve() {
+        let dir = suggestionsdir("inlay");
+        fs::write(dir.join("assets/logo.svg"), "").unwrap();
+        let args = CliArgs::parse_from([
+            "tsm-language-server",
+            "--suggestionsdir",
+            dir.to_str().unwrap(),
+        ]);
+        let mut client = TestClient::start(args);
+        client
+            .initialize(json!({
+                "textDocument": {
+                    "publishDiagnostics": {},
+                    "inlayHint": { "resolveSupport": { "properties": ["tooltip"] } },
+                }
+            }))
+            .await;
+
+        client
+            .did_open(
+                URI,
+                "typescript",
+                r#"export const folders = ["assets", "x"];"#,
+            )
+            .await;
+        let hints = client
+            .request(
+                "textDocument/inlayHint",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "range": {
+                        "start": { "line": 0, "character": 0 },
+                        "end": { "line": 1, "character": 0 },
+                    },
+                }),
+            )
+            .await
+            .unwrap();
+        let hints = hints.as_array().unwrap();
+        assert_eq!(1, hints.len());
+        assert_eq!(json!({ "line": 0, "character": 32 }), hints[0]["position"]);
+        assert!(hints[0].get("tooltip").is_none());
+
+        let resolved = client
+            .request("inlayHint/resolve", hints[0].clone())
+            .await
+            .unwrap();
+        assert_eq!("1 entries: logo.svg", resolved["tooltip"]);
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_virtual_file_system_completion() {
+        let dir = suggestionsdir("vfs");
+        let mut client = start(&dir).await;
+        let uri = "vscode-vfs://github/owner/repo/src/app.ts";
+
+        client
+            .did_open(uri, "typescript", r#"export const folders = ["a"];"#)
+            .await;
+        let completion = client
+            .request(
+                "textDocument/completion",
+                json!({
+                    "textDocument": { "uri": uri },
+                    "position": { "line": 0, "character": 26 },
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!("assets", completion["items"][0]["label"]);
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_completion() {
+        let dir = suggestionsdir("completion");
+        let mut client = start(&dir).await;
+
+        client
+            .did_open(URI, "typescript", r#"export const folders = ["a"];"#)
+            .await;
+        let completion = client
+            .request(
+                "textDocument/completion",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "position": { "line": 0, "character": 26 },
+                }),
+            )
+            .await
+            .unwrap();
+        let labels: Vec<&str> = completion["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|item| item["label"].as_str())
+            .collect();
+        assert_eq!(vec!["assets"], labels);
+        assert_eq!(
+            json!({
+                "range": {
+                    "start": { "line": 0, "character": 25 },
+                    "end": { "line": 0, "character": 26 },
+                },
+                "newText": "assets",
+            }),
+            completion["items"][0]["textEdit"]
+        );
+
+        // Unterminated strings end on another line
+        let unterminated = "file:///project/unterminated.ts";
+        client
+            .did_open(
+                unterminated,
+                "typescript",
+                "export const folders = [\"a\n];",
+            )
+            .await;
+        let completion = client
+            .request(
+                "textDocument/completion",
+                json!({
+                    "textDocument": { "uri": unterminated },
+                    "position": { "line": 0, "character": 26 },
+                }),
+            )
+            .await;
+        assert!(completion.is_ok());
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_completion_resolve() {
+        let dir = suggestionsdir("completion-resolve");
+        fs::write(
+            dir.join("assets").join("README.md"),
+            "# Assets\n\nImages and **icons**\nof the app.\n\nMore details.\n",
+        )
+        .unwrap();
+        let mut client = start(&dir).await;
+
+        client
+            .did_open(URI, "typescript", r#"export const folders = ["a", "f"];"#)
+            .await;
+        let completion = client
+            .request(
+                "textDocument/completion",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "position": { "line": 0, "character": 26 },
+                }),
+            )
+            .await
+            .unwrap();
+        let item = client
+            .request("completionItem/resolve", completion["items"][0].clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            json!("Images and icons\nof the app."),
+            item["documentation"]
+        );
+
+        let completion = client
+            .request(
+                "textDocument/completion",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "position": { "line": 0, "character": 31 },
+                }),
+            )
+            .await
+            .unwrap();
+        let item = client
+            .request("completionItem/resolve", completion["items"][0].clone())
+            .await
+            .unwrap();
+        assert_eq!("fonts", item["label"]);
+        assert_eq!(Value::Null, item["documentation"]);
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_completion_skips_listed() {
+        let dir = suggestionsdir("completion-listed");
+        let mut client = start(&dir).await;
+
+        client
+            .did_open(
+                URI,
+                "typescript",
+                r#"export const folders = ["assets", ""];"#,
+            )
+            .await;
+        let completion = client
+            .request(
+                "textDocument/completion",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "position": { "line": 0, "character": 35 },
+                }),
+            )
+            .await
+            .unwrap();
+        let labels: Vec<&str> = completion["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|item| item["label"].as_str())
+            .collect();
+        assert_eq!(vec!["fonts"], labels);
+
+        client.shutdown().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,242 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Custom validation rules, e.g. loaded from a WASM plugin.
+pub trait Validator: Send + Sync {
+    /// Name shown with the diagnostics of this validator.
+    fn name(&self) -> &str;
+
+    /// Additional valid names, on top of those of the provider.
+    fn names(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// The problem with `entry`, `None` if it is valid.
+    fn validate(&self, entry: &str) -> Option<String>;
+
+    /// Reorder the quick fix `suggestions` for the invalid `entry`, best first.
+    fn rank(&self, _entry: &str, suggestions: Vec<String>) -> Vec<String> {
+        suggestions
+    }
+}
+
+/// Load the WASM validator plugins at `paths`, relative ones are resolved against `base`.
+pub fn load_plugins(paths: &[PathBuf], base: &Path) -> Result<Vec<Arc<dyn Validator>>, String> {
+    paths
+        .iter()
+        .map(|path| {
+            let path = base.join(path);
+            load_plugin(&path)
+                .map_err(|err| format!("Failed to load plugin {}: {}", path.display(), err))
+        })
+        .collect()
+}
+
+#[cfg(feature = "plugins")]
+fn load_plugin(path: &Path) -> Result<Arc<dyn Validator>, String> {
+    Ok(Arc::new(wasm::WasmValidator::load(path)?))
+}
+
+#[cfg(not(feature = "plugins"))]
+fn load_plugin(_path: &Path) -> Result<Arc<dyn Validator>, String> {
+    Err("the server was built without the plugins feature".into())
+}
+
+/// Validators implemented as WebAssembly modules.
+///
+/// A module exports its `memory` and `alloc(len: u32) -> u32`, which returns a buffer the host
+/// writes UTF-8 arguments to. Results are returned as `u64` with the pointer in the upper and
+/// the length in the lower 32 bits, 0 meaning no result:
+///
+/// - `validate(ptr, len) -> u64`: the problem with the entry, 0 if it is valid
+/// - `names() -> u64`, optional: newline separated additional valid names
+/// - `rank(ptr, len) -> u64`, optional: takes the entry followed by the suggestions and returns
+///   the reordered suggestions, all newline separated
+#[cfg(feature = "plugins")]
+mod wasm {
+    use super::Validator;
+    use std::path::Path;
+    use std::sync::Mutex;
+    use wasmtime::{Engine, Instance, Memory, Module, Store, TypedFunc};
+
+    struct Plugin {
+        store: Store<()>,
+        memory: Memory,
+        alloc: TypedFunc<u32, u32>,
+        validate: TypedFunc<(u32, u32), u64>,
+        names: Option<TypedFunc<(), u64>>,
+        rank: Option<TypedFunc<(u32, u32), u64>>,
+    }
+
+    impl Plugin {
+        fn write(&mut self, text: &str) -> Result<(u32, u32), String> {
+            let len = text.len() as u32;
+            let ptr = self
+                .alloc
+                .call(&mut self.store, len)
+                .map_err(|err| err.to_string())?;
+            self.memory
+                .write(&mut self.store, ptr as usize, text.as_bytes())
+                .map_err(|err| err.to_string())?;
+            Ok((ptr, len))
+        }
+
+        fn read(&self, result: u64) -> Result<Option<String>, String> {
+            if result == 0 {
+                return Ok(None);
+            }
+            let (ptr, len) = ((result >> 32) as usize, (result & 0xffff_ffff) as usize);
+            let mut buffer = vec![0; len];
+            self.memory
+                .read(&self.store, ptr, &mut buffer)
+                .map_err(|err| err.to_string())?;
+            String::from_utf8(buffer)
+                .map(Some)
+                .map_err(|err| err.to_string())
+        }
+
+        fn call(
+            &mut self,
+            function: TypedFunc<(u32, u32), u64>,
+            argument: &str,
+        ) -> Result<Option<String>, String> {
+            let argument = self.write(argument)?;
+            let result = function
+                .call(&mut self.store, argument)
+                .map_err(|err| err.to_string())?;
+            self.read(result)
+        }
+    }
+
+    pub struct WasmValidator {
+        name: String,
+        plugin: Mutex<Plugin>,
+    }
+
+    impl WasmValidator {
+        pub fn load(path: &Path) -> Result<Self, String> {
+            let engine = Engine::default();
+            let module = Module::from_file(&engine, path).map_err(|err| err.to_string())?;
+            let mut store = Store::new(&engine, ());
+            let instance =
+                Instance::new(&mut store, &module, &[]).map_err(|err| err.to_string())?;
+
+            let memory = instance
+                .get_memory(&mut store, "memory")
+                .ok_or("missing memory export")?;
+            let alloc = instance
+                .get_typed_func(&mut store, "alloc")
+                .map_err(|err| err.to_string())?;
+            let validate = instance
+                .get_typed_func(&mut store, "validate")
+                .map_err(|err| err.to_string())?;
+            let names = instance.get_typed_func(&mut store, "names").ok();
+            let rank = instance.get_typed_func(&mut store, "rank").ok();
+
+            Ok(Self {
+                name: path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                plugin: Mutex::new(Plugin {
+                    store,
+                    memory,
+                    alloc,
+                    validate,
+                    names,
+                    rank,
+                }),
+            })
+        }
+    }
+
+    impl Validator for WasmValidator {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn names(&self) -> Vec<String> {
+            let mut plugin = match self.plugin.lock() {
+                Ok(plugin) => plugin,
+                Err(_) => return vec![],
+            };
+            let names = match plugin.names.clone() {
+                Some(names) => names,
+                None => return vec![],
+            };
+
+            let result = names.call(&mut plugin.store, ()).unwrap_or(0);
+            match plugin.read(result) {
+                Ok(Some(names)) => names.lines().map(String::from).collect(),
+                _ => vec![],
+            }
+        }
+
+        fn validate(&self, entry: &str) -> Option<String> {
+            let mut plugin = self.plugin.lock().ok()?;
+            let validate = plugin.validate.clone();
+            plugin
+                .call(validate, entry)
+                .unwrap_or_else(|err| Some(format!("plugin failed: {}", err)))
+        }
+
+        fn rank(&self, entry: &str, suggestions: Vec<String>) -> Vec<String> {
+            let mut plugin = match self.plugin.lock() {
+                Ok(plugin) => plugin,
+                Err(_) => return suggestions,
+            };
+            let rank = match plugin.rank.clone() {
+                Some(rank) => rank,
+                None => return suggestions,
+            };
+
+            let argument = std::iter::once(entry.to_string())
+                .chain(suggestions.iter().cloned())
+                .collect::<Vec<_>>()
+                .join("\n");
+            match plugin.call(rank, &argument) {
+                Ok(Some(ranked)) => ranked.lines().map(String::from).collect(),
+                _ => suggestions,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct KebabCase;
+
+    impl Validator for KebabCase {
+        fn name(&self) -> &str {
+            "kebab-case"
+        }
+
+        fn validate(&self, entry: &str) -> Option<String> {
+            entry
+                .contains('_')
+                .then(|| format!("'{}' is not kebab-case", entry))
+        }
+    }
+
+    #[test]
+    fn test_default_methods() {
+        let validator = KebabCase;
+
+        assert!(validator.names().is_empty());
+        assert_eq!(None, validator.validate("dir-a"));
+        assert!(validator.validate("dir_a").is_some());
+        assert_eq!(
+            vec!["b".to_string(), "a".to_string()],
+            validator.rank("c", vec!["b".into(), "a".into()])
+        );
+    }
+
+    #[cfg(not(feature = "plugins"))]
+    #[test]
+    fn test_load_plugins_without_feature() {
+        assert!(load_plugins(&[], Path::new(".")).unwrap().is_empty());
+        assert!(load_plugins(&["a.wasm".into()], Path::new(".")).is_err());
+    }
+}
@@ -0,0 +1,226 @@
+//! Entry point for running the server in a browser, e.g. in a web worker of vscode.dev.
+//!
+//! The host passes every JSON-RPC message it receives via `postMessage` to
+//! [`WasmServer::handle`] and posts the returned messages back, see `wasm/worker.js`. There is
+//! no filesystem, so the valid folder names are passed to the constructor or as
+//! `initializationOptions.folders`.
+
+use crate::config::Config;
+use crate::diagnostics::DiagnosticEngine;
+use crate::edits;
+use crate::i18n::Locale;
+use crate::index::SuggestionIndex;
+use crate::parser::LspParser;
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionItemTag, CompletionOptions, CompletionParams,
+    Diagnostic, DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    PublishDiagnosticsParams, ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind,
+    Url,
+};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use wasm_bindgen::prelude::*;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const PARSE_ERROR: i64 = -32700;
+
+/// Synchronous language server validating documents against a list of folder names.
+#[wasm_bindgen]
+pub struct WasmServer {
+    varname: String,
+    index: SuggestionIndex,
+    documents: HashMap<Url, String>,
+    config: Config,
+    locale: Locale,
+}
+
+#[wasm_bindgen]
+impl WasmServer {
+    /// Server validating arrays named `varname` against `folders`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(varname: String, folders: Vec<String>) -> Self {
+        Self {
+            varname,
+            index: SuggestionIndex::build(folders, false),
+            documents: HashMap::new(),
+            config: Config::default(),
+            locale: Locale::default(),
+        }
+    }
+
+    /// Handle one JSON-RPC message, returns a JSON array of the messages to post back.
+    pub fn handle(&mut self, message: &str) -> String {
+        let outgoing = match serde_json::from_str::<Value>(message) {
+            Ok(message) => self.dispatch(&message),
+            Err(err) => vec![error(Value::Null, PARSE_ERROR, &err.to_string())],
+        };
+        Value::Array(outgoing).to_string()
+    }
+}
+
+impl WasmServer {
+    fn dispatch(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => return self.notification(method, params),
+        };
+
+        match self.request(method, params) {
+            Ok(result) => vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })],
+            Err((code, message)) => vec![error(id, code, &message)],
+        }
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Result<Value, (i64, String)> {
+        match method {
+            "initialize" => {
+                if let Some(folders) = params["initializationOptions"]["folders"].as_array() {
+                    let folders = folders.iter().filter_map(|f| f.as_str().map(String::from));
+                    self.index = SuggestionIndex::build(folders, false);
+                }
+                if let Some(tag) = params["locale"].as_str() {
+                    self.locale = Locale::from_tag(tag);
+                }
+
+                let capabilities = ServerCapabilities {
+                    text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                        TextDocumentSyncKind::FULL,
+                    )),
+                    completion_provider: Some(CompletionOptions::default()),
+                    ..ServerCapabilities::default()
+                };
+                Ok(json!({
+                    "capabilities": capabilities,
+                    "serverInfo": { "name": "tsm-language-server" },
+                }))
+            }
+            "shutdown" => Ok(Value::Null),
+            "textDocument/completion" => {
+                let params: CompletionParams = parse(params)?;
+                Ok(json!(self.completions(params)))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("Unhandled method {}", method))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: Value) -> Vec<Value> {
+        let uri = match method {
+            "textDocument/didOpen" => parse(params).ok().map(|p: DidOpenTextDocumentParams| {
+                self.documents
+                    .insert(p.text_document.uri.clone(), p.text_document.text);
+                p.text_document.uri
+            }),
+            "textDocument/didChange" => {
+                parse(params)
+                    .ok()
+                    .map(|mut p: DidChangeTextDocumentParams| {
+                        if let Some(change) = p.content_changes.pop() {
+                            self.documents
+                                .insert(p.text_document.uri.clone(), change.text);
+                        }
+                        p.text_document.uri
+                    })
+            }
+            "textDocument/didClose" => parse(params).ok().map(|p: DidCloseTextDocumentParams| {
+                self.documents.remove(&p.text_document.uri);
+                p.text_document.uri
+            }),
+            _ => None,
+        };
+
+        uri.map(|uri| self.publish_diagnostics(uri))
+            .into_iter()
+            .collect()
+    }
+
+    fn diagnostics(&self, source_code: &str) -> Vec<Diagnostic> {
+        let engine = DiagnosticEngine {
+            varname: &self.varname,
+            suggestionsdir: "initializationOptions.folders",
+            locale: self.locale,
+            config: &self.config,
+            validators: &[],
+        };
+        engine.diagnostics_with_names(
+            source_code,
+            |name| self.index.contains(name),
+            |_| false,
+            || self.index.names().map(String::from).collect(),
+        )
+    }
+
+    /// Diagnostics of the document, empty ones if it was closed.
+    fn publish_diagnostics(&self, uri: Url) -> Value {
+        let diagnostics = self
+            .documents
+            .get(&uri)
+            .map(|text| self.diagnostics(text))
+            .unwrap_or_default();
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": PublishDiagnosticsParams::new(uri, diagnostics, None),
+        })
+    }
+
+    /// Folder names starting with the text between the opening quote and the cursor.
+    fn completions(&self, params: CompletionParams) -> Vec<CompletionItem> {
+        let position = params.text_document_position.position;
+        let content = match self
+            .documents
+            .get(&params.text_document_position.text_document.uri)
+        {
+            Some(content) => content,
+            None => return vec![],
+        };
+
+        let items = LspParser::parse_code(content, &self.varname);
+        // The syntax tree counts columns in bytes, the position in UTF-16 code units
+        let column = edits::byte_column(content, position);
+        items
+            .iter()
+            .find(|item| {
+                item.range.start_point.row == position.line as usize
+                    && item.range.start_point.column < column
+                    && item.range.end_point.column > column
+            })
+            .map(|item| {
+                let typed = column - item.range.start_point.column - 1;
+                let prefix = item.text.get(..typed).unwrap_or(item.text);
+                let listed: HashSet<&str> = items
+                    .iter()
+                    .filter(|other| other.range != item.range)
+                    .map(|other| other.text)
+                    .collect();
+                self.index
+                    .prefix(prefix)
+                    .filter(|name| self.config.complete_listed || !listed.contains(name))
+                    .map(|name| CompletionItem {
+                        label: name.to_string(),
+                        kind: Some(CompletionItemKind::FOLDER),
+                        tags: listed
+                            .contains(name)
+                            .then(|| vec![CompletionItemTag::DEPRECATED]),
+                        ..CompletionItem::default()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, (i64, String)> {
+    serde_json::from_value(params).map_err(|err| (INVALID_PARAMS, err.to_string()))
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
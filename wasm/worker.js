@@ -0,0 +1,12 @@
+// Web worker running the language server, built with
+// wasm-pack build --target web --no-default-features --features wasm
+import init, { WasmServer } from "../pkg/tsm_language_server.js";
+
+await init();
+const server = new WasmServer("folders", []);
+
+self.onmessage = (event) => {
+  for (const message of JSON.parse(server.handle(JSON.stringify(event.data)))) {
+    self.postMessage(message);
+  }
+};